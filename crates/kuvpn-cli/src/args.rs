@@ -1,4 +1,5 @@
-use clap::{Parser, ValueEnum};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Parser, Subcommand, ValueEnum};
 
 #[derive(Debug, ValueEnum, Clone)]
 pub enum LogLevel {
@@ -42,6 +43,26 @@ pub enum LoginMode {
     Manual,
 }
 
+impl From<kuvpn::LoginMode> for LoginMode {
+    fn from(mode: kuvpn::LoginMode) -> Self {
+        match mode {
+            kuvpn::LoginMode::FullAuto => LoginMode::FullAuto,
+            kuvpn::LoginMode::Visual => LoginMode::Visual,
+            kuvpn::LoginMode::Manual => LoginMode::Manual,
+        }
+    }
+}
+
+impl From<LoginMode> for kuvpn::LoginMode {
+    fn from(mode: LoginMode) -> Self {
+        match mode {
+            LoginMode::FullAuto => kuvpn::LoginMode::FullAuto,
+            LoginMode::Visual => kuvpn::LoginMode::Visual,
+            LoginMode::Manual => kuvpn::LoginMode::Manual,
+        }
+    }
+}
+
 impl LoginMode {
     /// Whether the browser should run headlessly.
     pub fn headless(&self) -> bool {
@@ -58,10 +79,27 @@ impl LoginMode {
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
+    /// Take the connection settings from a saved profile (see `kuvpn profile
+    /// list`); flags given on the command line still take precedence
+    #[arg(short, long)]
+    pub profile: Option<String>,
+
     /// Login mode: full-auto (default), visual, or manual
     #[arg(short, long, value_enum, default_value_t = LoginMode::FullAuto)]
     pub mode: LoginMode,
 
+    /// How a full-auto login is carried out: drive a headless browser, or
+    /// sign in over plain HTTP and fall back to the browser on pages it
+    /// does not know
+    #[arg(long, value_enum, default_value_t = CliLoginEngine::Browser)]
+    pub login_engine: CliLoginEngine,
+
+    /// Browser used for the login: the path to a Chromium-based browser or
+    /// the id of one KUVPN knows (see `kuvpn browser list`). Defaults to the
+    /// first one installed
+    #[arg(long)]
+    pub browser: Option<String>,
+
     /// The URL to the page where we will start logging in and looking for DSID
     #[arg(long, default_value = "https://vpn.ku.edu.tr")]
     pub url: String,
@@ -78,9 +116,10 @@ pub struct Args {
     #[arg(short, long, default_value_t = false)]
     pub dsid: bool,
 
-    /// Delete session information
-    #[arg(short, long, default_value_t = false)]
-    pub clean: bool,
+    /// Delete session information: gateway cookies only, the Microsoft
+    /// sign-in only, the browser cache, or everything (the default)
+    #[arg(short, long, value_enum, num_args = 0..=1, default_missing_value = "all")]
+    pub clean: Option<CliWipeScope>,
 
     /// Command to run openconnect with (e.g., sudo, pkexec, or a custom script)
     #[arg(long)]
@@ -113,6 +152,500 @@ pub struct Args {
     /// Only used when --tunnel-mode manual is set.
     #[arg(long)]
     pub vpnc_script: Option<String>,
+
+    /// MFA methods to choose, in order of preference, when Microsoft offers
+    /// a choice (comma-separated, e.g. "sms,voice").
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [CliMfaMethod::Push, CliMfaMethod::Sms, CliMfaMethod::FirstAvailable]
+    )]
+    pub mfa_preference: Vec<CliMfaMethod>,
+
+    /// How many times to re-send an unanswered push notification or phone
+    /// call before giving up
+    #[arg(long, default_value_t = kuvpn::DEFAULT_MAX_PUSH_RESENDS)]
+    pub max_push_resends: u32,
+
+    /// Incorrect passwords within the cooldown after which the password is
+    /// no longer submitted without confirmation (0 disables the guard)
+    #[arg(long, default_value_t = kuvpn::DEFAULT_LOCKOUT_THRESHOLD)]
+    pub lockout_threshold: u32,
+
+    /// Minutes an incorrect password counts towards --lockout-threshold
+    #[arg(long, default_value_t = kuvpn::DEFAULT_LOCKOUT_COOLDOWN_MINS)]
+    pub lockout_cooldown: u64,
+
+    /// What to do with sessions already open on the gateway when it asks
+    /// before opening another one
+    #[arg(long, value_enum, default_value_t = CliSessionConflict::Ask)]
+    pub session_conflict: CliSessionConflict,
+
+    /// What Full Auto does when the login cannot finish without you: show
+    /// the browser window, fail with the original error, or ask first
+    #[arg(long, value_enum, default_value_t = CliVisibleFallback::Allow)]
+    pub visible_fallback: CliVisibleFallback,
+
+    /// Minutes after which the gateway expires a session. Learned from
+    /// connection history when not set.
+    #[arg(long)]
+    pub session_lifetime: Option<u64>,
+
+    /// Minutes before the session lifetime ends to warn (0 disables)
+    #[arg(long, default_value_t = kuvpn::DEFAULT_EXPIRY_WARNING_MINS)]
+    pub expiry_warning: u64,
+
+    /// Log in again in the background shortly before the session lifetime
+    /// ends and restart the tunnel with the fresh session
+    #[arg(long, default_value_t = false)]
+    pub renew_session: bool,
+
+    /// Launch a headless browser a few minutes before the session lifetime
+    /// ends so the next login starts faster, and close it after this many
+    /// minutes unused (0 disables)
+    #[arg(long, default_value_t = 0)]
+    pub prewarm: u64,
+
+    /// Shell command to run once the tunnel is up (KUVPN_EVENT and
+    /// KUVPN_INTERFACE are set in its environment)
+    #[arg(long)]
+    pub on_connect: Option<String>,
+
+    /// Shell command to run after the tunnel has gone down
+    #[arg(long)]
+    pub on_disconnect: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Subcommands; without one KUVPN connects.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Inspect the saved browser session
+    Session {
+        #[command(subcommand)]
+        action: SessionCommand,
+    },
+    /// Manage saved connection profiles
+    Profile {
+        #[command(subcommand)]
+        action: ProfileCommand,
+    },
+    /// Find the browser used for the login
+    Browser {
+        #[command(subcommand)]
+        action: BrowserCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SessionCommand {
+    /// Show whether the saved Microsoft sign-in is still valid (so the next
+    /// login needs no MFA), and when a DSID and MFA were last obtained
+    Info,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProfileCommand {
+    /// List the saved profiles
+    List,
+    /// Save the connection settings given by the other flags as a profile,
+    /// replacing any profile with the same name
+    Save { name: String },
+    /// Delete a saved profile
+    Remove { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BrowserCommand {
+    /// List the Chromium-based browsers found on this system and show which
+    /// one the login uses
+    List,
+}
+
+/// MFA method choices for the CLI (mirrors `kuvpn::MfaMethod`).
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CliMfaMethod {
+    /// Approve a push notification in the Authenticator app.
+    Push,
+    /// Type a code from an authenticator app.
+    Totp,
+    /// Receive a code by text message.
+    Sms,
+    /// Answer a phone call.
+    Voice,
+    /// Whatever Microsoft lists first.
+    FirstAvailable,
+}
+
+impl From<CliMfaMethod> for kuvpn::MfaMethod {
+    fn from(method: CliMfaMethod) -> Self {
+        match method {
+            CliMfaMethod::Push => kuvpn::MfaMethod::Push,
+            CliMfaMethod::Totp => kuvpn::MfaMethod::Totp,
+            CliMfaMethod::Sms => kuvpn::MfaMethod::Sms,
+            CliMfaMethod::Voice => kuvpn::MfaMethod::Voice,
+            CliMfaMethod::FirstAvailable => kuvpn::MfaMethod::FirstAvailable,
+        }
+    }
+}
+
+impl From<kuvpn::MfaMethod> for CliMfaMethod {
+    fn from(method: kuvpn::MfaMethod) -> Self {
+        match method {
+            kuvpn::MfaMethod::Push => CliMfaMethod::Push,
+            kuvpn::MfaMethod::Totp => CliMfaMethod::Totp,
+            kuvpn::MfaMethod::Sms => CliMfaMethod::Sms,
+            kuvpn::MfaMethod::Voice => CliMfaMethod::Voice,
+            kuvpn::MfaMethod::FirstAvailable => CliMfaMethod::FirstAvailable,
+        }
+    }
+}
+
+/// What `--clean` removes (mirrors `kuvpn::WipeScope`).
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CliWipeScope {
+    /// Gateway cookies (DSID); keeps the Microsoft sign-in.
+    Gateway,
+    /// Microsoft sign-in cookies; the next login needs MFA.
+    Sso,
+    /// The browser cache only.
+    Cache,
+    /// The whole saved browser profile.
+    All,
+}
+
+impl From<CliWipeScope> for kuvpn::WipeScope {
+    fn from(scope: CliWipeScope) -> Self {
+        match scope {
+            CliWipeScope::Gateway => kuvpn::WipeScope::Gateway,
+            CliWipeScope::Sso => kuvpn::WipeScope::Sso,
+            CliWipeScope::Cache => kuvpn::WipeScope::Cache,
+            CliWipeScope::All => kuvpn::WipeScope::All,
+        }
+    }
+}
+
+/// Login engine choices for the CLI (mirrors `kuvpn::LoginEngine`).
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CliLoginEngine {
+    /// Drive a headless Chrome/Chromium.
+    Browser,
+    /// Plain HTTP requests, with the browser as the fallback.
+    Http,
+}
+
+impl From<CliLoginEngine> for kuvpn::LoginEngine {
+    fn from(engine: CliLoginEngine) -> Self {
+        match engine {
+            CliLoginEngine::Browser => kuvpn::LoginEngine::Browser,
+            CliLoginEngine::Http => kuvpn::LoginEngine::Http,
+        }
+    }
+}
+
+impl From<kuvpn::LoginEngine> for CliLoginEngine {
+    fn from(engine: kuvpn::LoginEngine) -> Self {
+        match engine {
+            kuvpn::LoginEngine::Browser => CliLoginEngine::Browser,
+            kuvpn::LoginEngine::Http => CliLoginEngine::Http,
+        }
+    }
+}
+
+/// Session-conflict choices for the CLI (mirrors `kuvpn::SessionConflictPolicy`).
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CliSessionConflict {
+    /// Ask which listed session to end.
+    Ask,
+    /// End every listed session.
+    CloseAll,
+    /// Keep the listed sessions and open another one.
+    KeepAll,
+}
+
+impl From<CliSessionConflict> for kuvpn::SessionConflictPolicy {
+    fn from(policy: CliSessionConflict) -> Self {
+        match policy {
+            CliSessionConflict::Ask => kuvpn::SessionConflictPolicy::Ask,
+            CliSessionConflict::CloseAll => kuvpn::SessionConflictPolicy::CloseAll,
+            CliSessionConflict::KeepAll => kuvpn::SessionConflictPolicy::KeepAll,
+        }
+    }
+}
+
+impl From<kuvpn::SessionConflictPolicy> for CliSessionConflict {
+    fn from(policy: kuvpn::SessionConflictPolicy) -> Self {
+        match policy {
+            kuvpn::SessionConflictPolicy::Ask => CliSessionConflict::Ask,
+            kuvpn::SessionConflictPolicy::CloseAll => CliSessionConflict::CloseAll,
+            kuvpn::SessionConflictPolicy::KeepAll => CliSessionConflict::KeepAll,
+        }
+    }
+}
+
+/// Visible-fallback choices for the CLI (mirrors `kuvpn::VisibleFallback`).
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CliVisibleFallback {
+    /// Show the browser window and finish there.
+    Allow,
+    /// Fail the login with the error that stopped it.
+    Deny,
+    /// Ask whether to show the browser window.
+    Ask,
+}
+
+impl From<CliVisibleFallback> for kuvpn::VisibleFallback {
+    fn from(policy: CliVisibleFallback) -> Self {
+        match policy {
+            CliVisibleFallback::Allow => kuvpn::VisibleFallback::Allow,
+            CliVisibleFallback::Deny => kuvpn::VisibleFallback::Deny,
+            CliVisibleFallback::Ask => kuvpn::VisibleFallback::Ask,
+        }
+    }
+}
+
+impl From<kuvpn::VisibleFallback> for CliVisibleFallback {
+    fn from(policy: kuvpn::VisibleFallback) -> Self {
+        match policy {
+            kuvpn::VisibleFallback::Allow => CliVisibleFallback::Allow,
+            kuvpn::VisibleFallback::Deny => CliVisibleFallback::Deny,
+            kuvpn::VisibleFallback::Ask => CliVisibleFallback::Ask,
+        }
+    }
+}
+
+impl Args {
+    /// Fills the connection settings from `profile`, except those given
+    /// explicitly on the command line.  Optional settings the profile leaves
+    /// unset keep the value from the config file or environment.
+    pub fn apply_profile(&mut self, profile: &kuvpn::Profile, matches: &ArgMatches) {
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        if !given("url") {
+            self.url = profile.url.clone();
+        }
+        if !given("domain") {
+            self.domain = profile.domain.clone();
+        }
+        if !given("email") {
+            if let Some(v) = &profile.email {
+                self.email = Some(v.clone());
+            }
+        }
+        if !given("mode") {
+            self.mode = profile.login_mode.into();
+        }
+        if !given("tunnel_mode") {
+            self.tunnel_mode = profile.tunnel_mode.into();
+        }
+        if !given("vpnc_script") {
+            if let Some(v) = &profile.vpnc_script {
+                self.vpnc_script = Some(v.clone());
+            }
+        }
+        if !given("run_command") {
+            if let Some(v) = &profile.escalation_tool {
+                self.run_command = Some(v.clone());
+            }
+        }
+        if !given("interface_name") {
+            self.interface_name = profile.interface_name.clone();
+        }
+        if !given("on_connect") {
+            if let Some(v) = &profile.hooks.on_connect {
+                self.on_connect = Some(v.clone());
+            }
+        }
+        if !given("on_disconnect") {
+            if let Some(v) = &profile.hooks.on_disconnect {
+                self.on_disconnect = Some(v.clone());
+            }
+        }
+    }
+
+    /// Fills the settings set in `config` (the config file or the `KUVPN_*`
+    /// environment), except those given explicitly on the command line.
+    pub fn apply_config(&mut self, config: &kuvpn::Config, matches: &ArgMatches) {
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        macro_rules! take {
+            ($id:literal, $field:ident, $value:expr) => {
+                if !given($id) {
+                    if let Some(v) = $value {
+                        self.$field = v;
+                    }
+                }
+            };
+        }
+        take!("url", url, config.url.clone());
+        take!("domain", domain, config.domain.clone());
+        take!("email", email, config.email.clone().map(Some));
+        take!("mode", mode, config.login_mode.map(Into::into));
+        take!(
+            "login_engine",
+            login_engine,
+            config.login_engine.map(Into::into)
+        );
+        take!("browser", browser, config.browser.clone().map(Some));
+        take!(
+            "tunnel_mode",
+            tunnel_mode,
+            config.tunnel_mode.map(Into::into)
+        );
+        take!(
+            "vpnc_script",
+            vpnc_script,
+            config.vpnc_script.clone().map(Some)
+        );
+        take!(
+            "run_command",
+            run_command,
+            config.escalation_tool.clone().map(Some)
+        );
+        take!(
+            "openconnect_path",
+            openconnect_path,
+            config.openconnect_path.clone()
+        );
+        take!(
+            "interface_name",
+            interface_name,
+            config.interface_name.clone()
+        );
+        take!(
+            "mfa_preference",
+            mfa_preference,
+            config
+                .mfa_preference
+                .as_ref()
+                .map(|order| order.iter().map(|&m| m.into()).collect())
+        );
+        take!(
+            "max_push_resends",
+            max_push_resends,
+            config.max_push_resends
+        );
+        take!(
+            "lockout_threshold",
+            lockout_threshold,
+            config.lockout_threshold
+        );
+        take!(
+            "lockout_cooldown",
+            lockout_cooldown,
+            config.lockout_cooldown
+        );
+        take!(
+            "session_conflict",
+            session_conflict,
+            config.session_conflict.map(Into::into)
+        );
+        take!(
+            "visible_fallback",
+            visible_fallback,
+            config.visible_fallback.map(Into::into)
+        );
+        take!(
+            "session_lifetime",
+            session_lifetime,
+            config.session_lifetime.map(Some)
+        );
+        take!("expiry_warning", expiry_warning, config.expiry_warning);
+        take!("renew_session", renew_session, config.renew_session);
+        take!("prewarm", prewarm, config.prewarm);
+        take!(
+            "on_connect",
+            on_connect,
+            config.on_connect.clone().map(Some)
+        );
+        take!(
+            "on_disconnect",
+            on_disconnect,
+            config.on_disconnect.clone().map(Some)
+        );
+    }
+
+    /// The settings given explicitly on the command line, in config form.
+    pub fn command_line_config(&self, matches: &ArgMatches) -> kuvpn::Config {
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        let pick = |id: &str, value: Option<String>| value.filter(|_| given(id));
+        kuvpn::Config {
+            url: pick("url", Some(self.url.clone())),
+            domain: pick("domain", Some(self.domain.clone())),
+            email: pick("email", self.email.clone()),
+            login_mode: given("mode").then(|| self.mode.clone().into()),
+            login_engine: given("login_engine").then(|| self.login_engine.into()),
+            browser: pick("browser", self.browser.clone()),
+            tunnel_mode: given("tunnel_mode").then(|| self.tunnel_mode.clone().into()),
+            vpnc_script: pick("vpnc_script", self.vpnc_script.clone()),
+            escalation_tool: pick("run_command", self.run_command.clone()),
+            openconnect_path: pick("openconnect_path", Some(self.openconnect_path.clone())),
+            interface_name: pick("interface_name", Some(self.interface_name.clone())),
+            mfa_preference: given("mfa_preference").then(|| self.mfa_preference()),
+            max_push_resends: given("max_push_resends").then_some(self.max_push_resends),
+            lockout_threshold: given("lockout_threshold").then_some(self.lockout_threshold),
+            lockout_cooldown: given("lockout_cooldown").then_some(self.lockout_cooldown),
+            session_conflict: given("session_conflict").then(|| self.session_conflict.into()),
+            visible_fallback: given("visible_fallback").then(|| self.visible_fallback.into()),
+            session_lifetime: self.session_lifetime.filter(|_| given("session_lifetime")),
+            expiry_warning: given("expiry_warning").then_some(self.expiry_warning),
+            renew_session: given("renew_session").then_some(self.renew_session),
+            prewarm: given("prewarm").then_some(self.prewarm),
+            on_connect: pick("on_connect", self.on_connect.clone()),
+            on_disconnect: pick("on_disconnect", self.on_disconnect.clone()),
+            profile: None,
+        }
+    }
+
+    /// Builds the session settings from the flags.
+    pub fn session_config(&self) -> kuvpn::SessionConfig {
+        kuvpn::SessionConfig {
+            url: self.url.clone(),
+            domain: self.domain.clone(),
+            user_agent: "Mozilla/5.0".to_string(),
+            headless: self.mode.headless(),
+            no_auto_login: self.mode.no_auto_login(),
+            email: self.email.clone(),
+            openconnect_path: self.openconnect_path.clone(),
+            escalation_tool: self.run_command.clone(),
+            interface_name: self.interface_name.clone(),
+            tunnel_mode: match self.tunnel_mode {
+                CliTunnelMode::Full => kuvpn::TunnelMode::Full,
+                CliTunnelMode::Manual => kuvpn::TunnelMode::Manual(self.vpnc_script.clone()),
+            },
+            mfa_preference: self.mfa_preference(),
+            max_push_resends: self.max_push_resends,
+            lockout: self.lockout(),
+            session_conflict: self.session_conflict.into(),
+            visible_fallback: self.visible_fallback.into(),
+            login_engine: self.login_engine.into(),
+            max_session_lifetime: self
+                .session_lifetime
+                .map(|m| std::time::Duration::from_secs(m * 60)),
+            expiry_warning: std::time::Duration::from_secs(self.expiry_warning * 60),
+            renew_before_expiry: self.renew_session,
+            prewarm_idle: (self.prewarm > 0)
+                .then(|| std::time::Duration::from_secs(self.prewarm * 60)),
+            hooks: kuvpn::ConnectionHooks {
+                on_connect: self.on_connect.clone(),
+                on_disconnect: self.on_disconnect.clone(),
+            },
+        }
+    }
+
+    /// The MFA preference list in core-library form.
+    pub fn mfa_preference(&self) -> Vec<kuvpn::MfaMethod> {
+        self.mfa_preference.iter().map(|&m| m.into()).collect()
+    }
+
+    /// The failed-password guard settings in core-library form.
+    pub fn lockout(&self) -> kuvpn::LockoutPolicy {
+        kuvpn::LockoutPolicy {
+            threshold: self.lockout_threshold,
+            cooldown: std::time::Duration::from_secs(self.lockout_cooldown * 60),
+        }
+    }
 }
 
 /// Tunnel mode choices for the CLI (mirrors `kuvpn::TunnelMode`).
@@ -124,3 +657,125 @@ pub enum CliTunnelMode {
     /// Use a custom vpnc-script (supply path via --vpnc-script).
     Manual,
 }
+
+impl From<kuvpn::TunnelKind> for CliTunnelMode {
+    fn from(kind: kuvpn::TunnelKind) -> Self {
+        match kind {
+            kuvpn::TunnelKind::Full => CliTunnelMode::Full,
+            kuvpn::TunnelKind::Manual => CliTunnelMode::Manual,
+        }
+    }
+}
+
+impl From<CliTunnelMode> for kuvpn::TunnelKind {
+    fn from(mode: CliTunnelMode) -> Self {
+        match mode {
+            CliTunnelMode::Full => kuvpn::TunnelKind::Full,
+            CliTunnelMode::Manual => kuvpn::TunnelKind::Manual,
+        }
+    }
+}
+
+/// The command-line flag that sets `setting`.
+pub fn flag_for(setting: kuvpn::Setting) -> String {
+    match setting {
+        kuvpn::Setting::LoginMode => "--mode".to_string(),
+        kuvpn::Setting::EscalationTool => "--run-command".to_string(),
+        other => format!("--{}", other.key()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn parse(flags: &[&str]) -> (Args, ArgMatches) {
+        let argv = std::iter::once("kuvpn").chain(flags.iter().copied());
+        let matches = Args::command().get_matches_from(argv);
+        let args = Args::from_arg_matches(&matches).unwrap();
+        (args, matches)
+    }
+
+    #[test]
+    fn flags_beat_the_config() {
+        let (mut args, matches) = parse(&["--email", "flag@example.com"]);
+        let config = kuvpn::Config {
+            email: Some("file@example.com".into()),
+            domain: Some("vpn.example.com".into()),
+            max_push_resends: Some(5),
+            ..kuvpn::Config::default()
+        };
+        args.apply_config(&config, &matches);
+        assert_eq!(args.email.as_deref(), Some("flag@example.com"));
+        assert_eq!(args.domain, "vpn.example.com");
+        assert_eq!(args.max_push_resends, 5);
+    }
+
+    #[test]
+    fn layers_apply_in_order() {
+        let (mut args, matches) = parse(&[]);
+        let file = kuvpn::Config {
+            email: Some("file@example.com".into()),
+            vpnc_script: Some("/etc/vpnc/file-script".into()),
+            max_push_resends: Some(1),
+            ..kuvpn::Config::default()
+        };
+        let profile = kuvpn::Profile {
+            name: "lab".into(),
+            url: "https://lab.example.com".into(),
+            ..kuvpn::Profile::default()
+        };
+        let env = kuvpn::Config {
+            max_push_resends: Some(4),
+            ..kuvpn::Config::default()
+        };
+        args.apply_config(&file, &matches);
+        args.apply_profile(&profile, &matches);
+        args.apply_config(&env, &matches);
+
+        assert_eq!(args.url, "https://lab.example.com");
+        // The profile leaves these unset, so the file's values stay.
+        assert_eq!(args.email.as_deref(), Some("file@example.com"));
+        assert_eq!(args.vpnc_script.as_deref(), Some("/etc/vpnc/file-script"));
+        assert_eq!(args.max_push_resends, 4);
+    }
+
+    #[test]
+    fn profile_does_not_override_flags() {
+        let (mut args, matches) = parse(&["--url", "https://flag.example.com"]);
+        let profile = kuvpn::Profile {
+            url: "https://lab.example.com".into(),
+            email: Some("lab@example.com".into()),
+            ..kuvpn::Profile::default()
+        };
+        args.apply_profile(&profile, &matches);
+        assert_eq!(args.url, "https://flag.example.com");
+        assert_eq!(args.email.as_deref(), Some("lab@example.com"));
+    }
+
+    #[test]
+    fn command_line_config_has_only_given_flags() {
+        let (args, matches) = parse(&["--session-conflict", "keep-all", "--prewarm", "0"]);
+        let config = args.command_line_config(&matches);
+        assert_eq!(
+            config.keys(),
+            vec![kuvpn::Setting::SessionConflict, kuvpn::Setting::Prewarm]
+        );
+        assert_eq!(
+            config.session_conflict,
+            Some(kuvpn::SessionConflictPolicy::KeepAll)
+        );
+        assert_eq!(config.prewarm, Some(0));
+    }
+
+    #[test]
+    fn flag_names_match_config_keys() {
+        assert_eq!(flag_for(kuvpn::Setting::LoginMode), "--mode");
+        assert_eq!(flag_for(kuvpn::Setting::EscalationTool), "--run-command");
+        assert_eq!(
+            flag_for(kuvpn::Setting::VisibleFallback),
+            "--visible-fallback"
+        );
+    }
+}
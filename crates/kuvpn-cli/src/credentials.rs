@@ -17,12 +17,10 @@ fn read_masked_password(prompt: &str) -> String {
             Key::Enter => {
                 break;
             }
-            Key::Backspace => {
-                if !password.is_empty() {
-                    password.pop();
-                    let _ = term.clear_chars(1);
-                    let _ = term.flush();
-                }
+            Key::Backspace if !password.is_empty() => {
+                password.pop();
+                let _ = term.clear_chars(1);
+                let _ = term.flush();
             }
             Key::Char(c) => {
                 if c == '\x03' {
@@ -67,6 +65,15 @@ impl CredentialsProvider for CliCredentialsProvider {
         Some(result)
     }
 
+    fn request_choice(&self, msg: &str, options: &[String]) -> Option<usize> {
+        self.spinner.finish_and_clear();
+        let result = kuvpn::utils::prompt_choice(msg, options);
+        // Clean up the prompt line after selection
+        let term = Term::stderr();
+        let _ = term.clear_last_lines(1);
+        result
+    }
+
     fn on_mfa_push(&self, code: &str) {
         self.spinner.finish_and_clear();
         let bold = Style::new().bold();
@@ -78,6 +85,45 @@ impl CredentialsProvider for CliCredentialsProvider {
         );
     }
 
+    fn on_mfa_call(&self) {
+        self.spinner.finish_and_clear();
+        let bold = Style::new().bold();
+        eprintln!(
+            "{} Answer the phone call to approve the sign-in",
+            bold.apply_to(">>"),
+        );
+    }
+
+    fn on_mfa_resend(&self, attempt: u32, max: u32) {
+        self.spinner.finish_and_clear();
+        let yellow = Style::new().yellow().bold();
+        eprintln!(
+            "  {} No response — sending another request ({}/{})",
+            yellow.apply_to("~"),
+            attempt,
+            max
+        );
+    }
+
+    fn on_browser_action(&self, page: &str) {
+        self.spinner.finish_and_clear();
+        let bold = Style::new().bold();
+        eprintln!(
+            "{} {} needs your input — finish it in the browser window",
+            bold.apply_to(">>"),
+            page
+        );
+    }
+
+    fn confirm_password_retry(&self, failures: u32, retry_after: std::time::Duration) -> bool {
+        self.spinner.finish_and_clear();
+        let result = kuvpn::utils::prompt_password_retry(failures, retry_after);
+        // Clean up the prompt line after the answer
+        let term = Term::stderr();
+        let _ = term.clear_last_lines(1);
+        result
+    }
+
     fn on_mfa_complete(&self) {
         let term = Term::stderr();
         let _ = term.clear_last_lines(1);
@@ -6,13 +6,12 @@ mod args;
 mod credentials;
 
 use args::Args;
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 use console::Style;
 use credentials::CliCredentialsProvider;
 use indicatif::{ProgressBar, ProgressStyle};
 use kuvpn::{
-    init_logger, run_login_and_get_dsid, ConnectionStatus, LoginConfig, ParsedLog, SessionConfig,
-    VpnSession,
+    init_logger, run_login_and_get_dsid, ConnectionStatus, LoginConfig, ParsedLog, VpnSession,
 };
 use std::process::ExitCode;
 use std::sync::Arc;
@@ -111,7 +110,11 @@ fn handle_log(
             );
             return;
         }
-        _ if msg.starts_with("Reconnecting") => {
+        _ if msg.starts_with("Session expires in") => {
+            eprintln!("  {} {}", styles.yellow.apply_to("!"), msg);
+            return;
+        }
+        _ if msg.starts_with("Reconnecting") || msg.starts_with("Restarting the tunnel") => {
             *connection_start = None;
             clear_spinner(spinner, spinner_active);
             eprintln!("  {} {}", styles.yellow.apply_to("~"), msg);
@@ -187,11 +190,71 @@ fn main() -> ExitCode {
         return ExitCode::from(code as u8);
     }
 
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
     init_logger(args.log.clone().into());
 
     let styles = CliStyles::new();
 
+    // Precedence: built-in defaults < policy defaults < config file < profile
+    // < KUVPN_* < flags, with settings locked by the policy overriding all.
+    let policy = match kuvpn::Policy::load() {
+        Ok(policy) => policy,
+        Err(e) => {
+            eprintln!("  {} Invalid policy file: {}", styles.red.apply_to("✗"), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let conflicts = policy.conflicts(&args.command_line_config(&matches));
+    if !conflicts.is_empty() {
+        for key in conflicts {
+            eprintln!(
+                "  {} {} is locked by the system policy ({}: {})",
+                styles.red.apply_to("✗"),
+                args::flag_for(key),
+                kuvpn::Policy::path().display(),
+                policy.locked_value(key)
+            );
+        }
+        return ExitCode::FAILURE;
+    }
+    args.apply_config(&policy.defaults, &matches);
+
+    let file_config = match kuvpn::Config::from_file() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("  {} Invalid config file: {}", styles.red.apply_to("✗"), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let env_config = kuvpn::Config::from_env();
+    args.apply_config(&file_config, &matches);
+
+    let profile_name = args
+        .profile
+        .clone()
+        .or_else(|| env_config.profile.clone())
+        .or_else(|| file_config.profile.clone())
+        .or_else(|| policy.defaults.profile.clone());
+    if let Some(name) = &profile_name {
+        let store = kuvpn::ProfileStore::load().unwrap_or_default();
+        match store.get(name) {
+            Some(profile) => args.apply_profile(profile, &matches),
+            None => {
+                eprintln!(
+                    "  {} No profile named '{}' (see `kuvpn profile list`)",
+                    styles.red.apply_to("✗"),
+                    name
+                );
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    args.apply_config(&env_config, &matches);
+    args.apply_config(&policy.locked_config(), &matches);
+    kuvpn::set_browser_choice(args.browser.clone());
+    kuvpn::set_browser_profile(profile_name);
+
     if !args.dsid {
         eprintln!(
             "{} {}",
@@ -206,21 +269,42 @@ fn main() -> ExitCode {
         eprintln!("  {} {}", styles.red.apply_to("✗"), e);
         return ExitCode::FAILURE;
     }
+    kuvpn::sweep_orphaned_browsers();
 
     if args.history {
         return print_history(&styles);
     }
 
-    if args.clean {
-        return match kuvpn::utils::wipe_user_data_dir() {
+    if let Some(args::Command::Session {
+        action: args::SessionCommand::Info,
+    }) = args.command
+    {
+        return print_session_info(&args, &styles);
+    }
+
+    if let Some(args::Command::Profile { action }) = &args.command {
+        return run_profile_command(action, &args, &styles);
+    }
+
+    if let Some(args::Command::Browser {
+        action: args::BrowserCommand::List,
+    }) = args.command
+    {
+        return print_browsers(&args, &styles);
+    }
+
+    if let Some(scope) = args.clean {
+        let scope: kuvpn::WipeScope = scope.into();
+        return match kuvpn::wipe_session_data(scope, &args.domain) {
             Ok(_) => {
-                eprintln!("  {} Session data wiped", styles.green.apply_to("✓"));
+                eprintln!("  {} Cleared {}", styles.green.apply_to("✓"), scope);
                 ExitCode::SUCCESS
             }
             Err(e) => {
                 eprintln!(
-                    "  {} Failed to wipe session data: {}",
+                    "  {} Failed to clear {}: {}",
                     styles.red.apply_to("✗"),
+                    scope,
                     e
                 );
                 ExitCode::FAILURE
@@ -232,7 +316,10 @@ fn main() -> ExitCode {
         return run_get_dsid(&args, &styles);
     }
 
-    run_vpn_session(&args, &styles)
+    let code = run_vpn_session(&args, &styles);
+    // A browser pre-warmed near the end of the session must not outlive us.
+    kuvpn::discard_warm_browser();
+    code
 }
 
 fn print_history(styles: &CliStyles) -> ExitCode {
@@ -258,6 +345,12 @@ fn print_history(styles: &CliStyles) -> ExitCode {
                     }
                     kuvpn::EventKind::Cancelled => styles.dim.apply_to("Cancelled   ").to_string(),
                     kuvpn::EventKind::Error => styles.red.apply_to("Error       ").to_string(),
+                    kuvpn::EventKind::BrowserFallback => {
+                        styles.yellow.apply_to("Fallback    ").to_string()
+                    }
+                    kuvpn::EventKind::BrowserCrash => {
+                        styles.yellow.apply_to("Relaunched  ").to_string()
+                    }
                 };
                 let dur = event
                     .format_duration_display()
@@ -289,6 +382,175 @@ fn print_history(styles: &CliStyles) -> ExitCode {
     }
 }
 
+fn run_profile_command(action: &args::ProfileCommand, args: &Args, styles: &CliStyles) -> ExitCode {
+    let mut store = kuvpn::ProfileStore::load().unwrap_or_default();
+    let done = match action {
+        args::ProfileCommand::List => {
+            if store.profiles.is_empty() {
+                eprintln!("  {} No saved profiles.", styles.dim.apply_to("●"));
+            }
+            for profile in &store.profiles {
+                eprintln!(
+                    "  {} {}",
+                    styles.bold.apply_to(&profile.name),
+                    styles.dim.apply_to(format!(
+                        "{} · {:?} login · {:?} tunnel{}",
+                        profile.url,
+                        profile.login_mode,
+                        profile.tunnel_mode,
+                        profile
+                            .email
+                            .as_deref()
+                            .map(|e| format!(" · {}", e))
+                            .unwrap_or_default()
+                    )),
+                );
+            }
+            return ExitCode::SUCCESS;
+        }
+        args::ProfileCommand::Save { name } => {
+            store.upsert(kuvpn::Profile::from_session_config(
+                name.clone(),
+                &args.session_config(),
+            ));
+            format!("Saved profile '{}'", name)
+        }
+        args::ProfileCommand::Remove { name } => {
+            if !store.remove(name) {
+                eprintln!("  {} No profile named '{}'", styles.red.apply_to("✗"), name);
+                return ExitCode::FAILURE;
+            }
+            if let Err(e) = kuvpn::remove_browser_profile(name) {
+                eprintln!(
+                    "  {} Failed to remove the browser data of '{}': {}",
+                    styles.yellow.apply_to("!"),
+                    name,
+                    e
+                );
+            }
+            format!("Removed profile '{}'", name)
+        }
+    };
+    match store.save() {
+        Ok(()) => {
+            eprintln!("  {} {}", styles.green.apply_to("✓"), done);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!(
+                "  {} Failed to save profiles: {}",
+                styles.red.apply_to("✗"),
+                e
+            );
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_browsers(args: &Args, styles: &CliStyles) -> ExitCode {
+    let installed = kuvpn::discover_browsers();
+    let chosen = kuvpn::resolve_browser(args.browser.as_deref());
+    if installed.is_empty() && chosen.is_ok() {
+        eprintln!("  {} No known browser installed.", styles.dim.apply_to("●"));
+    }
+    for browser in &installed {
+        let used = chosen.as_ref().is_ok_and(|path| *path == browser.path);
+        eprintln!(
+            "  {} {} {}",
+            if used {
+                styles.green.apply_to("▸")
+            } else {
+                styles.dim.apply_to(" ")
+            },
+            styles.bold.apply_to(browser.id),
+            styles.dim.apply_to(browser.to_string()),
+        );
+    }
+    // Indent the "Searched:" lines of the error under its first line.
+    let report = |e: &kuvpn::BrowserNotFound| e.to_string().replace('\n', "\n    ");
+    match chosen {
+        Ok(path) => {
+            if !installed.iter().any(|b| b.path == path) {
+                eprintln!("  {} Using {}", styles.green.apply_to("▸"), path.display());
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) if e.choice.is_none() => {
+            eprintln!("  {} {}", styles.yellow.apply_to("!"), report(&e));
+            eprintln!(
+                "  {} Chromium will be downloaded on the first login.",
+                styles.dim.apply_to("●")
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("  {} {}", styles.red.apply_to("✗"), report(&e));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_session_info(args: &Args, styles: &CliStyles) -> ExitCode {
+    let info = match kuvpn::session_info(&args.domain) {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!(
+                "  {} Failed to read the saved session: {}",
+                styles.red.apply_to("✗"),
+                e
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    let when = |ts: Option<u64>| {
+        ts.map(|t| format!("{} UTC", kuvpn::format_timestamp_unix(t)))
+            .unwrap_or_else(|| "never".to_string())
+    };
+
+    if !info.has_profile {
+        eprintln!("  {} No saved session.", styles.dim.apply_to("●"));
+    } else if info.sso_valid() {
+        eprintln!(
+            "  {} Microsoft sign-in saved until {} — no MFA expected",
+            styles.green.apply_to("✓"),
+            when(info.sso_expires),
+        );
+    } else if let Some(expired) = info.sso_expires {
+        eprintln!(
+            "  {} Microsoft sign-in expired at {} — the next login needs MFA",
+            styles.yellow.apply_to("!"),
+            when(Some(expired)),
+        );
+    } else {
+        eprintln!(
+            "  {} No persistent Microsoft sign-in — the next login needs MFA",
+            styles.yellow.apply_to("!"),
+        );
+    }
+    if info.has_profile {
+        eprintln!(
+            "  {} Gateway cookie: {}",
+            styles.dim.apply_to("●"),
+            if info.has_dsid_cookie {
+                "present"
+            } else {
+                "none"
+            },
+        );
+    }
+    eprintln!(
+        "  {} Last DSID:      {}",
+        styles.dim.apply_to("●"),
+        when(info.last_dsid)
+    );
+    eprintln!(
+        "  {} Last MFA:       {}",
+        styles.dim.apply_to("●"),
+        when(info.last_mfa)
+    );
+    ExitCode::SUCCESS
+}
+
 fn run_get_dsid(args: &Args, styles: &CliStyles) -> ExitCode {
     // Save the cursor position before any output (spinner + log lines).
     // On success we restore here and erase to end-of-screen, removing only
@@ -312,6 +574,12 @@ fn run_get_dsid(args: &Args, styles: &CliStyles) -> ExitCode {
         user_agent: "Mozilla/5.0".to_string(),
         no_auto_login: args.mode.no_auto_login(),
         email: args.email.clone(),
+        mfa_preference: args.mfa_preference(),
+        max_push_resends: args.max_push_resends,
+        lockout: args.lockout(),
+        session_conflict: args.session_conflict.into(),
+        visible_fallback: args.visible_fallback.into(),
+        engine: args.login_engine.into(),
     };
 
     let provider = CliCredentialsProvider {
@@ -345,21 +613,7 @@ fn run_vpn_session(args: &Args, styles: &CliStyles) -> ExitCode {
         );
     }
 
-    let config = SessionConfig {
-        url: args.url.clone(),
-        domain: args.domain.clone(),
-        user_agent: "Mozilla/5.0".to_string(),
-        headless: args.mode.headless(),
-        no_auto_login: args.mode.no_auto_login(),
-        email: args.email.clone(),
-        openconnect_path: args.openconnect_path.clone(),
-        escalation_tool: args.run_command.clone(),
-        interface_name: args.interface_name.clone(),
-        tunnel_mode: match args.tunnel_mode {
-            args::CliTunnelMode::Full => kuvpn::TunnelMode::Full,
-            args::CliTunnelMode::Manual => kuvpn::TunnelMode::Manual(args.vpnc_script.clone()),
-        },
-    };
+    let config = args.session_config();
 
     let mut cli_log_file = kuvpn::get_user_data_dir()
         .ok()
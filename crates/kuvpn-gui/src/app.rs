@@ -9,8 +9,8 @@ use crate::tray::TrayMenuItems;
 use crate::config::GuiSettings;
 use crate::provider::{GuiInteraction, GuiProvider};
 use crate::types::{
-    log_level_from_slider, login_mode_flags, ConnectionStatus, InputRequest, InputRequestWrapper,
-    Message, Tab,
+    ChoiceRequest, ChoiceRequestWrapper, ConnectionStatus, InputRequest, InputRequestWrapper,
+    Message, MfaPrompt, Tab,
 };
 use kuvpn::{ErrorCategory, SessionConfig, VpnSession};
 use std::time::Duration;
@@ -50,10 +50,11 @@ pub struct KuVpnGui {
     pub logs: Vec<String>,
     pub status: ConnectionStatus,
     pub pending_request: Option<InputRequest>,
+    pub pending_choice: Option<ChoiceRequest>,
     pub pending_email: Option<String>,
     pub current_input: String,
     pub show_password_held: bool,
-    pub mfa_info: Option<String>,
+    pub mfa_info: Option<MfaPrompt>,
     pub status_message: String,
     pub error_message: Option<String>,
     pub error_category: Option<ErrorCategory>,
@@ -63,11 +64,18 @@ pub struct KuVpnGui {
     pub automation_warning: Option<String>,
     /// Set when the Test button resolves a different path than what was entered.
     pub oc_path_notification: Option<String>,
+    /// Result of the last browser test: true = a browser was found.
+    pub browser_test_result: Option<bool>,
+    /// The browser the last test found, or why none was.
+    pub browser_notification: Option<String>,
     /// True after the first (startup) auto-test completes; used to suppress
     /// the replacement notification for the initial auto-detection pass.
     pub oc_startup_tested: bool,
-    /// Result of the last Wipe Session action: Some(true) = success, Some(false) = failed.
-    pub session_wipe_result: Option<bool>,
+    /// Result of the last wipe action and its scope: true = success, false = failed.
+    pub session_wipe_result: Option<(kuvpn::WipeScope, bool)>,
+    /// True once stale-session recovery has cleared the gateway cookies and
+    /// retried; a second tunnel failure is then reported instead of looping.
+    pub stale_session_retried: bool,
     /// True immediately after Reset Defaults is pressed; shows confirmation in the view.
     pub reset_notification: bool,
     /// Drives the fade animation for action notifications (1.0 = fully visible → 0.0 = gone).
@@ -75,6 +83,17 @@ pub struct KuVpnGui {
 
     // Connection history
     pub history: Vec<kuvpn::ConnectionEvent>,
+    /// Freshness of the saved browser session, shown while disconnected.
+    /// `None` until read, or when reading it failed.
+    pub session_info: Option<kuvpn::SessionInfo>,
+
+    // Connection profiles
+    pub profiles: kuvpn::ProfileStore,
+    /// Name typed into the "save as profile" field.
+    pub new_profile_name: String,
+
+    /// Defaults and locked settings from the administrator's policy file.
+    pub policy: kuvpn::Policy,
 
     // VPN Session
     pub session: Option<Arc<VpnSession>>,
@@ -129,10 +148,60 @@ impl KuVpnGui {
         }
     }
 
-    fn save_settings(&self) {
+    fn save_settings(&mut self) {
+        self.settings.enforce(&self.policy);
+        kuvpn::set_browser_choice(Some(self.settings.browser.clone()));
         if let Err(e) = self.settings.save() {
             log::error!("Failed to save settings: {}", e);
         }
+        // Settings edits go to the selected profile as well.
+        if let Some(name) = self.profiles.active.clone() {
+            let profile = self.settings.to_profile(&name);
+            if self.profiles.get(&name).is_some_and(|p| *p != profile) {
+                self.profiles.upsert(profile);
+                self.save_profiles();
+            }
+        }
+    }
+
+    fn save_profiles(&self) {
+        kuvpn::set_browser_profile(self.profiles.active.clone());
+        if let Err(e) = self.profiles.save() {
+            log::error!("Failed to save profiles: {}", e);
+        }
+    }
+
+    /// Rebuilds the tray profile submenu from the saved profiles.
+    pub fn sync_tray_profiles(&self) {
+        if let Some(menu) = &self.tray_menu {
+            menu.set_profiles(&self.profiles.names(), self.profiles.active.as_deref());
+        }
+    }
+
+    fn handle_profile_selected(&mut self, name: String) -> Task<Message> {
+        let idle = matches!(
+            self.status,
+            ConnectionStatus::Disconnected | ConnectionStatus::Error
+        );
+        if idle {
+            if let Some(profile) = self.profiles.get(&name).cloned() {
+                self.settings.apply_profile(&profile);
+                self.settings.enforce(&self.policy);
+                if let Err(e) = self.settings.save() {
+                    log::error!("Failed to save settings: {}", e);
+                }
+                self.profiles.active = Some(name);
+                self.save_profiles();
+                self.oc_test_result = None;
+                self.vpnc_script_test_result = None;
+                // The profile comes with its own browser data.
+                self.sync_tray_profiles();
+                return self.refresh_session_info_task();
+            }
+        }
+        // Also undoes the check the tray toggles by itself on click.
+        self.sync_tray_profiles();
+        Task::none()
     }
 
     fn is_transitioning(&self) -> bool {
@@ -163,6 +232,30 @@ impl KuVpnGui {
         }
     }
 
+    /// Shows the MFA banner and brings the window forward so the user sees it.
+    fn show_mfa_prompt(&mut self, prompt: MfaPrompt) -> Task<Message> {
+        self.mfa_info = Some(prompt);
+        self.current_tab = Tab::Connection;
+        log::info!("MFA received - bringing window to front");
+        if !self.is_visible && !self.window_close_pending && !self.window_open_pending {
+            self.was_shown_for_prompt = true;
+        }
+        self.show_or_focus_window()
+    }
+
+    /// Reads the saved session's freshness in the background.
+    pub fn refresh_session_info_task(&self) -> Task<Message> {
+        let domain = self.settings.domain.clone();
+        Task::perform(
+            async move {
+                kuvpn::session_info(&domain)
+                    .map_err(|e| log::warn!("Could not read the saved session: {}", e))
+                    .ok()
+            },
+            Message::SessionInfoLoaded,
+        )
+    }
+
     fn maybe_auto_hide_task(&mut self) -> Task<Message> {
         if self.was_shown_for_prompt && self.settings.auto_hide_after_prompt {
             self.was_shown_for_prompt = false;
@@ -239,9 +332,25 @@ impl KuVpnGui {
                                 ))))
                                 .await;
                         }
+                        Ok(GuiInteraction::Choice(req)) => {
+                            let _ = output
+                                .send(Message::RequestChoice(Arc::new(ChoiceRequestWrapper(
+                                    Mutex::new(Some(req)),
+                                ))))
+                                .await;
+                        }
                         Ok(GuiInteraction::MfaPush(code)) => {
                             let _ = output.send(Message::MfaPushReceived(code)).await;
                         }
+                        Ok(GuiInteraction::MfaCall) => {
+                            let _ = output.send(Message::MfaCallReceived).await;
+                        }
+                        Ok(GuiInteraction::BrowserAction(page)) => {
+                            let _ = output.send(Message::BrowserActionReceived(page)).await;
+                        }
+                        Ok(GuiInteraction::MfaResend(attempt, max)) => {
+                            let _ = output.send(Message::MfaResendReceived(attempt, max)).await;
+                        }
                         Ok(GuiInteraction::MfaComplete) => {
                             let _ = output.send(Message::MfaCompleteReceived).await;
                         }
@@ -278,6 +387,7 @@ impl KuVpnGui {
             menu.disconnect
                 .set_enabled(status == ConnectionStatus::Connected);
             menu.wipe.set_enabled(is_idle);
+            menu.profiles.set_enabled(is_idle);
             menu.status.set_text(crate::tray::status_label(status));
         }
     }
@@ -327,13 +437,31 @@ impl KuVpnGui {
         }
         self.sync_tray_menu_items(ConnectionStatus::Connecting);
 
-        let (headless, no_auto_login) = login_mode_flags(self.settings.login_mode_val);
-        let config = SessionConfig {
+        let session = Arc::new(VpnSession::new(self.session_config()));
+        self.session = Some(Arc::clone(&session));
+
+        let (log_tx, log_rx) = crossbeam_channel::unbounded();
+        session.set_logs_tx(log_tx);
+
+        crate::logger::LOGGER_INIT.call_once(|| {
+            let _ = log::set_logger(&crate::logger::GUI_LOGGER);
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+
+        let (gui_tx, gui_rx) = tokio::sync::mpsc::channel(100);
+        crate::logger::GUI_LOGGER.set_tx(gui_tx);
+
+        self.build_connection_stream(session, log_rx, gui_rx)
+    }
+
+    /// The session settings for the current GUI settings.
+    fn session_config(&self) -> SessionConfig {
+        SessionConfig {
             url: self.settings.url.clone(),
             domain: self.settings.domain.clone(),
             user_agent: "Mozilla/5.0".to_string(),
-            headless,
-            no_auto_login,
+            headless: self.settings.login_mode.headless(),
+            no_auto_login: self.settings.login_mode.no_auto_login(),
             email: if self.settings.email.is_empty() {
                 None
             } else {
@@ -345,7 +473,7 @@ impl KuVpnGui {
                 self.settings.openconnect_path.clone()
             },
             escalation_tool: Some(self.settings.escalation_tool.clone()),
-            interface_name: "kuvpn0".to_string(),
+            interface_name: self.settings.interface_name.clone(),
             tunnel_mode: if self.settings.is_manual_mode() {
                 kuvpn::TunnelMode::Manual(if self.settings.vpnc_script.is_empty() {
                     None
@@ -355,23 +483,42 @@ impl KuVpnGui {
             } else {
                 kuvpn::TunnelMode::Full
             },
-        };
-
-        let session = Arc::new(VpnSession::new(config));
-        self.session = Some(Arc::clone(&session));
-
-        let (log_tx, log_rx) = crossbeam_channel::unbounded();
-        session.set_logs_tx(log_tx);
-
-        crate::logger::LOGGER_INIT.call_once(|| {
-            let _ = log::set_logger(&crate::logger::GUI_LOGGER);
-            log::set_max_level(log::LevelFilter::Trace);
-        });
-
-        let (gui_tx, gui_rx) = tokio::sync::mpsc::channel(100);
-        crate::logger::GUI_LOGGER.set_tx(gui_tx);
+            mfa_preference: self.settings.mfa_preference.clone(),
+            max_push_resends: self.settings.max_push_resends,
+            lockout: kuvpn::LockoutPolicy {
+                threshold: self.settings.lockout_threshold,
+                cooldown: std::time::Duration::from_secs(self.settings.lockout_cooldown_mins * 60),
+            },
+            session_conflict: self.settings.session_conflict,
+            visible_fallback: self.settings.visible_fallback,
+            login_engine: self.settings.login_engine,
+            max_session_lifetime: match self.settings.session_lifetime_mins {
+                0 => None,
+                mins => Some(std::time::Duration::from_secs(mins * 60)),
+            },
+            expiry_warning: std::time::Duration::from_secs(self.settings.expiry_warning_mins * 60),
+            renew_before_expiry: self.settings.renew_before_expiry,
+            prewarm_idle: match self.settings.prewarm_mins {
+                0 => None,
+                mins => Some(std::time::Duration::from_secs(mins * 60)),
+            },
+            hooks: kuvpn::ConnectionHooks {
+                on_connect: Some(self.settings.connect_hook.clone()).filter(|c| !c.is_empty()),
+                on_disconnect: Some(self.settings.disconnect_hook.clone())
+                    .filter(|c| !c.is_empty()),
+            },
+        }
+    }
 
-        self.build_connection_stream(session, log_rx, gui_rx)
+    /// Launches a headless browser for the next connect in the background,
+    /// if pre-warming is enabled.
+    pub fn prewarm_browser(&self) {
+        if self.settings.prewarm_mins > 0 {
+            kuvpn::prewarm_browser(
+                &self.session_config().login_config(),
+                std::time::Duration::from_secs(self.settings.prewarm_mins * 60),
+            );
+        }
     }
 
     fn handle_connection_finished(
@@ -401,16 +548,18 @@ impl KuVpnGui {
             // Auto-recover from a stale DSID caused by a previous force-quit.
             // OpenConnect exiting immediately before the tunnel is established
             // often means the server still holds an active session and rejected
-            // the new cookie. Wipe the cached browser session data and retry
-            // once — the guard is that has_session_data() returns false after
-            // a successful wipe, so a second failure won't loop.
+            // the new cookie. Clear the gateway cookies (keeping the Microsoft
+            // sign-in, so no MFA is needed) and retry once — the guard is
+            // stale_session_retried, so a second failure won't loop.
             if matches!(category, Some(kuvpn::ErrorCategory::Connection))
                 && e.contains("OpenConnect process exited before tunnel was established")
                 && kuvpn::has_session_data()
-                && kuvpn::wipe_user_data_dir().is_ok()
+                && !self.stale_session_retried
             {
+                self.stale_session_retried = true;
                 self.logs.push(
-                    "[INF] Stale session detected — session data cleared. Retrying...".to_string(),
+                    "[INF] Stale session detected — clearing gateway cookies. Retrying..."
+                        .to_string(),
                 );
                 self.status = ConnectionStatus::Disconnected;
                 self.sync_tray_menu_items(self.status);
@@ -421,7 +570,21 @@ impl KuVpnGui {
                     async { kuvpn::load_events().unwrap_or_default() },
                     Message::HistoryLoaded,
                 );
-                return Task::batch(vec![history_task, Task::done(Message::AutoRetryConnect)]);
+                let domain = self.settings.domain.clone();
+                let wipe_task = Task::perform(
+                    async move {
+                        if let Err(e) = kuvpn::wipe_session_data(kuvpn::WipeScope::Gateway, &domain)
+                        {
+                            log::warn!(
+                                "Clearing gateway cookies failed ({}), wiping the profile",
+                                e
+                            );
+                            let _ = kuvpn::wipe_user_data_dir();
+                        }
+                    },
+                    |_| Message::AutoRetryConnect,
+                );
+                return Task::batch(vec![history_task, wipe_task]);
             }
 
             let is_automation_failure =
@@ -447,10 +610,13 @@ impl KuVpnGui {
 
         // Reload history — the session thread just appended a disconnect/error
         // event to disk, so refresh the in-memory list to reflect it.
-        Task::perform(
-            async { kuvpn::load_events().unwrap_or_default() },
-            Message::HistoryLoaded,
-        )
+        Task::batch(vec![
+            Task::perform(
+                async { kuvpn::load_events().unwrap_or_default() },
+                Message::HistoryLoaded,
+            ),
+            self.refresh_session_info_task(),
+        ])
     }
 
     fn handle_status_changed(&mut self, status: ConnectionStatus) -> Task<Message> {
@@ -474,7 +640,8 @@ impl KuVpnGui {
             }
             #[cfg(unix)]
             {
-                self.active_interface = kuvpn::get_vpn_interface_name("kuvpn0");
+                self.active_interface =
+                    kuvpn::get_vpn_interface_name(&self.settings.interface_name);
             }
             // Reload history — the session thread just appended a connect event
             // to disk; pull it into the in-memory list immediately.
@@ -553,7 +720,11 @@ impl KuVpnGui {
                 }
                 "connect" => self.update(Message::ConnectPressed),
                 "disconnect" => self.update(Message::DisconnectPressed),
-                "wipe" => self.update(Message::ClearSessionPressed),
+                "wipe" => self.update(Message::WipeSessionPressed(kuvpn::WipeScope::All)),
+                id if id.starts_with(crate::tray::PROFILE_ID_PREFIX) => {
+                    let name = id[crate::tray::PROFILE_ID_PREFIX.len()..].to_string();
+                    self.update(Message::ProfileSelected(name))
+                }
                 "copy_logs" => self.update(Message::CopyLogs),
                 "settings" => {
                     self.current_tab = Tab::Settings;
@@ -572,7 +743,7 @@ impl KuVpnGui {
                 Task::none()
             }
             Message::TunnelModeChanged(v) => {
-                self.settings.tunnel_mode_val = v;
+                self.settings.tunnel_mode = v;
                 self.save_settings();
                 Task::none()
             }
@@ -678,9 +849,9 @@ impl KuVpnGui {
                 self.save_settings();
                 Task::none()
             }
-            Message::LogLevelSliderChanged(val) => {
-                self.settings.log_level_val = val;
-                crate::logger::GUI_LOGGER.set_level(log_level_from_slider(val));
+            Message::LogLevelChanged(level) => {
+                self.settings.log_level = level;
+                crate::logger::GUI_LOGGER.set_level(level);
                 self.save_settings();
                 Task::none()
             }
@@ -696,11 +867,134 @@ impl KuVpnGui {
                 self.save_settings();
                 Task::none()
             }
-            Message::LoginModeChanged(val) => {
-                self.settings.login_mode_val = val;
+            Message::LoginModeChanged(mode) => {
+                self.settings.login_mode = mode;
+                self.save_settings();
+                Task::none()
+            }
+            Message::MaxPushResendsChanged(val) => {
+                self.settings.max_push_resends = val.round() as u32;
+                self.save_settings();
+                Task::none()
+            }
+            Message::LockoutThresholdChanged(val) => {
+                self.settings.lockout_threshold = val.round() as u32;
+                self.save_settings();
+                Task::none()
+            }
+            Message::MfaMethodAdded(method) => {
+                self.settings.add_mfa(method);
+                self.save_settings();
+                Task::none()
+            }
+            Message::MfaMethodMoved(index, up) => {
+                self.settings.move_mfa(index, up);
+                self.save_settings();
+                Task::none()
+            }
+            Message::MfaMethodRemoved(index) => {
+                self.settings.remove_mfa(index);
+                self.save_settings();
+                Task::none()
+            }
+            Message::LoginEngineChanged(engine) => {
+                self.settings.login_engine = engine;
+                self.save_settings();
+                Task::none()
+            }
+            Message::BrowserChanged(browser) => {
+                self.settings.browser = browser;
+                self.browser_test_result = None;
+                self.browser_notification = None;
+                self.save_settings();
+                Task::none()
+            }
+            Message::SessionConflictChanged(policy) => {
+                self.settings.session_conflict = policy;
+                self.save_settings();
+                Task::none()
+            }
+            Message::VisibleFallbackChanged(policy) => {
+                self.settings.visible_fallback = policy;
+                self.save_settings();
+                Task::none()
+            }
+            Message::SessionLifetimeChanged(val) => {
+                self.settings.session_lifetime_mins = val.round() as u64;
+                self.save_settings();
+                Task::none()
+            }
+            Message::ExpiryWarningChanged(val) => {
+                self.settings.expiry_warning_mins = val.round() as u64;
                 self.save_settings();
                 Task::none()
             }
+            Message::RenewBeforeExpiryToggled(v) => {
+                self.settings.renew_before_expiry = v;
+                self.save_settings();
+                Task::none()
+            }
+            Message::PrewarmChanged(val) => {
+                self.settings.prewarm_mins = val.round() as u64;
+                self.save_settings();
+                if self.settings.prewarm_mins == 0 {
+                    kuvpn::discard_warm_browser();
+                } else if self.status == ConnectionStatus::Disconnected {
+                    self.prewarm_browser();
+                }
+                Task::none()
+            }
+            Message::InterfaceNameChanged(val) => {
+                self.settings.interface_name = val;
+                self.save_settings();
+                Task::none()
+            }
+            Message::ConnectHookChanged(val) => {
+                self.settings.connect_hook = val;
+                self.save_settings();
+                Task::none()
+            }
+            Message::DisconnectHookChanged(val) => {
+                self.settings.disconnect_hook = val;
+                self.save_settings();
+                Task::none()
+            }
+            Message::ProfileSelected(name) => self.handle_profile_selected(name),
+            Message::NewProfileNameChanged(val) => {
+                self.new_profile_name = val;
+                Task::none()
+            }
+            Message::SaveProfilePressed => {
+                let name = self.new_profile_name.trim().to_string();
+                if !name.is_empty() {
+                    self.profiles.upsert(self.settings.to_profile(&name));
+                    self.profiles.active = Some(name);
+                    self.save_profiles();
+                    self.new_profile_name.clear();
+                    self.sync_tray_profiles();
+                    return self.refresh_session_info_task();
+                }
+                Task::none()
+            }
+            Message::DeleteProfilePressed => {
+                if let Some(name) = self.profiles.active.clone() {
+                    self.profiles.remove(&name);
+                    self.save_profiles();
+                    self.sync_tray_profiles();
+                    // A login in progress may still have the browser open on it.
+                    let idle = matches!(
+                        self.status,
+                        ConnectionStatus::Disconnected | ConnectionStatus::Error
+                    );
+                    if idle {
+                        if let Err(e) = kuvpn::remove_browser_profile(&name) {
+                            log::warn!("Failed to remove the browser data of '{}': {}", name, e);
+                        }
+                    }
+                    return self.refresh_session_info_task();
+                }
+                Task::none()
+            }
             Message::Tick => {
                 if self.is_transitioning() {
                     self.rotation += 0.1;
@@ -710,12 +1004,14 @@ impl KuVpnGui {
                 // the 100 ms session-poll loop that drives StatusChanged.
                 #[cfg(unix)]
                 if self.status == ConnectionStatus::Connected && self.active_interface.is_none() {
-                    self.active_interface = kuvpn::get_vpn_interface_name("kuvpn0");
+                    self.active_interface =
+                        kuvpn::get_vpn_interface_name(&self.settings.interface_name);
                 }
                 Task::none()
             }
             Message::ConnectPressed => {
                 self.logs.clear();
+                self.stale_session_retried = false;
                 self.handle_connect_pressed()
             }
             Message::AutoRetryConnect => {
@@ -772,14 +1068,14 @@ impl KuVpnGui {
                 Task::none()
             }
 
-            Message::MfaPushReceived(code) => {
-                self.mfa_info = Some(code.clone());
-                self.current_tab = Tab::Connection;
-                log::info!("MFA received - bringing window to front");
-                if !self.is_visible && !self.window_close_pending && !self.window_open_pending {
-                    self.was_shown_for_prompt = true;
-                }
-                self.show_or_focus_window()
+            Message::MfaPushReceived(code) => self.show_mfa_prompt(MfaPrompt::Push(code)),
+            Message::MfaCallReceived => self.show_mfa_prompt(MfaPrompt::Call),
+            Message::BrowserActionReceived(page) => self.show_mfa_prompt(MfaPrompt::Browser(page)),
+            Message::MfaResendReceived(attempt, max) => {
+                log::info!("No response to MFA request - resent ({}/{})", attempt, max);
+                self.status_message =
+                    format!("No response — sent another request ({}/{})", attempt, max);
+                Task::none()
             }
             Message::MfaCompleteReceived => {
                 self.mfa_info = None;
@@ -802,6 +1098,28 @@ impl KuVpnGui {
                 }
                 Task::none()
             }
+            Message::RequestChoice(wrapper) => {
+                if let Ok(mut guard) = wrapper.0.lock() {
+                    if let Some(req) = guard.take() {
+                        self.pending_choice = Some(req);
+                        log::info!("Choice requested - bringing window to front");
+                        if !self.is_visible
+                            && !self.window_close_pending
+                            && !self.window_open_pending
+                        {
+                            self.was_shown_for_prompt = true;
+                        }
+                        return self.show_or_focus_window();
+                    }
+                }
+                Task::none()
+            }
+            Message::ChoiceSelected(index) => {
+                if let Some(req) = self.pending_choice.take() {
+                    let _ = req.response_tx.send(index);
+                }
+                self.maybe_auto_hide_task()
+            }
             Message::InputChanged(val) => {
                 self.current_input = val;
                 Task::none()
@@ -822,6 +1140,7 @@ impl KuVpnGui {
             Message::DismissPrompt => {
                 // Page changed while a prompt was visible — retract it
                 self.pending_request = None;
+                self.pending_choice = None;
                 self.current_input = String::new();
                 self.show_password_held = false;
                 self.maybe_auto_hide_task()
@@ -830,24 +1149,56 @@ impl KuVpnGui {
                 self.show_password_held = held;
                 Task::none()
             }
-            Message::ClearSessionPressed => {
+            Message::WipeSessionPressed(scope) => {
                 self.reset_notification = false;
+                if !matches!(
+                    self.status,
+                    ConnectionStatus::Disconnected | ConnectionStatus::Error
+                ) {
+                    self.logs
+                        .push("Disconnect before clearing session data.".to_string());
+                    return Task::none();
+                }
                 if !kuvpn::has_session_data() {
                     self.logs.push("No active session found.".to_string());
-                    self.session_wipe_result = Some(true);
-                } else {
-                    match kuvpn::wipe_user_data_dir() {
-                        Ok(()) => {
-                            self.logs.push("Saved session data wiped.".to_string());
-                            self.session_wipe_result = Some(true);
-                        }
-                        Err(e) => {
-                            self.logs.push(format!("Failed to clear session: {}", e));
-                            self.session_wipe_result = Some(false);
-                        }
+                    self.session_wipe_result = Some((scope, true));
+                    self.notif_fade = 1.0;
+                    return Task::none();
+                }
+                // Clearing cookies opens the profile in a headless browser,
+                // which takes a moment; only the full wipe is done inline.
+                if scope == kuvpn::WipeScope::All {
+                    let result = kuvpn::wipe_user_data_dir().map_err(|e| e.to_string());
+                    return self.update(Message::SessionWiped(scope, result));
+                }
+                let domain = self.settings.domain.clone();
+                Task::perform(
+                    async move { kuvpn::wipe_session_data(scope, &domain).map_err(|e| e.to_string()) },
+                    move |result| Message::SessionWiped(scope, result),
+                )
+            }
+            Message::SessionWiped(scope, result) => {
+                match result {
+                    Ok(()) => {
+                        self.logs.push(format!("Saved {} cleared.", scope));
+                        self.session_wipe_result = Some((scope, true));
+                    }
+                    Err(e) => {
+                        self.logs.push(format!("Failed to clear {}: {}", scope, e));
+                        self.session_wipe_result = Some((scope, false));
                     }
                 }
                 self.notif_fade = 1.0;
+                self.refresh_session_info_task()
+            }
+            Message::SessionInfoLoaded(info) => {
+                self.session_info = info;
+                Task::none()
+            }
+            Message::PrewarmBrowser => {
+                if self.status == ConnectionStatus::Disconnected {
+                    self.prewarm_browser();
+                }
                 Task::none()
             }
             Message::ConnectionFinished(err, category) => {
@@ -856,7 +1207,7 @@ impl KuVpnGui {
             Message::StatusChanged(status) => self.handle_status_changed(status),
             Message::ResetSettings => {
                 let old_use_csd = self.settings.use_client_decorations;
-                self.settings = GuiSettings::default();
+                self.settings = GuiSettings::with_policy(&self.policy);
                 // Auto-select first available tool if the default (pkexec) isn't installed
                 #[cfg(unix)]
                 if !self.available_escalation_tools.is_empty()
@@ -870,6 +1221,8 @@ impl KuVpnGui {
                 self.oc_test_result = None;
                 self.oc_path_notification = None;
                 self.oc_startup_tested = false; // treat the next test as a fresh startup
+                self.browser_test_result = None;
+                self.browser_notification = None;
                 self.session_wipe_result = None;
                 self.reset_notification = true;
                 self.notif_fade = 1.0;
@@ -897,10 +1250,39 @@ impl KuVpnGui {
                     Message::OpenConnectTestResult,
                 )
             }
+            Message::TestBrowser => {
+                let choice = self.settings.browser.clone();
+                Task::perform(
+                    async move {
+                        kuvpn::resolve_browser(Some(&choice))
+                            .map(|p| p.display().to_string())
+                            .map_err(|e| {
+                                if e.choice.is_some() {
+                                    e.to_string()
+                                } else {
+                                    format!(
+                                        "{}\nChromium will be downloaded on the first login.",
+                                        e
+                                    )
+                                }
+                            })
+                    },
+                    Message::BrowserTestResult,
+                )
+            }
+            Message::BrowserTestResult(result) => {
+                self.browser_test_result = Some(result.is_ok());
+                self.browser_notification = Some(match result {
+                    Ok(path) => format!("Using {}", path),
+                    Err(e) => e,
+                });
+                Task::none()
+            }
             Message::OpenConnectTestResult(resolved) => {
                 let old_path = self.settings.openconnect_path.trim().to_string();
                 self.oc_test_result = Some(resolved.is_some());
-                if let Some(new_path) = resolved {
+                let locked = self.policy.is_locked(kuvpn::Setting::OpenconnectPath);
+                if let Some(new_path) = resolved.filter(|_| !locked) {
                     // Show a notification when the user explicitly tested a path and
                     // it was replaced (skip on the silent startup auto-test).
                     if self.oc_startup_tested
@@ -1029,6 +1411,7 @@ impl KuVpnGui {
             }
             Message::QuitRequested => {
                 log::info!("Quit requested - cleaning up");
+                kuvpn::discard_warm_browser();
                 // Disconnect VPN if connected
                 if let Some(session) = &self.session {
                     if self.status == ConnectionStatus::Connected
@@ -1157,10 +1540,18 @@ impl KuVpnGui {
 
 impl Default for KuVpnGui {
     fn default() -> Self {
+        let policy = kuvpn::Policy::load().unwrap_or_else(|e| {
+            log::error!("Ignoring the policy file: {}", e);
+            kuvpn::Policy::default()
+        });
         #[allow(unused_mut)]
-        let mut settings = GuiSettings::load();
+        let mut settings = GuiSettings::load(&policy);
+
+        crate::logger::GUI_LOGGER.set_level(settings.log_level);
+        kuvpn::set_browser_choice(Some(settings.browser.clone()));
 
-        crate::logger::GUI_LOGGER.set_level(log_level_from_slider(settings.log_level_val));
+        let profiles = kuvpn::ProfileStore::load().unwrap_or_default();
+        kuvpn::set_browser_profile(profiles.active.clone());
 
         // Detect which privilege escalation tools are installed on this system.
         // On Windows this is always empty (elevation is handled differently).
@@ -1180,6 +1571,7 @@ impl Default for KuVpnGui {
         #[cfg(unix)]
         if !available_escalation_tools.is_empty()
             && !available_escalation_tools.contains(&settings.escalation_tool.as_str())
+            && !policy.is_locked(kuvpn::Setting::EscalationTool)
         {
             settings.escalation_tool = available_escalation_tools[0].to_string();
             let _ = settings.save();
@@ -1191,6 +1583,7 @@ impl Default for KuVpnGui {
             logs: vec!["Ready for secure campus access.".to_string()],
             status: ConnectionStatus::Disconnected,
             pending_request: None,
+            pending_choice: None,
             pending_email: None,
             current_input: String::new(),
             show_password_held: false,
@@ -1203,11 +1596,18 @@ impl Default for KuVpnGui {
             vpnc_script_test_result: None,
             automation_warning: None,
             oc_path_notification: None,
+            browser_test_result: None,
+            browser_notification: None,
             oc_startup_tested: false,
             session_wipe_result: None,
+            stale_session_retried: false,
             reset_notification: false,
             notif_fade: 0.0,
             history: Vec::new(),
+            session_info: None,
+            profiles,
+            new_profile_name: String::new(),
+            policy,
             session: None,
             tray_icon: None,
             tray_menu: None,
@@ -1,6 +1,10 @@
 use crate::theme::ThemeConfig;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::fs;
+use std::path::PathBuf;
+
+const SETTINGS_FILE: &str = "gui_settings.json";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GuiSettings {
@@ -8,9 +12,11 @@ pub struct GuiSettings {
     pub domain: String,
     pub email: String,
     pub escalation_tool: String,
-    pub log_level_val: f32,
+    #[serde(default = "default_log_level")]
+    pub log_level: log::LevelFilter,
     pub openconnect_path: String,
-    pub login_mode_val: f32,
+    #[serde(default)]
+    pub login_mode: kuvpn::LoginMode,
     pub close_to_tray: bool,
     pub use_client_decorations: bool,
     #[serde(default = "default_auto_hide")]
@@ -19,25 +25,105 @@ pub struct GuiSettings {
     pub advanced_mode: bool,
     #[serde(default)]
     pub theme: ThemeConfig,
-    /// Tunnel mode: 0.0 = Split, 1.0 = Full, 2.0 = Manual.
     /// Manual mode uses `vpnc_script` as the script path.
-    #[serde(default = "default_tunnel_mode_val")]
-    pub tunnel_mode_val: f32,
+    #[serde(default)]
+    pub tunnel_mode: kuvpn::TunnelKind,
     /// Path to a custom vpnc-script passed via --script to openconnect (advanced).
-    /// Only used when `tunnel_mode_val` == 2.0 (Manual).
+    /// Only used in Manual tunnel mode.
     #[serde(default)]
     pub vpnc_script: String,
+    /// MFA methods to pick, in order, when Microsoft offers a choice.
+    #[serde(default = "kuvpn::MfaMethod::default_order")]
+    pub mfa_preference: Vec<kuvpn::MfaMethod>,
+    /// How many times an unanswered push or call is re-sent before giving up.
+    #[serde(default = "default_max_push_resends")]
+    pub max_push_resends: u32,
+    /// Incorrect passwords within the cooldown before sign-in pauses (0 = off).
+    #[serde(default = "default_lockout_threshold")]
+    pub lockout_threshold: u32,
+    /// Minutes an incorrect password counts towards `lockout_threshold`.
+    #[serde(default = "default_lockout_cooldown_mins")]
+    pub lockout_cooldown_mins: u64,
+    /// What to do with existing gateway sessions on the session-conflict page.
+    #[serde(default)]
+    pub session_conflict: kuvpn::SessionConflictPolicy,
+    /// Whether Full Auto may show the browser when it needs the user.
+    #[serde(default)]
+    pub visible_fallback: kuvpn::VisibleFallback,
+    /// Engine used for Full Auto logins.
+    #[serde(default)]
+    pub login_engine: kuvpn::LoginEngine,
+    /// Browser for the login: a path or a known browser id (empty = first found).
+    #[serde(default)]
+    pub browser: String,
+    /// Minutes after which the gateway expires a session (0 = learn from history).
+    #[serde(default)]
+    pub session_lifetime_mins: u64,
+    /// Minutes before the session expires to warn (0 = off).
+    #[serde(default = "default_expiry_warning_mins")]
+    pub expiry_warning_mins: u64,
+    /// Renew the gateway session in the background shortly before it expires.
+    #[serde(default)]
+    pub renew_before_expiry: bool,
+    /// Minutes a pre-warmed browser is kept unused (0 = no pre-warm).
+    #[serde(default)]
+    pub prewarm_mins: u64,
+    /// Name of the TUN interface created by openconnect.
+    #[serde(default = "default_interface_name")]
+    pub interface_name: String,
+    /// Shell command run once the tunnel is up (empty = none).
+    #[serde(default)]
+    pub connect_hook: String,
+    /// Shell command run after the tunnel has gone down (empty = none).
+    #[serde(default)]
+    pub disconnect_hook: String,
     /// Config schema version for migration.
     #[serde(default)]
     pub config_version: u32,
+    /// What the `KUVPN_*` overrides and the policy changed on load.
+    #[serde(skip)]
+    layers: Option<Box<Layers>>,
+}
+
+/// Settings the `KUVPN_*` overrides and the locked policy values replaced,
+/// so that saving writes the stored values back for them rather than
+/// turning a one-off override into a setting.
+#[derive(Debug, Clone)]
+struct Layers {
+    /// The settings that were replaced.
+    keys: Vec<kuvpn::Setting>,
+    /// Their values right after the replacement.
+    applied: kuvpn::Config,
+    /// The settings as stored, before the replacement.
+    stored: GuiSettings,
 }
 
 fn default_auto_hide() -> bool {
     true
 }
 
-fn default_tunnel_mode_val() -> f32 {
-    1.0 // Full tunnel
+fn default_max_push_resends() -> u32 {
+    kuvpn::DEFAULT_MAX_PUSH_RESENDS
+}
+
+fn default_lockout_threshold() -> u32 {
+    kuvpn::DEFAULT_LOCKOUT_THRESHOLD
+}
+
+fn default_lockout_cooldown_mins() -> u64 {
+    kuvpn::DEFAULT_LOCKOUT_COOLDOWN_MINS
+}
+
+fn default_expiry_warning_mins() -> u64 {
+    kuvpn::DEFAULT_EXPIRY_WARNING_MINS
+}
+
+fn default_interface_name() -> String {
+    "kuvpn0".to_string()
+}
+
+fn default_log_level() -> log::LevelFilter {
+    log::LevelFilter::Info
 }
 
 impl Default for GuiSettings {
@@ -47,56 +133,405 @@ impl Default for GuiSettings {
             domain: "vpn.ku.edu.tr".to_string(),
             email: String::new(),
             escalation_tool: "sudo".to_string(),
-            log_level_val: 3.0, // Default: Info level
+            log_level: default_log_level(),
             openconnect_path: "openconnect".to_string(),
-            login_mode_val: 0.0,
+            login_mode: kuvpn::LoginMode::FullAuto,
             close_to_tray: true,
             use_client_decorations: true,
             auto_hide_after_prompt: true,
             advanced_mode: false,
             theme: ThemeConfig::default(),
-            tunnel_mode_val: 1.0, // Full tunnel
+            tunnel_mode: kuvpn::TunnelKind::Full,
             vpnc_script: String::new(),
+            mfa_preference: kuvpn::MfaMethod::default_order(),
+            max_push_resends: kuvpn::DEFAULT_MAX_PUSH_RESENDS,
+            lockout_threshold: kuvpn::DEFAULT_LOCKOUT_THRESHOLD,
+            lockout_cooldown_mins: kuvpn::DEFAULT_LOCKOUT_COOLDOWN_MINS,
+            session_conflict: kuvpn::SessionConflictPolicy::default(),
+            visible_fallback: kuvpn::VisibleFallback::default(),
+            login_engine: kuvpn::LoginEngine::default(),
+            browser: String::new(),
+            session_lifetime_mins: 0,
+            expiry_warning_mins: kuvpn::DEFAULT_EXPIRY_WARNING_MINS,
+            renew_before_expiry: false,
+            prewarm_mins: 0,
+            interface_name: default_interface_name(),
+            connect_hook: String::new(),
+            disconnect_hook: String::new(),
             config_version: Self::CURRENT_VERSION,
+            layers: None,
         }
     }
 }
 
 impl GuiSettings {
-    const CURRENT_VERSION: u32 = 1;
+    const CURRENT_VERSION: u32 = 2;
 
-    /// Runs any pending migrations and stamps the current version.
-    /// Add new migration steps here as fields change across releases.
-    fn migrate(&mut self) {
+    /// Runs any pending migrations on the raw settings file and stamps the
+    /// current version.  Add new migration steps here as fields change
+    /// across releases.
+    fn migrate(value: &mut Value) {
+        let Some(obj) = value.as_object_mut() else {
+            return;
+        };
+        let version = obj
+            .get("config_version")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
         // 0 → 1: first versioned release; no field changes needed.
-        self.config_version = Self::CURRENT_VERSION;
+        // 1 → 2: the slider floats became enums.
+        if version < 2 {
+            let mut take = |key: &str| {
+                obj.remove(key)
+                    .and_then(|v| v.as_f64())
+                    .map(|v| v.round() as i64)
+            };
+            let log_level = take("log_level_val").map(|v| match v {
+                0 => log::LevelFilter::Off,
+                1 => log::LevelFilter::Error,
+                2 => log::LevelFilter::Warn,
+                4 => log::LevelFilter::Debug,
+                5 => log::LevelFilter::Trace,
+                _ => log::LevelFilter::Info,
+            });
+            let login_mode = take("login_mode_val").map(|v| match v {
+                0 => kuvpn::LoginMode::FullAuto,
+                1 => kuvpn::LoginMode::Visual,
+                _ => kuvpn::LoginMode::Manual,
+            });
+            // 0 was the retired split-tunnel mode; it maps to Full.
+            let tunnel_mode = take("tunnel_mode_val").map(|v| match v {
+                2 => kuvpn::TunnelKind::Manual,
+                _ => kuvpn::TunnelKind::Full,
+            });
+            if let Some(level) = log_level {
+                obj.insert("log_level".into(), json!(level));
+            }
+            if let Some(mode) = login_mode {
+                obj.insert("login_mode".into(), json!(mode));
+            }
+            if let Some(mode) = tunnel_mode {
+                obj.insert("tunnel_mode".into(), json!(mode));
+            }
+        }
+        obj.insert("config_version".into(), json!(Self::CURRENT_VERSION));
+    }
+
+    /// The built-in defaults with the administrator's defaults and locked
+    /// settings applied.
+    pub fn with_policy(policy: &kuvpn::Policy) -> Self {
+        let mut settings = Self::policy_defaults(policy);
+        settings.apply_layers(policy);
+        settings
+    }
+
+    /// The built-in defaults with the administrator's unlocked defaults.
+    fn policy_defaults(policy: &kuvpn::Policy) -> Self {
+        let unlocked: Vec<_> = policy
+            .defaults
+            .keys()
+            .into_iter()
+            .filter(|&k| !policy.is_locked(k))
+            .collect();
+        let mut settings = Self::default();
+        settings.apply_config(&policy.defaults.only(&unlocked));
+        settings
+    }
+
+    /// Applies the `KUVPN_*` overrides and the locked settings, remembering
+    /// what they replaced.
+    fn apply_layers(&mut self, policy: &kuvpn::Policy) {
+        let stored = self.clone();
+        let env = kuvpn::Config::from_env();
+        self.apply_config(&env);
+        self.enforce(policy);
+        self.layers = Some(Box::new(Layers {
+            keys: env.overlay(policy.locked_config()).keys(),
+            applied: self.to_config(kuvpn::Config::default()),
+            stored,
+        }));
+    }
+
+    /// The settings to write to disk: those an override or the policy set
+    /// and the user has not changed since keep their stored values.
+    fn persisted(&self) -> Self {
+        let mut settings = self.clone();
+        if let Some(layers) = &self.layers {
+            let current = self.to_config(kuvpn::Config::default());
+            let unchanged: Vec<_> = layers
+                .keys
+                .iter()
+                .copied()
+                .filter(|&k| current.only(&[k]) == layers.applied.only(&[k]))
+                .collect();
+            settings.copy_settings(&layers.stored, &unchanged);
+        }
+        settings
+    }
+
+    /// Copies the connection settings in `keys` from `other`.
+    fn copy_settings(&mut self, other: &Self, keys: &[kuvpn::Setting]) {
+        use kuvpn::Setting;
+        for key in keys {
+            match key {
+                Setting::Url => self.url = other.url.clone(),
+                Setting::Domain => self.domain = other.domain.clone(),
+                Setting::Email => self.email = other.email.clone(),
+                Setting::LoginMode => self.login_mode = other.login_mode,
+                Setting::LoginEngine => self.login_engine = other.login_engine,
+                Setting::Browser => self.browser = other.browser.clone(),
+                Setting::TunnelMode => self.tunnel_mode = other.tunnel_mode,
+                Setting::VpncScript => self.vpnc_script = other.vpnc_script.clone(),
+                Setting::EscalationTool => self.escalation_tool = other.escalation_tool.clone(),
+                Setting::OpenconnectPath => self.openconnect_path = other.openconnect_path.clone(),
+                Setting::InterfaceName => self.interface_name = other.interface_name.clone(),
+                Setting::MfaPreference => self.mfa_preference = other.mfa_preference.clone(),
+                Setting::MaxPushResends => self.max_push_resends = other.max_push_resends,
+                Setting::LockoutThreshold => self.lockout_threshold = other.lockout_threshold,
+                Setting::LockoutCooldown => {
+                    self.lockout_cooldown_mins = other.lockout_cooldown_mins
+                }
+                Setting::SessionConflict => self.session_conflict = other.session_conflict,
+                Setting::VisibleFallback => self.visible_fallback = other.visible_fallback,
+                Setting::SessionLifetime => {
+                    self.session_lifetime_mins = other.session_lifetime_mins
+                }
+                Setting::ExpiryWarning => self.expiry_warning_mins = other.expiry_warning_mins,
+                Setting::RenewSession => self.renew_before_expiry = other.renew_before_expiry,
+                Setting::Prewarm => self.prewarm_mins = other.prewarm_mins,
+                Setting::OnConnect => self.connect_hook = other.connect_hook.clone(),
+                Setting::OnDisconnect => self.disconnect_hook = other.disconnect_hook.clone(),
+            }
+        }
     }
 
-    /// Returns `true` when the tunnel mode slider is set to Manual (value 2).
+    /// Resets the settings locked by `policy` to their locked values.
+    pub fn enforce(&mut self, policy: &kuvpn::Policy) {
+        self.apply_config(&policy.locked_config());
+    }
+
+    /// Returns `true` when the tunnel mode is Manual.
     pub fn is_manual_mode(&self) -> bool {
-        self.tunnel_mode_val.round() as i32 == 2
+        self.tunnel_mode == kuvpn::TunnelKind::Manual
+    }
+
+    /// Loads the connection settings stored in `profile`.
+    pub fn apply_profile(&mut self, profile: &kuvpn::Profile) {
+        self.url = profile.url.clone();
+        self.domain = profile.domain.clone();
+        self.email = profile.email.clone().unwrap_or_default();
+        self.login_mode = profile.login_mode;
+        self.tunnel_mode = profile.tunnel_mode;
+        self.vpnc_script = profile.vpnc_script.clone().unwrap_or_default();
+        if let Some(tool) = &profile.escalation_tool {
+            self.escalation_tool = tool.clone();
+        }
+        self.interface_name = profile.interface_name.clone();
+        self.connect_hook = profile.hooks.on_connect.clone().unwrap_or_default();
+        self.disconnect_hook = profile.hooks.on_disconnect.clone().unwrap_or_default();
+    }
+
+    /// Captures the connection settings as a profile named `name`.
+    pub fn to_profile(&self, name: &str) -> kuvpn::Profile {
+        let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+        kuvpn::Profile {
+            name: name.to_string(),
+            url: self.url.clone(),
+            domain: self.domain.clone(),
+            email: non_empty(&self.email),
+            login_mode: self.login_mode,
+            tunnel_mode: self.tunnel_mode,
+            vpnc_script: non_empty(&self.vpnc_script),
+            escalation_tool: non_empty(&self.escalation_tool),
+            interface_name: self.interface_name.clone(),
+            hooks: kuvpn::ConnectionHooks {
+                on_connect: non_empty(&self.connect_hook),
+                on_disconnect: non_empty(&self.disconnect_hook),
+            },
+        }
+    }
+
+    /// Loads the connection settings set in the shared config file (see
+    /// [`kuvpn::config`]); keys it leaves unset keep their current value.
+    fn apply_config(&mut self, config: &kuvpn::Config) {
+        let set = |field: &mut String, value: &Option<String>| {
+            if let Some(v) = value {
+                *field = v.clone();
+            }
+        };
+        set(&mut self.url, &config.url);
+        set(&mut self.domain, &config.domain);
+        set(&mut self.email, &config.email);
+        set(&mut self.vpnc_script, &config.vpnc_script);
+        set(&mut self.escalation_tool, &config.escalation_tool);
+        set(&mut self.openconnect_path, &config.openconnect_path);
+        set(&mut self.browser, &config.browser);
+        set(&mut self.interface_name, &config.interface_name);
+        set(&mut self.connect_hook, &config.on_connect);
+        set(&mut self.disconnect_hook, &config.on_disconnect);
+        self.login_mode = config.login_mode.unwrap_or(self.login_mode);
+        self.tunnel_mode = config.tunnel_mode.unwrap_or(self.tunnel_mode);
+        if let Some(order) = &config.mfa_preference {
+            self.mfa_preference = order.clone();
+        }
+        self.max_push_resends = config.max_push_resends.unwrap_or(self.max_push_resends);
+        self.lockout_threshold = config.lockout_threshold.unwrap_or(self.lockout_threshold);
+        self.lockout_cooldown_mins = config
+            .lockout_cooldown
+            .unwrap_or(self.lockout_cooldown_mins);
+        self.session_conflict = config.session_conflict.unwrap_or(self.session_conflict);
+        self.visible_fallback = config.visible_fallback.unwrap_or(self.visible_fallback);
+        self.login_engine = config.login_engine.unwrap_or(self.login_engine);
+        self.session_lifetime_mins = config
+            .session_lifetime
+            .unwrap_or(self.session_lifetime_mins);
+        self.expiry_warning_mins = config.expiry_warning.unwrap_or(self.expiry_warning_mins);
+        self.renew_before_expiry = config.renew_session.unwrap_or(self.renew_before_expiry);
+        self.prewarm_mins = config.prewarm.unwrap_or(self.prewarm_mins);
+    }
+
+    /// The connection settings in config-file form.  Keys the GUI does not
+    /// manage (such as the CLI's default profile) are taken from `base`.
+    fn to_config(&self, base: kuvpn::Config) -> kuvpn::Config {
+        let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+        kuvpn::Config {
+            url: Some(self.url.clone()),
+            domain: Some(self.domain.clone()),
+            email: non_empty(&self.email),
+            login_mode: Some(self.login_mode),
+            login_engine: Some(self.login_engine),
+            browser: non_empty(&self.browser),
+            tunnel_mode: Some(self.tunnel_mode),
+            vpnc_script: non_empty(&self.vpnc_script),
+            escalation_tool: non_empty(&self.escalation_tool),
+            openconnect_path: non_empty(&self.openconnect_path),
+            interface_name: Some(self.interface_name.clone()),
+            mfa_preference: Some(self.mfa_preference.clone()),
+            max_push_resends: Some(self.max_push_resends),
+            lockout_threshold: Some(self.lockout_threshold),
+            lockout_cooldown: Some(self.lockout_cooldown_mins),
+            session_conflict: Some(self.session_conflict),
+            visible_fallback: Some(self.visible_fallback),
+            session_lifetime: (self.session_lifetime_mins > 0)
+                .then_some(self.session_lifetime_mins),
+            expiry_warning: Some(self.expiry_warning_mins),
+            renew_session: Some(self.renew_before_expiry),
+            prewarm: Some(self.prewarm_mins),
+            on_connect: non_empty(&self.connect_hook),
+            on_disconnect: non_empty(&self.disconnect_hook),
+            ..base
+        }
+    }
+
+    /// Writes the connection settings to the shared config file, unless the
+    /// file exists but does not parse (it is left for the user to fix).
+    fn save_shared_config(&self) -> anyhow::Result<()> {
+        let base = kuvpn::Config::from_file().map_err(|e| anyhow::anyhow!("{}", e))?;
+        self.to_config(base)
+            .save()
+            .map_err(|e| anyhow::anyhow!("{}", e))
+    }
+
+    /// Appends `method` to the MFA preference order, unless it is listed.
+    pub fn add_mfa(&mut self, method: kuvpn::MfaMethod) {
+        if !self.mfa_preference.contains(&method) {
+            self.mfa_preference.push(method);
+        }
+    }
+
+    /// Swaps the MFA preference at `index` with the one above (`up`) or
+    /// below it.
+    pub fn move_mfa(&mut self, index: usize, up: bool) {
+        let other = if up {
+            index.checked_sub(1)
+        } else {
+            Some(index + 1)
+        };
+        if let Some(other) = other.filter(|&o| o < self.mfa_preference.len()) {
+            if index < self.mfa_preference.len() {
+                self.mfa_preference.swap(index, other);
+            }
+        }
+    }
+
+    /// Drops the MFA preference at `index`, keeping at least one.
+    pub fn remove_mfa(&mut self, index: usize) {
+        if self.mfa_preference.len() > 1 && index < self.mfa_preference.len() {
+            self.mfa_preference.remove(index);
+        }
+    }
+
+    /// Path of the GUI settings file in the config directory.
+    fn settings_path() -> anyhow::Result<PathBuf> {
+        let dir = kuvpn::config::config_dir().map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(dir.join(SETTINGS_FILE))
+    }
+
+    /// Where older releases kept the settings: inside the browser profile,
+    /// which a session wipe deletes.
+    fn legacy_settings_path() -> Option<PathBuf> {
+        kuvpn::utils::get_user_data_dir()
+            .ok()
+            .map(|dir| dir.join(SETTINGS_FILE))
     }
 
     pub fn save(&self) -> anyhow::Result<()> {
-        let dir = kuvpn::utils::get_user_data_dir().map_err(|e| anyhow::anyhow!("{}", e))?;
-        let path = dir.join("gui_settings.json");
+        let settings = self.persisted();
+        settings.save_gui_file()?;
+        settings.save_shared_config()
+    }
+
+    fn save_gui_file(&self) -> anyhow::Result<()> {
         let json = serde_json::to_string_pretty(self)?;
-        fs::write(path, json)?;
+        fs::write(Self::settings_path()?, json)?;
         Ok(())
     }
 
-    pub fn load() -> Self {
-        if let Ok(dir) = kuvpn::utils::get_user_data_dir() {
-            let path = dir.join("gui_settings.json");
-            if path.exists() {
-                if let Ok(content) = fs::read_to_string(path) {
-                    if let Ok(mut settings) = serde_json::from_str::<Self>(&content) {
-                        settings.migrate();
-                        return settings;
+    pub fn load(policy: &kuvpn::Policy) -> Self {
+        let mut settings = Self::load_gui_file().unwrap_or_else(|| Self::policy_defaults(policy));
+        // The shared config file wins for the connection settings, and the
+        // KUVPN_* overrides and locked settings for this run; create the file
+        // on first run so the CLI sees the settings made here.
+        let exists = kuvpn::Config::exists();
+        if exists {
+            match kuvpn::Config::from_file() {
+                Ok(config) => settings.apply_config(&config),
+                Err(e) => log::warn!("Ignoring the config file: {}", e),
+            }
+        }
+        settings.apply_layers(policy);
+        if !exists {
+            if let Err(e) = settings.persisted().save_shared_config() {
+                log::warn!("Failed to create the config file: {}", e);
+            }
+        }
+        settings
+    }
+
+    fn load_gui_file() -> Option<Self> {
+        if let Some(settings) = Self::settings_path().ok().and_then(|p| Self::read_file(&p)) {
+            return Some(settings);
+        }
+        // Move settings left in the browser profile by older releases.
+        if let Some(legacy) = Self::legacy_settings_path() {
+            if let Some(settings) = Self::read_file(&legacy) {
+                match settings.save_gui_file() {
+                    Ok(()) => {
+                        let _ = fs::remove_file(&legacy);
+                        log::info!("Moved GUI settings out of the browser profile");
                     }
+                    Err(e) => log::warn!("Failed to move GUI settings: {}", e),
                 }
+                return Some(settings);
             }
         }
-        Self::default()
+        None
+    }
+
+    fn read_file(path: &std::path::Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        let mut value: Value = serde_json::from_str(&content).ok()?;
+        Self::migrate(&mut value);
+        serde_json::from_value(value).ok()
     }
 }
@@ -47,6 +47,7 @@ pub fn main() -> iced::Result {
         eprintln!("{}", e);
         return Ok(());
     }
+    kuvpn::sweep_orphaned_browsers();
 
     #[cfg(target_os = "linux")]
     {
@@ -65,11 +66,17 @@ pub fn main() -> iced::Result {
                 if let Some(c) = guard.take() {
                     gui.tray_icon = Some(c.tray);
                     gui.tray_menu = Some(c.menu_items);
+                    gui.sync_tray_profiles();
                 }
             }
 
             let use_csd = gui.settings.use_client_decorations;
             let (id, task) = iced::window::open(app::window_settings(use_csd));
+            // Pre-warm only after the saved session has been read, which
+            // opens the profile in a browser of its own.
+            let session_info_task = gui
+                .refresh_session_info_task()
+                .chain(Task::done(Message::PrewarmBrowser));
 
             gui.window_id = Some(id);
             gui.is_visible = true;
@@ -83,6 +90,7 @@ pub fn main() -> iced::Result {
                         async { kuvpn::load_events().unwrap_or_default() },
                         Message::HistoryLoaded,
                     ),
+                    session_info_task,
                 ]),
             )
         },
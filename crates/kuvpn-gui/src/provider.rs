@@ -1,4 +1,4 @@
-use crate::types::InputRequest;
+use crate::types::{ChoiceRequest, InputRequest};
 use kuvpn::utils::{CancellationToken, CredentialsProvider};
 use std::sync::Mutex;
 use tokio::sync::{mpsc, oneshot};
@@ -6,7 +6,11 @@ use tokio::sync::{mpsc, oneshot};
 #[derive(Debug)]
 pub enum GuiInteraction {
     Request(InputRequest),
+    Choice(ChoiceRequest),
     MfaPush(String),
+    MfaCall,
+    MfaResend(u32, u32),
+    BrowserAction(String),
     MfaComplete,
     DismissPrompt,
 }
@@ -27,11 +31,47 @@ impl CredentialsProvider for GuiProvider {
     fn request_email(&self, msg: &str) -> Option<String> {
         self.request(msg, false, true)
     }
+    fn request_choice(&self, msg: &str, options: &[String]) -> Option<usize> {
+        let (tx, rx) = oneshot::channel();
+        let request = ChoiceRequest {
+            msg: msg.to_string(),
+            options: options.to_vec(),
+            response_tx: tx,
+        };
+
+        let _ = self
+            .interaction_tx
+            .blocking_send(GuiInteraction::Choice(request));
+
+        self.wait_for_response(rx)
+    }
     fn on_mfa_push(&self, code: &str) {
         let _ = self
             .interaction_tx
             .blocking_send(GuiInteraction::MfaPush(code.to_string()));
     }
+    fn on_mfa_call(&self) {
+        let _ = self.interaction_tx.blocking_send(GuiInteraction::MfaCall);
+    }
+    fn on_browser_action(&self, page: &str) {
+        let _ = self
+            .interaction_tx
+            .blocking_send(GuiInteraction::BrowserAction(page.to_string()));
+    }
+    fn on_mfa_resend(&self, attempt: u32, max: u32) {
+        let _ = self
+            .interaction_tx
+            .blocking_send(GuiInteraction::MfaResend(attempt, max));
+    }
+    fn confirm_password_retry(&self, failures: u32, retry_after: std::time::Duration) -> bool {
+        let msg = format!(
+            "{} recent incorrect passwords. To avoid locking your account, sign-in is paused for {}.",
+            failures,
+            kuvpn::format_duration_secs(retry_after.as_secs())
+        );
+        let options = ["Wait".to_string(), "Re-enter password now".to_string()];
+        self.request_choice(&msg, &options) == Some(1)
+    }
     fn on_mfa_complete(&self) {
         let _ = self
             .interaction_tx
@@ -51,7 +91,7 @@ impl CredentialsProvider for GuiProvider {
 
 impl GuiProvider {
     fn request(&self, msg: &str, is_password: bool, is_email: bool) -> Option<String> {
-        let (tx, mut rx) = oneshot::channel();
+        let (tx, rx) = oneshot::channel();
         let request = InputRequest {
             msg: msg.to_string(),
             is_password,
@@ -63,7 +103,12 @@ impl GuiProvider {
             .interaction_tx
             .blocking_send(GuiInteraction::Request(request));
 
-        // Wait for response, but also poll for cancellation and page changes
+        self.wait_for_response(rx)
+    }
+
+    /// Waits for the user's answer, but also polls for cancellation and page
+    /// changes, dismissing the prompt if either happens.
+    fn wait_for_response<T>(&self, mut rx: oneshot::Receiver<T>) -> Option<T> {
         loop {
             if self.cancel_token.is_cancelled() {
                 let _ = self
@@ -1,5 +1,5 @@
 use tray_icon::{
-    menu::{Menu, MenuItem, PredefinedMenuItem},
+    menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
     TrayIcon, TrayIconBuilder,
 };
 
@@ -15,8 +15,34 @@ pub struct TrayMenuItems {
     pub connect: MenuItem,
     pub disconnect: MenuItem,
     pub wipe: MenuItem,
+    pub profiles: Submenu,
 }
 
+impl TrayMenuItems {
+    /// Rebuilds the profile submenu, checking the `active` profile.
+    pub fn set_profiles(&self, names: &[String], active: Option<&str>) {
+        while self.profiles.remove_at(0).is_some() {}
+        if names.is_empty() {
+            let _ = self
+                .profiles
+                .append(&MenuItem::new("No saved profiles", false, None));
+        }
+        for name in names {
+            let item = CheckMenuItem::with_id(
+                format!("{}{}", PROFILE_ID_PREFIX, name),
+                name,
+                true,
+                active == Some(name.as_str()),
+                None,
+            );
+            let _ = self.profiles.append(&item);
+        }
+    }
+}
+
+/// Menu-id prefix of the profile submenu entries; the rest is the name.
+pub const PROFILE_ID_PREFIX: &str = "profile:";
+
 pub struct TrayComponents {
     pub tray: TrayIcon,
     pub menu_items: TrayMenuItems,
@@ -68,6 +94,7 @@ pub fn init_tray() -> TrayComponents {
     let connect_item = MenuItem::with_id("connect", "Connect", true, None);
     let disconnect_item = MenuItem::with_id("disconnect", "Disconnect", false, None);
     let wipe_item = MenuItem::with_id("wipe", "Wipe Session", true, None);
+    let profiles_menu = Submenu::new("Profile", true);
     let copy_logs_item = MenuItem::with_id("copy_logs", "Copy Logs", true, None);
     let settings_item = MenuItem::with_id("settings", "Settings", true, None);
     let quit_item = MenuItem::with_id("quit", "Quit", true, None);
@@ -79,6 +106,7 @@ pub fn init_tray() -> TrayComponents {
         &PredefinedMenuItem::separator(),
         &connect_item,
         &disconnect_item,
+        &profiles_menu,
         &PredefinedMenuItem::separator(),
         &wipe_item,
         &copy_logs_item,
@@ -118,6 +146,7 @@ pub fn init_tray() -> TrayComponents {
             connect: connect_item,
             disconnect: disconnect_item,
             wipe: wipe_item,
+            profiles: profiles_menu,
         },
     }
 }
@@ -57,10 +57,31 @@ pub enum Message {
     DomainChanged(String),
     #[cfg(not(windows))]
     EscalationToolChanged(String),
-    LogLevelSliderChanged(f32),
+    LogLevelChanged(log::LevelFilter),
     OpenConnectPathChanged(String),
     EmailChanged(String),
-    LoginModeChanged(f32),
+    LoginModeChanged(kuvpn::LoginMode),
+    LoginEngineChanged(kuvpn::LoginEngine),
+    BrowserChanged(String),
+    MfaMethodAdded(kuvpn::MfaMethod),
+    /// Moves the MFA preference at the index one place up (`true`) or down.
+    MfaMethodMoved(usize, bool),
+    MfaMethodRemoved(usize),
+    MaxPushResendsChanged(f32),
+    LockoutThresholdChanged(f32),
+    SessionConflictChanged(kuvpn::SessionConflictPolicy),
+    VisibleFallbackChanged(kuvpn::VisibleFallback),
+    SessionLifetimeChanged(f32),
+    ExpiryWarningChanged(f32),
+    RenewBeforeExpiryToggled(bool),
+    PrewarmChanged(f32),
+    InterfaceNameChanged(String),
+    ConnectHookChanged(String),
+    DisconnectHookChanged(String),
+    ProfileSelected(String),
+    NewProfileNameChanged(String),
+    SaveProfilePressed,
+    DeleteProfilePressed,
     ConnectPressed,
     /// Like ConnectPressed but triggered automatically (stale session retry).
     /// Does NOT clear the console log — appends a separator banner instead.
@@ -70,10 +91,16 @@ pub enum Message {
     RequestInput(Arc<InputRequestWrapper>),
     InputChanged(String),
     SubmitInput,
+    RequestChoice(Arc<ChoiceRequestWrapper>),
+    ChoiceSelected(usize),
     DismissPrompt,
     MfaPushReceived(String),
+    MfaCallReceived,
+    BrowserActionReceived(String),
+    MfaResendReceived(u32, u32),
     MfaCompleteReceived,
-    ClearSessionPressed,
+    WipeSessionPressed(kuvpn::WipeScope),
+    SessionWiped(kuvpn::WipeScope, Result<(), String>),
     ConnectionFinished(Option<String>, Option<kuvpn::ErrorCategory>),
     StatusChanged(ConnectionStatus),
     Tick,
@@ -93,6 +120,8 @@ pub enum Message {
     ResetSettings,
     TestOpenConnect,
     OpenConnectTestResult(Option<String>),
+    TestBrowser,
+    BrowserTestResult(Result<String, String>),
     TestVpncScript,
     VpncScriptTestResult(Option<String>),
     CopyLogs,
@@ -102,13 +131,15 @@ pub enum Message {
     QuitRequested,
     QuitAfterCleanup,
     AutoHideAfterPromptToggled(bool),
-    TunnelModeChanged(f32),
+    TunnelModeChanged(kuvpn::TunnelKind),
     VpncScriptChanged(String),
     AutoHideWindow,
     AdvancedModeToggled(bool),
     ShowPasswordHeld(bool),
     ActionNotifTick,
     HistoryLoaded(Vec<kuvpn::ConnectionEvent>),
+    SessionInfoLoaded(Option<kuvpn::SessionInfo>),
+    PrewarmBrowser,
     ClearHistory,
     OpenDiagnosticsFolder,
     // Theme
@@ -118,6 +149,17 @@ pub enum Message {
     ThemeShadowChanged(ShadowDepth),
 }
 
+/// The sign-in step currently waiting on the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MfaPrompt {
+    /// Authenticator push; holds the number to enter (may be empty).
+    Push(String),
+    /// Microsoft is calling the user's phone.
+    Call,
+    /// A page the user has to finish in the browser window; holds its name.
+    Browser(String),
+}
+
 #[derive(Debug)]
 pub struct InputRequest {
     pub msg: String,
@@ -129,22 +171,13 @@ pub struct InputRequest {
 #[derive(Debug)]
 pub struct InputRequestWrapper(pub Mutex<Option<InputRequest>>);
 
-pub fn log_level_from_slider(val: f32) -> log::LevelFilter {
-    match val.round() as i32 {
-        0 => log::LevelFilter::Off,
-        1 => log::LevelFilter::Error,
-        2 => log::LevelFilter::Warn,
-        3 => log::LevelFilter::Info,
-        4 => log::LevelFilter::Debug,
-        5 => log::LevelFilter::Trace,
-        _ => log::LevelFilter::Info,
-    }
+/// A "pick one of N" prompt raised by the login flow.
+#[derive(Debug)]
+pub struct ChoiceRequest {
+    pub msg: String,
+    pub options: Vec<String>,
+    pub response_tx: oneshot::Sender<usize>,
 }
 
-pub fn login_mode_flags(val: f32) -> (bool, bool) {
-    match val.round() as i32 {
-        0 => (true, false),  // Full Automatic
-        1 => (false, false), // Visual Automatic
-        _ => (false, true),  // Manual
-    }
-}
+#[derive(Debug)]
+pub struct ChoiceRequestWrapper(pub Mutex<Option<ChoiceRequest>>);
@@ -107,6 +107,8 @@ fn view_event_row<'a>(event: &'a kuvpn::ConnectionEvent, s: Styler) -> Element<'
         EventKind::Disconnected => (p.text_muted, "Disconnected"),
         EventKind::Cancelled => (p.text_muted, "Cancelled"),
         EventKind::Error => (p.danger, "Error"),
+        EventKind::BrowserFallback => (p.warning, "Fallback"),
+        EventKind::BrowserCrash => (p.warning, "Relaunched"),
     };
 
     let ts = event.format_timestamp();
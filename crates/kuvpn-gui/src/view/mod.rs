@@ -8,7 +8,7 @@ pub mod status;
 use crate::app::KuVpnGui;
 use crate::styles::Styler;
 use crate::types::{
-    Message, SegmentPosition, Tab, ICON_CLOCK_SVG, ICON_SETTINGS_SVG, ICON_SHIELD_SVG,
+    Message, MfaPrompt, SegmentPosition, Tab, ICON_CLOCK_SVG, ICON_SETTINGS_SVG, ICON_SHIELD_SVG,
     ICON_TERMINAL_SVG,
 };
 use iced::widget::{button, column, container, mouse_area, row, stack, svg, text, Column, Space};
@@ -88,8 +88,13 @@ impl KuVpnGui {
         .spacing(8)
         .align_y(Alignment::Center);
 
-        if let Some(code) = &self.mfa_info {
-            title_row = title_row.push(text(format!("MFA: {}", code)).size(11).color(p.warning));
+        if let Some(prompt) = &self.mfa_info {
+            let label = match prompt {
+                MfaPrompt::Push(code) => format!("MFA: {}", code),
+                MfaPrompt::Call => "MFA: answer call".to_string(),
+                MfaPrompt::Browser(_) => "Action: browser".to_string(),
+            };
+            title_row = title_row.push(text(label).size(11).color(p.warning));
         }
 
         let title_bar_content = title_row
@@ -177,6 +182,8 @@ impl KuVpnGui {
 
         if let Some(req) = &self.pending_request {
             stack![main_container, self.view_modal(req)].into()
+        } else if let Some(req) = &self.pending_choice {
+            stack![main_container, self.view_choice_modal(req)].into()
         } else {
             main_container.into()
         }
@@ -267,12 +274,23 @@ impl KuVpnGui {
             content = content.push(self.view_connection_details());
         }
 
+        // Saved sign-in freshness when idle
+        if let Some(info) = &self.session_info {
+            if matches!(
+                self.status,
+                ConnectionStatus::Disconnected | ConnectionStatus::Error
+            ) {
+                content = content.push(Space::new().height(14));
+                content = content.push(self.view_session_freshness(info));
+            }
+        }
+
         // Bottom spacer — pushes banners + button to bottom
         content = content.push(Space::new().height(Length::Fill));
 
         // MFA banner
-        if let Some(code) = &self.mfa_info {
-            content = content.push(self.view_mfa_card(code));
+        if let Some(prompt) = &self.mfa_info {
+            content = content.push(self.view_mfa_card(prompt));
             content = content.push(Space::new().height(10));
         }
 
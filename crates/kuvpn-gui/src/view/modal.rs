@@ -1,5 +1,7 @@
 use crate::app::KuVpnGui;
-use crate::types::{InputRequest, Message, ICON_EYE_OFF_SVG, ICON_EYE_SVG, ICON_LOCK_SVG};
+use crate::types::{
+    ChoiceRequest, InputRequest, Message, ICON_EYE_OFF_SVG, ICON_EYE_SVG, ICON_LOCK_SVG,
+};
 use iced::widget::{button, column, container, stack, svg, text, text_input};
 use iced::{Alignment, Border, Color, Element, Length, Padding, Shadow};
 
@@ -111,4 +113,51 @@ impl KuVpnGui {
             .style(s.modal_overlay())
             .into()
     }
+
+    /// Modal listing the options of a [`ChoiceRequest`]; clicking one answers it.
+    pub fn view_choice_modal<'a>(&self, req: &'a ChoiceRequest) -> Element<'a, Message> {
+        let s = self.styler();
+        let p = s.p;
+
+        let mut options = column![].spacing(8);
+        for (index, label) in req.options.iter().enumerate() {
+            options = options.push(
+                button(text(label).size(13).color(p.text))
+                    .width(Length::Fill)
+                    .padding([10, 14])
+                    .on_press(Message::ChoiceSelected(index))
+                    .style(s.btn_secondary()),
+            );
+        }
+
+        let modal_content = container(
+            column![
+                iced::widget::row![
+                    svg(svg::Handle::from_memory(ICON_LOCK_SVG))
+                        .width(26)
+                        .height(26)
+                        .style(move |_, _| svg::Style {
+                            color: Some(p.accent)
+                        }),
+                    text("KU Authentication").size(20),
+                ]
+                .spacing(12)
+                .align_y(Alignment::Center),
+                text(&req.msg).size(14).color(p.text),
+                options,
+            ]
+            .spacing(20)
+            .padding(32),
+        )
+        .width(Length::Fixed(400.0))
+        .style(s.modal_card());
+
+        container(modal_content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .style(s.modal_overlay())
+            .into()
+    }
 }
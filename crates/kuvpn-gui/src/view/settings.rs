@@ -36,6 +36,7 @@ impl KuVpnGui {
         let s = self.styler();
         let p = s.p;
         let is_locked = false;
+        let locked = |key: kuvpn::Setting| is_locked || self.policy.is_locked(key);
 
         let locked_hint = if is_locked {
             container(
@@ -128,6 +129,10 @@ impl KuVpnGui {
         let adv = self.settings.advanced_mode;
         let mut col: Column<'_, Message> = Column::new().spacing(12);
 
+        if !self.policy.locked.is_empty() {
+            col = col.push(self.view_policy_notice());
+        }
+
         col = col.push(header);
 
         // ── APPEARANCE section ────────────────────────────────────────────────
@@ -236,6 +241,11 @@ impl KuVpnGui {
             ));
         }
 
+        // PROFILE section
+        col = col.push(divider());
+        col = col.push(section_label("PROFILE"));
+        col = col.push(self.view_profile_controls());
+
         // BROWSER section
         col = col.push(divider());
         col = col.push(section_label("BROWSER"));
@@ -244,20 +254,144 @@ impl KuVpnGui {
             "username@ku.edu.tr",
             &self.settings.email,
             "Your KU email address (e.g. jdoe24@ku.edu.tr). Saved locally so you don't re-enter after wiping session. Used in Full Auto and Visual Auto modes to pre-fill the login form.",
-            is_locked,
+            locked(kuvpn::Setting::Email),
             Message::EmailChanged,
         ));
         col = col.push(self.view_unified_control(
             "Login Mode:",
             self.view_segmented_control(
                 &["Full Auto", "Visual Auto", "Manual"],
-                &[0.0, 1.0, 2.0],
-                self.settings.login_mode_val,
-                is_locked,
+                &[
+                    kuvpn::LoginMode::FullAuto,
+                    kuvpn::LoginMode::Visual,
+                    kuvpn::LoginMode::Manual,
+                ],
+                self.settings.login_mode,
+                locked(kuvpn::Setting::LoginMode),
                 Message::LoginModeChanged,
             ),
             "Full Auto: fully hands-free login via browser automation — no interaction needed.\n\nVisual Auto: same automation but shows the browser window, useful for debugging.\n\nManual: the browser opens and you complete the login yourself.",
         ));
+        if adv {
+            col = col.push(self.view_unified_control(
+                "Login Engine:",
+                self.view_segmented_control(
+                    &["Browser", "HTTP"],
+                    &kuvpn::LoginEngine::ALL,
+                    self.settings.login_engine,
+                    locked(kuvpn::Setting::LoginEngine),
+                    Message::LoginEngineChanged,
+                ),
+                "How Full Auto signs in. Browser drives a hidden Chrome window. HTTP talks to the gateway and Microsoft directly, without starting a browser, and hands over to the browser when it meets a page it doesn't know.",
+            ));
+        }
+        col = col.push(self.view_unified_control(
+            "MFA Methods:",
+            self.view_mfa_order(locked(kuvpn::Setting::MfaPreference)),
+            "The second factors to choose, in order, when Microsoft asks how you want to verify your identity. The first one offered is picked; add First Available to fall back to whatever Microsoft lists first.",
+        ));
+        if adv {
+            col = col.push(self.view_unified_control(
+                "MFA Resends:",
+                self.view_segmented_control(
+                    &["Off", "1", "2", "3"],
+                    &[0.0, 1.0, 2.0, 3.0],
+                    self.settings.max_push_resends as f32,
+                    locked(kuvpn::Setting::MaxPushResends),
+                    Message::MaxPushResendsChanged,
+                ),
+                "How many times to send another push notification or phone call when the previous one goes unanswered, before giving up.",
+            ));
+            col = col.push(self.view_unified_control(
+                "Lockout Guard:",
+                self.view_segmented_control(
+                    &["Off", "3", "5", "10"],
+                    &[0.0, 3.0, 5.0, 10.0],
+                    self.settings.lockout_threshold as f32,
+                    locked(kuvpn::Setting::LockoutThreshold),
+                    Message::LockoutThresholdChanged,
+                ),
+                "After this many incorrect passwords, KUVPN stops submitting the password until the cooldown passes or you confirm that you want to type it again. Protects your account from being locked by repeated retries.",
+            ));
+            col = col.push(self.view_unified_control(
+                "Open Sessions:",
+                pick_list(
+                    kuvpn::SessionConflictPolicy::ALL.to_vec(),
+                    Some(self.settings.session_conflict),
+                    if locked(kuvpn::Setting::SessionConflict) {
+                        |_| Message::Tick
+                    } else {
+                        Message::SessionConflictChanged
+                    },
+                )
+                .style(s.pick_list_style())
+                .menu_style(s.pick_list_menu_style())
+                .width(Length::Fill)
+                .into(),
+                "What to do when the gateway reports that you already have VPN sessions open (for example on another computer). Ask lets you pick which one to end; Close others ends them all; Keep others leaves them open.",
+            ));
+            col = col.push(self.view_unified_control(
+                "When Stuck:",
+                pick_list(
+                    kuvpn::VisibleFallback::ALL.to_vec(),
+                    Some(self.settings.visible_fallback),
+                    if locked(kuvpn::Setting::VisibleFallback) {
+                        |_| Message::Tick
+                    } else {
+                        Message::VisibleFallbackChanged
+                    },
+                )
+                .style(s.pick_list_style())
+                .menu_style(s.pick_list_menu_style())
+                .width(Length::Fill)
+                .into(),
+                "What Full Auto does when the login cannot finish without you (a page only you can complete, or automation that got stuck). Show the browser brings up the login window at the same step; Fail the login stops with the error instead; Ask lets you decide each time.",
+            ));
+            col = col.push(self.view_unified_control(
+                "Session Limit:",
+                self.view_segmented_control(
+                    &["Auto", "8h", "12h", "24h"],
+                    &[0.0, 480.0, 720.0, 1440.0],
+                    self.settings.session_lifetime_mins as f32,
+                    locked(kuvpn::Setting::SessionLifetime),
+                    Message::SessionLifetimeChanged,
+                ),
+                "How long the gateway keeps a VPN session before ending it. Auto learns the limit from your connection history once the tunnel has dropped after the same time a few times.",
+            ));
+            col = col.push(self.view_unified_control(
+                "Expiry Warning:",
+                self.view_segmented_control(
+                    &["Off", "5m", "10m", "15m"],
+                    &[0.0, 5.0, 10.0, 15.0],
+                    self.settings.expiry_warning_mins as f32,
+                    locked(kuvpn::Setting::ExpiryWarning),
+                    Message::ExpiryWarningChanged,
+                ),
+                "How long before the session limit is reached to warn you, so you can save your work before the tunnel drops.",
+            ));
+            col = col.push(self.view_unified_control(
+                "Renew Session:",
+                self.view_segmented_control(
+                    &["Yes", "No"],
+                    &[1.0, 0.0],
+                    if self.settings.renew_before_expiry { 1.0 } else { 0.0 },
+                    locked(kuvpn::Setting::RenewSession),
+                    |val| Message::RenewBeforeExpiryToggled(val > 0.5),
+                ),
+                "When Yes, KUVPN signs in again in the background a few minutes before the session limit and restarts the tunnel with the new session, so the connection only blinks instead of dropping.",
+            ));
+            col = col.push(self.view_unified_control(
+                "Pre-warm Browser:",
+                self.view_segmented_control(
+                    &["Off", "5m", "10m", "30m"],
+                    &[0.0, 5.0, 10.0, 30.0],
+                    self.settings.prewarm_mins as f32,
+                    locked(kuvpn::Setting::Prewarm),
+                    Message::PrewarmChanged,
+                ),
+                "Starts the hidden Full Auto browser ahead of time, when KUVPN opens and a few minutes before the session limit, so connecting skips the browser launch. The browser is closed again after this long unused.",
+            ));
+        }
         // NETWORK section (advanced only)
         if adv {
             col = col.push(divider());
@@ -267,7 +401,7 @@ impl KuVpnGui {
                 "https://vpn.example.com",
                 &self.settings.url,
                 "The HTTPS address of the KU VPN gateway server. Leave as the default (https://vpn.ku.edu.tr) unless IT Support instructs you to use a different server.",
-                is_locked,
+                locked(kuvpn::Setting::Url),
                 Message::UrlChanged,
             ));
             col = col.push(self.view_unified_field(
@@ -275,7 +409,7 @@ impl KuVpnGui {
                 "vpn.example.com",
                 &self.settings.domain,
                 "Hostname used to extract the DSID session cookie after login. Must match the domain of your Gateway URL. Only change this if you changed the Gateway URL.",
-                is_locked,
+                locked(kuvpn::Setting::Domain),
                 Message::DomainChanged,
             ));
 
@@ -294,7 +428,7 @@ impl KuVpnGui {
                 let oc_row = row![
                     text("OC Path:").size(11).width(Length::Fixed(100.0)),
                     text_input(oc_placeholder, &self.settings.openconnect_path)
-                        .on_input(if is_locked {
+                        .on_input(if locked(kuvpn::Setting::OpenconnectPath) {
                             |_| Message::Tick
                         } else {
                             Message::OpenConnectPathChanged
@@ -302,65 +436,14 @@ impl KuVpnGui {
                         .padding(10)
                         .width(Length::Fill)
                         .style(s.text_input()),
-                    {
-                        let test = self.oc_test_result;
-                        let rounding = s.rounding;
-                        button(
-                            text(if test == Some(true) {
-                                "✓"
-                            } else if test == Some(false) {
-                                "✗"
-                            } else {
-                                "Test"
-                            })
-                            .size(11),
-                        )
-                        .padding([8, 12])
-                        .on_press(if is_locked {
+                    self.view_test_button(
+                        self.oc_test_result,
+                        if is_locked {
                             Message::Tick
                         } else {
                             Message::TestOpenConnect
-                        })
-                        .style(move |_, status: button::Status| match test {
-                            Some(true) => button::Style {
-                                background: Some(p.success.into()),
-                                text_color: Color::WHITE,
-                                border: Border {
-                                    radius: rounding.small_radius().into(),
-                                    ..Default::default()
-                                },
-                                ..Default::default()
-                            },
-                            Some(false) => button::Style {
-                                background: Some(p.danger.into()),
-                                text_color: Color::WHITE,
-                                border: Border {
-                                    radius: rounding.small_radius().into(),
-                                    ..Default::default()
-                                },
-                                ..Default::default()
-                            },
-                            None => {
-                                let base = button::Style {
-                                    background: Some(Color::TRANSPARENT.into()),
-                                    text_color: p.text,
-                                    border: Border {
-                                        color: p.border,
-                                        width: rounding.border_width(),
-                                        radius: rounding.small_radius().into(),
-                                    },
-                                    ..Default::default()
-                                };
-                                match status {
-                                    button::Status::Hovered => button::Style {
-                                        background: Some(p.surface.into()),
-                                        ..base
-                                    },
-                                    _ => base,
-                                }
-                            }
-                        })
-                    },
+                        }
+                    ),
                     info_tip(oc_tip, s),
                 ]
                 .spacing(10)
@@ -370,28 +453,99 @@ impl KuVpnGui {
                 col = col.push(oc_path_notif);
             }
 
+            // Browser row, with a Test button like the OC Path one
+            {
+                let browser_row = row![
+                    text("Browser:").size(11).width(Length::Fixed(100.0)),
+                    text_input("Automatic", &self.settings.browser)
+                        .on_input(if locked(kuvpn::Setting::Browser) {
+                            |_| Message::Tick
+                        } else {
+                            Message::BrowserChanged
+                        })
+                        .padding(10)
+                        .width(Length::Fill)
+                        .style(s.text_input()),
+                    self.view_test_button(
+                        self.browser_test_result,
+                        if is_locked {
+                            Message::Tick
+                        } else {
+                            Message::TestBrowser
+                        }
+                    ),
+                    info_tip("Chromium-based browser used for the login: chrome, chromium, ungoogled-chromium, brave, edge, vivaldi, or a path to the executable. Leave blank to use the first one installed. Click Test to see which browser will be used.", s),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center);
+
+                col = col.push(browser_row);
+                if let Some(msg) = &self.browser_notification {
+                    let color = if self.browser_test_result == Some(true) {
+                        p.success
+                    } else {
+                        p.danger
+                    };
+                    col = col.push(
+                        container(
+                            row![
+                                svg(svg::Handle::from_memory(ICON_INFO_SVG))
+                                    .width(13)
+                                    .height(13)
+                                    .style(move |_, _| svg::Style { color: Some(color) }),
+                                text(msg.as_str()).size(10).color(color),
+                            ]
+                            .spacing(6)
+                            .align_y(Alignment::Center),
+                        )
+                        .width(Length::Fill)
+                        .padding([6, 110])
+                        .style(move |_| container::Style {
+                            background: Some(
+                                Color::from_rgba(color.r, color.g, color.b, 0.07).into(),
+                            ),
+                            border: Border {
+                                radius: 6.0.into(),
+                                color: Color::from_rgba(color.r, color.g, color.b, 0.25),
+                                width: 1.0,
+                            },
+                            ..Default::default()
+                        }),
+                    );
+                }
+            }
+
             col = col.push(self.view_unified_control(
                 "Tunnel Mode:",
                 self.view_segmented_control(
                     &["Full", "Manual"],
-                    &[1.0, 2.0],
-                    self.settings.tunnel_mode_val,
-                    is_locked,
+                    &[kuvpn::TunnelKind::Full, kuvpn::TunnelKind::Manual],
+                    self.settings.tunnel_mode,
+                    locked(kuvpn::Setting::TunnelMode),
                     Message::TunnelModeChanged,
                 ),
                 "Full: all traffic is routed through the VPN tunnel.\n\nManual: supply your own vpnc-script for full control over routing and DNS.",
             ));
+            #[cfg(unix)]
+            {
+                col = col.push(self.view_unified_field(
+                    "Interface:",
+                    "kuvpn0",
+                    &self.settings.interface_name,
+                    "Name of the network interface OpenConnect creates for the tunnel. Change it only to keep two setups apart, e.g. in firewall rules.",
+                    locked(kuvpn::Setting::InterfaceName),
+                    Message::InterfaceNameChanged,
+                ));
+            }
             // VPN Script field — visible only in Manual mode
             if self.settings.is_manual_mode() {
-                let script_test = self.vpnc_script_test_result;
-                let rounding = s.rounding;
                 let script_row = row![
                     text("VPN Script:").size(11).width(Length::Fixed(100.0)),
                     text_input(
                         "/usr/share/vpnc-scripts/vpnc-script",
                         &self.settings.vpnc_script,
                     )
-                    .on_input(if is_locked {
+                    .on_input(if locked(kuvpn::Setting::VpncScript) {
                         |_| Message::Tick
                     } else {
                         Message::VpncScriptChanged
@@ -399,61 +553,14 @@ impl KuVpnGui {
                     .padding(10)
                     .width(Length::Fill)
                     .style(s.text_input()),
-                    button(
-                        text(if script_test == Some(true) {
-                            "✓"
-                        } else if script_test == Some(false) {
-                            "✗"
+                    self.view_test_button(
+                        self.vpnc_script_test_result,
+                        if is_locked {
+                            Message::Tick
                         } else {
-                            "Test"
-                        })
-                        .size(11),
-                    )
-                    .padding([8, 12])
-                    .on_press(if is_locked {
-                        Message::Tick
-                    } else {
-                        Message::TestVpncScript
-                    })
-                    .style(move |_, status: button::Status| match script_test {
-                        Some(true) => button::Style {
-                            background: Some(p.success.into()),
-                            text_color: Color::WHITE,
-                            border: Border {
-                                radius: rounding.small_radius().into(),
-                                ..Default::default()
-                            },
-                            ..Default::default()
-                        },
-                        Some(false) => button::Style {
-                            background: Some(p.danger.into()),
-                            text_color: Color::WHITE,
-                            border: Border {
-                                radius: rounding.small_radius().into(),
-                                ..Default::default()
-                            },
-                            ..Default::default()
-                        },
-                        None => {
-                            let base = button::Style {
-                                background: Some(Color::TRANSPARENT.into()),
-                                text_color: p.text,
-                                border: Border {
-                                    color: p.border,
-                                    width: rounding.border_width(),
-                                    radius: rounding.small_radius().into(),
-                                },
-                                ..Default::default()
-                            };
-                            match status {
-                                button::Status::Hovered => button::Style {
-                                    background: Some(p.surface.into()),
-                                    ..base
-                                },
-                                _ => base,
-                            }
+                            Message::TestVpncScript
                         }
-                    }),
+                    ),
                     info_tip("Path to a custom vpnc-script passed to openconnect via --script. The script receives VPN configuration as environment variables and is responsible for all routing and DNS setup. Click Test to verify the file exists.", s),
                 ]
                 .spacing(10)
@@ -527,6 +634,23 @@ impl KuVpnGui {
 
                 col = col.push(script_warn);
             }
+
+            col = col.push(self.view_unified_field(
+                "On Connect:",
+                "e.g. notify-send 'VPN up'",
+                &self.settings.connect_hook,
+                "Shell command run each time the tunnel comes up. KUVPN_EVENT and KUVPN_INTERFACE are set in its environment. Leave empty to run nothing.",
+                locked(kuvpn::Setting::OnConnect),
+                Message::ConnectHookChanged,
+            ));
+            col = col.push(self.view_unified_field(
+                "On Disconnect:",
+                "e.g. notify-send 'VPN down'",
+                &self.settings.disconnect_hook,
+                "Shell command run after the tunnel has been taken down on disconnect. Leave empty to run nothing.",
+                locked(kuvpn::Setting::OnDisconnect),
+                Message::DisconnectHookChanged,
+            ));
         }
 
         // SYSTEM section
@@ -539,10 +663,17 @@ impl KuVpnGui {
                 "Log Level:",
                 self.view_segmented_control(
                     &["Off", "Error", "Warn", "Info", "Debug", "Trace"],
-                    &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0],
-                    self.settings.log_level_val,
+                    &[
+                        log::LevelFilter::Off,
+                        log::LevelFilter::Error,
+                        log::LevelFilter::Warn,
+                        log::LevelFilter::Info,
+                        log::LevelFilter::Debug,
+                        log::LevelFilter::Trace,
+                    ],
+                    self.settings.log_level,
                     false,
-                    Message::LogLevelSliderChanged,
+                    Message::LogLevelChanged,
                 ),
                 "Controls what gets recorded in the Session Log tab. Info is recommended for everyday use. Debug and Trace add detailed technical output for troubleshooting. Off disables all logging.",
             ));
@@ -576,7 +707,7 @@ impl KuVpnGui {
                         self.view_segmented_control_str(
                             &self.available_escalation_tools,
                             &self.settings.escalation_tool,
-                            is_locked,
+                            locked(kuvpn::Setting::EscalationTool),
                             Message::EscalationToolChanged,
                         ),
                         "The privilege escalation tool used to launch OpenConnect as root (required to create the VPN network interface). Only tools installed on your system are listed.",
@@ -625,55 +756,6 @@ impl KuVpnGui {
         {
             let fade = self.notif_fade;
 
-            let wipe_btn: Element<'_, Message> = if let Some(success) = self.session_wipe_result {
-                let (label, fb_color) = if success {
-                    ("✓  WIPED", p.success)
-                } else {
-                    ("✗  FAILED", p.danger)
-                };
-                let (r, g, b) = (fb_color.r, fb_color.g, fb_color.b);
-                button(
-                    text(label)
-                        .size(11)
-                        .color(Color::from_rgba(1.0, 1.0, 1.0, fade)),
-                )
-                .padding([10, 14])
-                .on_press(Message::ClearSessionPressed)
-                .style(move |_, _| button::Style {
-                    background: Some(Color::from_rgba(r, g, b, 0.55 * fade).into()),
-                    border: Border {
-                        radius: 10.0.into(),
-                        color: Color::from_rgba(r, g, b, 0.75 * fade),
-                        width: 1.5,
-                    },
-                    shadow: Shadow {
-                        color: Color::from_rgba(r, g, b, 0.45 * fade),
-                        offset: Vector::new(0.0, 0.0),
-                        blur_radius: 12.0,
-                    },
-                    ..Default::default()
-                })
-                .into()
-            } else {
-                button(
-                    row![
-                        svg(svg::Handle::from_memory(ICON_TRASH_SVG))
-                            .width(13)
-                            .height(13)
-                            .style(move |_, _| svg::Style {
-                                color: Some(p.text)
-                            }),
-                        text("WIPE SESSION").size(11).color(p.text),
-                    ]
-                    .spacing(7)
-                    .align_y(Alignment::Center),
-                )
-                .padding([10, 14])
-                .on_press(Message::ClearSessionPressed)
-                .style(s.btn_secondary())
-                .into()
-            };
-
             let reset_btn: Element<'_, Message> = if self.reset_notification {
                 let sc = p.success;
                 button(
@@ -726,7 +808,21 @@ impl KuVpnGui {
                 .into()
             };
 
-            col = col.push(row([wipe_btn, reset_btn]).spacing(10));
+            col = col.push(
+                row([
+                    self.view_wipe_button(kuvpn::WipeScope::Gateway, "WIPE GATEWAY"),
+                    self.view_wipe_button(kuvpn::WipeScope::Sso, "WIPE SIGN-IN"),
+                    self.view_wipe_button(kuvpn::WipeScope::Cache, "CLEAR CACHE"),
+                ])
+                .spacing(10),
+            );
+            col = col.push(
+                row([
+                    self.view_wipe_button(kuvpn::WipeScope::All, "WIPE ALL"),
+                    reset_btn,
+                ])
+                .spacing(10),
+            );
         }
 
         container(
@@ -747,6 +843,229 @@ impl KuVpnGui {
 
     // ── Field helpers ─────────────────────────────────────────────────────────
 
+    /// The MFA preference order, one row per method with buttons to move or
+    /// remove it, and a picker adding the methods not listed yet.
+    fn view_mfa_order(&self, locked: bool) -> Element<'_, Message> {
+        let s = self.styler();
+        let order = &self.settings.mfa_preference;
+        let small_button = |label: &'static str, message: Option<Message>| {
+            button(text(label).size(11))
+                .padding([4, 8])
+                .on_press_maybe(message.filter(|_| !locked))
+                .style(s.btn_secondary())
+        };
+
+        let mut list = Column::new().spacing(6);
+        for (i, method) in order.iter().enumerate() {
+            list = list.push(
+                row![
+                    text(format!("{}. {}", i + 1, method))
+                        .size(11)
+                        .color(s.p.text)
+                        .width(Length::Fill),
+                    small_button("↑", (i > 0).then_some(Message::MfaMethodMoved(i, true))),
+                    small_button(
+                        "↓",
+                        (i + 1 < order.len()).then_some(Message::MfaMethodMoved(i, false)),
+                    ),
+                    small_button(
+                        "✕",
+                        (order.len() > 1).then_some(Message::MfaMethodRemoved(i)),
+                    ),
+                ]
+                .spacing(4)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        let missing: Vec<kuvpn::MfaMethod> = kuvpn::MfaMethod::ALL
+            .into_iter()
+            .filter(|m| !order.contains(m))
+            .collect();
+        if !missing.is_empty() {
+            list = list.push(
+                pick_list(
+                    missing,
+                    None::<kuvpn::MfaMethod>,
+                    if locked {
+                        |_| Message::Tick
+                    } else {
+                        Message::MfaMethodAdded
+                    },
+                )
+                .placeholder("Add a method")
+                .style(s.pick_list_style())
+                .menu_style(s.pick_list_menu_style())
+                .width(Length::Fill),
+            );
+        }
+        list.width(Length::Fill).into()
+    }
+
+    /// Profile picker with a delete button, and a field to save the current
+    /// settings under a new name.  Switching is only possible while idle.
+    fn view_profile_controls(&self) -> Element<'_, Message> {
+        let s = self.styler();
+        let p = s.p;
+        let idle = matches!(
+            self.status,
+            kuvpn::ConnectionStatus::Disconnected | kuvpn::ConnectionStatus::Error
+        );
+
+        let picker = row![
+            pick_list(
+                self.profiles.names(),
+                self.profiles.active.clone(),
+                if idle {
+                    Message::ProfileSelected
+                } else {
+                    |_| Message::Tick
+                },
+            )
+            .placeholder("No profile selected")
+            .style(s.pick_list_style())
+            .menu_style(s.pick_list_menu_style())
+            .width(Length::Fill),
+            button(
+                svg(svg::Handle::from_memory(ICON_TRASH_SVG))
+                    .width(13)
+                    .height(13)
+                    .style(move |_, _| svg::Style {
+                        color: Some(p.text)
+                    }),
+            )
+            .padding([10, 12])
+            .on_press_maybe(
+                self.profiles
+                    .active
+                    .as_ref()
+                    .map(|_| Message::DeleteProfilePressed)
+            )
+            .style(s.btn_secondary()),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center);
+
+        let save = row![
+            text_input("New profile name", &self.new_profile_name)
+                .on_input(Message::NewProfileNameChanged)
+                .on_submit(Message::SaveProfilePressed)
+                .padding(10)
+                .width(Length::Fill)
+                .style(s.text_input()),
+            button(text("Save").size(11))
+                .padding([10, 14])
+                .on_press_maybe(
+                    (!self.new_profile_name.trim().is_empty())
+                        .then_some(Message::SaveProfilePressed),
+                )
+                .style(s.btn_secondary()),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center);
+
+        Column::new()
+            .spacing(12)
+            .push(self.view_unified_control(
+                "Profile:",
+                picker.into(),
+                "Saved connection setups — gateway, account, login and tunnel mode, and hooks. Picking one loads its settings; changes you make afterwards are saved to it. Also available from the tray menu, and as `kuvpn --profile NAME` in the CLI.",
+            ))
+            .push(self.view_unified_control(
+                "Save As:",
+                save.into(),
+                "Saves the current connection settings as a new profile (or over the profile with that name) and selects it.",
+            ))
+            .into()
+    }
+
+    /// A wipe action button for `scope`, showing the outcome of the last
+    /// wipe of that scope while the notification fades.
+    fn view_wipe_button(
+        &self,
+        scope: kuvpn::WipeScope,
+        label: &'static str,
+    ) -> Element<'_, Message> {
+        let s = self.styler();
+        let p = s.p;
+        let fade = self.notif_fade;
+
+        if let Some((_, success)) = self.session_wipe_result.filter(|&(w, _)| w == scope) {
+            let (label, fb_color) = if success {
+                ("✓  WIPED", p.success)
+            } else {
+                ("✗  FAILED", p.danger)
+            };
+            let (r, g, b) = (fb_color.r, fb_color.g, fb_color.b);
+            button(
+                text(label)
+                    .size(11)
+                    .color(Color::from_rgba(1.0, 1.0, 1.0, fade)),
+            )
+            .padding([10, 14])
+            .on_press(Message::WipeSessionPressed(scope))
+            .style(move |_, _| button::Style {
+                background: Some(Color::from_rgba(r, g, b, 0.55 * fade).into()),
+                border: Border {
+                    radius: 10.0.into(),
+                    color: Color::from_rgba(r, g, b, 0.75 * fade),
+                    width: 1.5,
+                },
+                shadow: Shadow {
+                    color: Color::from_rgba(r, g, b, 0.45 * fade),
+                    offset: Vector::new(0.0, 0.0),
+                    blur_radius: 12.0,
+                },
+                ..Default::default()
+            })
+            .into()
+        } else {
+            button(
+                row![
+                    svg(svg::Handle::from_memory(ICON_TRASH_SVG))
+                        .width(13)
+                        .height(13)
+                        .style(move |_, _| svg::Style {
+                            color: Some(p.text)
+                        }),
+                    text(label).size(11).color(p.text),
+                ]
+                .spacing(7)
+                .align_y(Alignment::Center),
+            )
+            .padding([10, 14])
+            .on_press(Message::WipeSessionPressed(scope))
+            .style(s.btn_secondary())
+            .into()
+        }
+    }
+
+    /// Explains which settings the administrator's policy file fixes.
+    fn view_policy_notice(&self) -> Element<'_, Message> {
+        let p = self.styler().p;
+        let names: Vec<&str> = self.policy.locked.iter().map(|k| k.label()).collect();
+        container(
+            row![
+                svg(svg::Handle::from_memory(ICON_INFO_SVG))
+                    .width(13)
+                    .height(13)
+                    .style(move |_, _| svg::Style {
+                        color: Some(p.text_muted)
+                    }),
+                text(format!(
+                    "Set by your administrator and read-only: {}.",
+                    names.join(", ")
+                ))
+                .size(11)
+                .color(p.text_muted),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        )
+        .padding(5)
+        .into()
+    }
+
     fn view_unified_field<'a>(
         &self,
         label: &'a str,
@@ -775,6 +1094,64 @@ impl KuVpnGui {
         .into()
     }
 
+    /// The Test button next to a path field, showing ✓ or ✗ once `result`
+    /// is known.
+    fn view_test_button(&self, result: Option<bool>, on_press: Message) -> Element<'_, Message> {
+        let s = self.styler();
+        let p = s.p;
+        let rounding = s.rounding;
+        button(
+            text(match result {
+                Some(true) => "✓",
+                Some(false) => "✗",
+                None => "Test",
+            })
+            .size(11),
+        )
+        .padding([8, 12])
+        .on_press(on_press)
+        .style(move |_, status: button::Status| match result {
+            Some(true) => button::Style {
+                background: Some(p.success.into()),
+                text_color: Color::WHITE,
+                border: Border {
+                    radius: rounding.small_radius().into(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            Some(false) => button::Style {
+                background: Some(p.danger.into()),
+                text_color: Color::WHITE,
+                border: Border {
+                    radius: rounding.small_radius().into(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            None => {
+                let base = button::Style {
+                    background: Some(Color::TRANSPARENT.into()),
+                    text_color: p.text,
+                    border: Border {
+                        color: p.border,
+                        width: rounding.border_width(),
+                        radius: rounding.small_radius().into(),
+                    },
+                    ..Default::default()
+                };
+                match status {
+                    button::Status::Hovered => button::Style {
+                        background: Some(p.surface.into()),
+                        ..base
+                    },
+                    _ => base,
+                }
+            }
+        })
+        .into()
+    }
+
     fn view_unified_control<'a>(
         &self,
         label: &'a str,
@@ -798,13 +1175,13 @@ impl KuVpnGui {
 
     // ── Segmented controls ────────────────────────────────────────────────────
 
-    fn view_segmented_control<'a>(
+    fn view_segmented_control<'a, T: Copy + PartialEq>(
         &self,
         options: &'a [&'static str],
-        values: &'a [f32],
-        current_value: f32,
+        values: &'a [T],
+        current_value: T,
         locked: bool,
-        on_change: fn(f32) -> Message,
+        on_change: fn(T) -> Message,
     ) -> Element<'a, Message> {
         let s = self.styler();
         let buttons: Vec<Element<'a, Message>> = options
@@ -812,7 +1189,7 @@ impl KuVpnGui {
             .zip(values.iter())
             .enumerate()
             .map(|(idx, (label, &value))| {
-                let is_selected = (current_value - value).abs() < 0.1;
+                let is_selected = current_value == value;
 
                 let position = if options.len() == 1 {
                     SegmentPosition::Single
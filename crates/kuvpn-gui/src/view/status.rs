@@ -1,7 +1,7 @@
 use crate::app::KuVpnGui;
 use crate::types::{
-    Message, ICON_CLOCK_SVG, ICON_INFO_SVG, ICON_PHONE_SVG, ICON_REFRESH_SVG,
-    ICON_SHIELD_CHECK_SVG, ICON_SHIELD_SVG, ICON_SHIELD_X_SVG,
+    Message, MfaPrompt, ICON_CLOCK_SVG, ICON_INFO_SVG, ICON_LOCK_SVG, ICON_PHONE_SVG,
+    ICON_REFRESH_SVG, ICON_SHIELD_CHECK_SVG, ICON_SHIELD_SVG, ICON_SHIELD_X_SVG,
 };
 use iced::widget::{button, column, container, row, stack, svg, text, Space};
 use iced::{Alignment, Border, Color, Element, Font, Length, Shadow, Vector};
@@ -204,6 +204,25 @@ impl KuVpnGui {
             #[allow(unused_mut)]
             let mut details_row = row![duration_pill].spacing(8).align_y(Alignment::Center);
 
+            if let Some(remaining) = self.session.as_ref().and_then(|s| s.expires_in()) {
+                let mins = remaining.as_secs().div_ceil(60);
+                let warn_mins = self.settings.expiry_warning_mins;
+                let color = if warn_mins > 0 && mins <= warn_mins {
+                    p.warning
+                } else {
+                    p.text_muted
+                };
+                let label = if mins >= 60 {
+                    format!("Expires in {}h {}m", mins / 60, mins % 60)
+                } else {
+                    format!("Expires in {}m", mins)
+                };
+                let expiry_pill = container(text(label).size(12).color(color))
+                    .padding([6, 14])
+                    .style(s.pill(color));
+                details_row = details_row.push(expiry_pill);
+            }
+
             #[cfg(unix)]
             if let Some(ref iface_name) = self.active_interface {
                 let iface_display = iface_name.clone();
@@ -234,10 +253,106 @@ impl KuVpnGui {
         }
     }
 
+    /// Pill telling whether the saved Microsoft sign-in is still valid, i.e.
+    /// whether the next connect is expected to ask for MFA.  Hovering shows
+    /// when a DSID and MFA were last obtained.
+    pub fn view_session_freshness<'a>(&'a self, info: &kuvpn::SessionInfo) -> Element<'a, Message> {
+        use iced::widget::tooltip;
+
+        let s = self.styler();
+        let p = s.p;
+
+        let (label, color) = match info.sso_expires {
+            Some(expires) if info.sso_valid() => (
+                format!(
+                    "Sign-in saved until {}",
+                    &kuvpn::format_timestamp_unix(expires)[..10]
+                ),
+                p.success,
+            ),
+            _ if !info.has_profile => ("No saved sign-in — MFA needed".to_string(), p.warning),
+            _ => ("Sign-in expired — MFA needed".to_string(), p.warning),
+        };
+        let when = |ts: Option<u64>| {
+            ts.map(|t| format!("{} UTC", kuvpn::format_timestamp_unix(t)))
+                .unwrap_or_else(|| "never".to_string())
+        };
+        let details = format!(
+            "Last DSID: {}\nLast MFA: {}",
+            when(info.last_dsid),
+            when(info.last_mfa)
+        );
+
+        let pill = container(
+            row![
+                svg(svg::Handle::from_memory(ICON_LOCK_SVG))
+                    .width(14)
+                    .height(14)
+                    .style(move |_, _| svg::Style { color: Some(color) }),
+                text(label).size(12).color(color),
+            ]
+            .spacing(6)
+            .align_y(Alignment::Center),
+        )
+        .padding([6, 14])
+        .style(s.pill(color));
+
+        container(
+            tooltip(
+                pill,
+                container(text(details).size(11).color(p.text))
+                    .padding([7, 10])
+                    .style(s.tooltip_container()),
+                tooltip::Position::Bottom,
+            )
+            .gap(4),
+        )
+        .width(Length::Fill)
+        .center_x(Length::Fill)
+        .into()
+    }
+
     /// MFA approval banner with phone icon and prominent code
-    pub fn view_mfa_card<'a>(&'a self, code: &'a str) -> Element<'a, Message> {
+    pub fn view_mfa_card<'a>(&'a self, prompt: &'a MfaPrompt) -> Element<'a, Message> {
         let s = self.styler();
         let p = s.p;
+        let (title, instruction): (&str, Element<'a, Message>) = match prompt {
+            MfaPrompt::Push(code) => (
+                "Approve Sign-In",
+                row![
+                    text("Enter ").size(12).color(p.text_muted),
+                    container(
+                        text(code.as_str())
+                            .size(20)
+                            .color(p.warning)
+                            .font(Font::MONOSPACE)
+                    )
+                    .padding([2, 10])
+                    .style(s.code_badge(p.warning)),
+                    text(" in Authenticator").size(12).color(p.text_muted),
+                ]
+                .spacing(4)
+                .align_y(Alignment::Center)
+                .into(),
+            ),
+            MfaPrompt::Call => (
+                "Answer the Call",
+                text("Microsoft is calling your phone — answer it to approve the sign-in")
+                    .size(12)
+                    .color(p.text_muted)
+                    .into(),
+            ),
+            MfaPrompt::Browser(page) => (
+                "Continue in Browser",
+                text(format!(
+                    "{} needs your input — finish it in the browser window",
+                    page
+                ))
+                .size(12)
+                .color(p.text_muted)
+                .into(),
+            ),
+        };
         container(
             column![
                 row![
@@ -247,23 +362,13 @@ impl KuVpnGui {
                         .style(move |_, _| svg::Style {
                             color: Some(p.warning)
                         }),
-                    text("Approve Sign-In").size(14).color(p.warning),
+                    text(title).size(14).color(p.warning),
                 ]
                 .spacing(10)
                 .align_y(Alignment::Center),
-                container(
-                    row![
-                        text("Enter ").size(12).color(p.text_muted),
-                        container(text(code).size(20).color(p.warning).font(Font::MONOSPACE))
-                            .padding([2, 10])
-                            .style(s.code_badge(p.warning)),
-                        text(" in Authenticator").size(12).color(p.text_muted),
-                    ]
-                    .spacing(4)
-                    .align_y(Alignment::Center)
-                )
-                .width(Length::Fill)
-                .center_x(Length::Fill),
+                container(instruction)
+                    .width(Length::Fill)
+                    .center_x(Length::Fill),
             ]
             .spacing(10)
             .align_x(Alignment::Center),
@@ -1,9 +1,37 @@
-use headless_chrome::browser::default_executable;
-use headless_chrome::{Browser, LaunchOptions};
+use headless_chrome::protocol::cdp::Browser as BrowserDomain;
+use headless_chrome::{Browser, LaunchOptions, Tab};
 use std::error::Error;
 use std::ffi::{OsStr, OsString};
+use std::sync::Mutex;
 use std::time::Duration;
 
+/// Held by [`with_profile_tab`] while its short-lived browser is open, so a
+/// login started meanwhile waits instead of failing on the profile lock (and
+/// wiping the profile in the retry).
+static PROFILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// How a launched browser shows itself.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Window {
+    Headless,
+    /// A real window, started off-screen so it stays out of the way until a
+    /// headless login needs the user and reveals it.
+    Hidden,
+    Visible,
+}
+
+/// Whether a browser window can be shown on this machine.  On Linux that
+/// needs an X11 or Wayland display; SSH sessions and servers have neither.
+pub(crate) fn can_show_window() -> bool {
+    if cfg!(target_os = "linux") {
+        ["DISPLAY", "WAYLAND_DISPLAY"]
+            .iter()
+            .any(|var| std::env::var_os(var).is_some_and(|v| !v.is_empty()))
+    } else {
+        true
+    }
+}
+
 /// Creates a browser instance configured with a blank page, a custom user agent,
 /// and a dedicated user data directory.
 ///
@@ -29,57 +57,19 @@ pub fn create_browser(
     headless: bool,
     manual_mode: bool,
 ) -> Result<Browser, Box<dyn Error>> {
-    let user_agent = OsString::from(format!("--user-agent={agent}"));
-
-    let mut attempts = 0;
-    loop {
-        let user_data_dir = crate::utils::get_user_data_dir()?;
-
-        // Timeout based on mode:
-        // - Headless (auto): 2 minutes - the flow is fully automated, shouldn't take long
-        // - Manual mode: 10 minutes - user might interact slowly or be away from screen
-        let idle_timeout = if manual_mode {
-            Duration::from_secs(600) // 10 minutes for manual modes
-        } else {
-            Duration::from_secs(120) // 2 minutes for headless mode
-        };
-        log::info!(
-            "Browser idle timeout: {}s ({})",
-            idle_timeout.as_secs(),
-            if manual_mode { "manual mode" } else { "headless mode" }
-        );
+    crate::prewarm::discard_warm_browser();
+    drop(PROFILE_LOCK.lock().unwrap_or_else(|e| e.into_inner()));
 
-        // --new-window on Windows causes Chrome to open a window that is not
-        // attached to the CDP session; headless_chrome then controls a blank
-        // internal tab while the visible window is unreachable.
-        #[allow(unused_mut)]
-        let mut args: Vec<&OsStr> = vec![
-            user_agent.as_os_str(),
-            OsStr::new("--no-first-run"),
-            OsStr::new("--no-default-browser-check"),
-            OsStr::new("--disable-session-crashed-bubble"),
-            OsStr::new("--lang=en-US"),
-        ];
-        #[cfg(not(target_os = "windows"))]
-        args.push(OsStr::new("--new-window"));
-
-        let mut options = LaunchOptions::default_builder();
-        let mut launch_options = options
-            .headless(headless)
-            .sandbox(false)
-            .idle_browser_timeout(idle_timeout)
-            .window_size(Some((800, 800)))
-            .enable_gpu(false)
-            .args(args)
-            .user_data_dir(Some(user_data_dir));
-
-        if let Ok(path) = std::env::var("KUVPN_CHROME_PATH") {
-            launch_options = launch_options.path(Some(path.into()));
-        } else if let Ok(executable_path) = default_executable() {
-            launch_options = launch_options.path(Some(executable_path));
+    // A missing browser is not fixed by wiping the profile.
+    if let Err(e) = crate::browser_discovery::resolve_chosen_browser() {
+        if e.choice.is_some() {
+            return Err(e.into());
         }
+    }
 
-        match Browser::new(launch_options.build()?) {
+    let mut attempts = 0;
+    loop {
+        match launch_browser(agent, headless, manual_mode) {
             Ok(browser) => return Ok(browser),
             Err(e) => {
                 attempts += 1;
@@ -92,3 +82,129 @@ pub fn create_browser(
         }
     }
 }
+
+/// Launches the browser once on the user data directory, without the
+/// wipe-and-retry of [`create_browser`].
+pub(crate) fn launch_browser(
+    agent: &str,
+    headless: bool,
+    manual_mode: bool,
+) -> Result<Browser, Box<dyn Error>> {
+    // Timeout based on mode:
+    // - Headless (auto): 2 minutes - the flow is fully automated, shouldn't take long
+    // - Manual mode: 10 minutes - user might interact slowly or be away from screen
+    let idle_timeout = if manual_mode {
+        Duration::from_secs(600) // 10 minutes for manual modes
+    } else {
+        Duration::from_secs(120) // 2 minutes for headless mode
+    };
+    log::info!(
+        "Browser idle timeout: {}s ({})",
+        idle_timeout.as_secs(),
+        if manual_mode { "manual mode" } else { "headless mode" }
+    );
+    let window = if headless {
+        Window::Headless
+    } else {
+        Window::Visible
+    };
+    launch_with_timeout(agent, window, idle_timeout)
+}
+
+/// Launches a browser for a headless login in a [`Window::Hidden`] window,
+/// waiting for [`with_profile_tab`] like [`create_browser`] does.  There is
+/// no wipe-and-retry: where no window can be opened, the caller falls back
+/// to [`create_browser`].
+pub(crate) fn create_hidden_browser(agent: &str) -> Result<Browser, Box<dyn Error>> {
+    crate::prewarm::discard_warm_browser();
+    drop(PROFILE_LOCK.lock().unwrap_or_else(|e| e.into_inner()));
+    launch_with_timeout(agent, Window::Hidden, Duration::from_secs(120))
+}
+
+/// Launches a headless or hidden browser for [`crate::prewarm`], waiting for
+/// [`with_profile_tab`] like [`create_browser`] does.  There is no
+/// wipe-and-retry: a failed pre-warm is left to the login to sort out.
+///
+/// The browser stays connected for `idle` plus the usual headless timeout,
+/// so it survives sitting unused until it is handed over or torn down.
+pub(crate) fn launch_warm_browser(
+    agent: &str,
+    window: Window,
+    idle: Duration,
+) -> Result<Browser, Box<dyn Error>> {
+    drop(PROFILE_LOCK.lock().unwrap_or_else(|e| e.into_inner()));
+    launch_with_timeout(agent, window, idle + Duration::from_secs(120))
+}
+
+fn launch_with_timeout(
+    agent: &str,
+    window: Window,
+    idle_timeout: Duration,
+) -> Result<Browser, Box<dyn Error>> {
+    let user_agent = OsString::from(format!("--user-agent={agent}"));
+    let user_data_dir = crate::utils::get_user_data_dir()?;
+
+    // --new-window on Windows causes Chrome to open a window that is not
+    // attached to the CDP session; headless_chrome then controls a blank
+    // internal tab while the visible window is unreachable.
+    #[allow(unused_mut)]
+    let mut args: Vec<&OsStr> = vec![
+        user_agent.as_os_str(),
+        OsStr::new("--no-first-run"),
+        OsStr::new("--no-default-browser-check"),
+        OsStr::new("--disable-session-crashed-bubble"),
+        OsStr::new("--lang=en-US"),
+    ];
+    #[cfg(not(target_os = "windows"))]
+    args.push(OsStr::new("--new-window"));
+    if window == Window::Hidden {
+        args.push(OsStr::new("--window-position=-32000,-32000"));
+    }
+
+    let mut options = LaunchOptions::default_builder();
+    let mut launch_options = options
+        .headless(window == Window::Headless)
+        .sandbox(false)
+        .idle_browser_timeout(idle_timeout)
+        .window_size(Some((800, 800)))
+        .enable_gpu(false)
+        .args(args)
+        .user_data_dir(Some(user_data_dir));
+
+    let not_found = match crate::browser_discovery::resolve_chosen_browser() {
+        Ok(executable) => {
+            log::info!("Using browser: {}", executable.display());
+            launch_options = launch_options.path(Some(executable));
+            None
+        }
+        Err(e) if e.choice.is_some() => return Err(e.into()),
+        Err(e) => {
+            log::warn!("No installed browser found, downloading Chromium instead");
+            Some(e)
+        }
+    };
+
+    Browser::new(launch_options.build()?).map_err(|err| match not_found {
+        Some(e) => format!("{}\nDownloading Chromium failed as well: {}", e, err).into(),
+        None => err.into(),
+    })
+}
+
+/// Opens the user data directory in a short-lived headless browser, runs `f`
+/// on a blank tab and shuts the browser down again.
+///
+/// Cookie changes are written to disk lazily, and dropping the `Browser`
+/// kills the process right after requesting a close, so Chrome is first asked
+/// to exit on its own and given a moment to do so.
+pub(crate) fn with_profile_tab<T>(
+    f: impl FnOnce(&Tab) -> Result<T, Box<dyn Error>>,
+) -> Result<T, Box<dyn Error>> {
+    crate::prewarm::discard_warm_browser();
+    let _guard = PROFILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let browser = launch_browser("Mozilla/5.0", true, false)?;
+    let tab = browser.new_tab()?;
+    let result = f(&tab);
+    let _ = tab.call_method(BrowserDomain::Close(None));
+    std::thread::sleep(Duration::from_secs(1));
+    result
+}
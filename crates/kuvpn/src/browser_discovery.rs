@@ -0,0 +1,338 @@
+//! Finding a Chromium-family browser to drive the login with.
+//!
+//! Any browser built on Chromium speaks the DevTools protocol the login
+//! needs, so besides Chrome and Chromium this looks for Brave, Edge, Vivaldi
+//! and ungoogled-chromium, including their Flatpak and Snap wrappers.  The
+//! browser is picked by, in order:
+//!
+//! 1. `KUVPN_CHROME_PATH`, a path to the executable;
+//! 2. the `browser` setting, a path or the id of a known browser (`brave`);
+//! 3. the first installed browser, in the order of [`KNOWN_BROWSERS`].
+//!
+//! When nothing is chosen and nothing is installed, the browser launch falls
+//! back to a Chromium build downloaded by `headless_chrome`.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A Chromium-family browser KUVPN knows how to find.
+pub struct KnownBrowser {
+    /// Short identifier used in settings (`brave`).
+    pub id: &'static str,
+    /// Display name.
+    pub name: &'static str,
+    /// Command names searched on `PATH`.
+    pub commands: &'static [&'static str],
+    /// Flatpak application ID.
+    pub flatpak: Option<&'static str>,
+    /// Snap name.
+    pub snap: Option<&'static str>,
+    /// macOS executable inside `/Applications`; empty if not made for macOS.
+    pub mac_app: &'static str,
+    /// Executable under `Program Files` or `%LOCALAPPDATA%`; empty if not
+    /// made for Windows.
+    pub windows_exe: &'static str,
+}
+
+/// The browsers searched for, in order of preference.
+pub const KNOWN_BROWSERS: &[KnownBrowser] = &[
+    KnownBrowser {
+        id: "chrome",
+        name: "Google Chrome",
+        commands: &["google-chrome-stable", "google-chrome", "chrome"],
+        flatpak: Some("com.google.Chrome"),
+        snap: None,
+        mac_app: "Google Chrome.app/Contents/MacOS/Google Chrome",
+        windows_exe: r"Google\Chrome\Application\chrome.exe",
+    },
+    KnownBrowser {
+        id: "chromium",
+        name: "Chromium",
+        commands: &["chromium", "chromium-browser"],
+        flatpak: Some("org.chromium.Chromium"),
+        snap: Some("chromium"),
+        mac_app: "Chromium.app/Contents/MacOS/Chromium",
+        windows_exe: r"Chromium\Application\chrome.exe",
+    },
+    KnownBrowser {
+        id: "ungoogled-chromium",
+        name: "ungoogled-chromium",
+        commands: &["ungoogled-chromium"],
+        flatpak: Some("io.github.ungoogled_software.ungoogled_chromium"),
+        snap: None,
+        mac_app: "",
+        windows_exe: "",
+    },
+    KnownBrowser {
+        id: "brave",
+        name: "Brave",
+        commands: &["brave-browser", "brave-browser-stable", "brave"],
+        flatpak: Some("com.brave.Browser"),
+        snap: Some("brave"),
+        mac_app: "Brave Browser.app/Contents/MacOS/Brave Browser",
+        windows_exe: r"BraveSoftware\Brave-Browser\Application\brave.exe",
+    },
+    KnownBrowser {
+        id: "edge",
+        name: "Microsoft Edge",
+        commands: &["microsoft-edge-stable", "microsoft-edge"],
+        flatpak: Some("com.microsoft.Edge"),
+        snap: None,
+        mac_app: "Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+        windows_exe: r"Microsoft\Edge\Application\msedge.exe",
+    },
+    KnownBrowser {
+        id: "vivaldi",
+        name: "Vivaldi",
+        commands: &["vivaldi-stable", "vivaldi"],
+        flatpak: Some("com.vivaldi.Vivaldi"),
+        snap: None,
+        mac_app: "Vivaldi.app/Contents/MacOS/Vivaldi",
+        windows_exe: r"Vivaldi\Application\vivaldi.exe",
+    },
+];
+
+/// How an installed browser was packaged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserSource {
+    System,
+    Flatpak,
+    Snap,
+}
+
+/// A browser found on this system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledBrowser {
+    /// Id of the [`KnownBrowser`] it is.
+    pub id: &'static str,
+    pub name: &'static str,
+    pub source: BrowserSource,
+    /// Executable (or Flatpak/Snap wrapper) to launch.
+    pub path: PathBuf,
+}
+
+impl fmt::Display for InstalledBrowser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.source {
+            BrowserSource::System => write!(f, "{}", self.name)?,
+            BrowserSource::Flatpak => write!(f, "{} (Flatpak)", self.name)?,
+            BrowserSource::Snap => write!(f, "{} (Snap)", self.name)?,
+        }
+        write!(f, " — {}", self.path.display())
+    }
+}
+
+/// No usable browser was found.  The message lists where KUVPN looked.
+#[derive(Debug)]
+pub struct BrowserNotFound {
+    /// The `browser` setting that could not be resolved, if one was given.
+    pub choice: Option<String>,
+    /// Places searched, e.g. `PATH: google-chrome, chromium`.
+    pub searched: Vec<String>,
+    /// Browsers that were found but not chosen.
+    pub installed: Vec<InstalledBrowser>,
+}
+
+impl fmt::Display for BrowserNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.choice {
+            Some(choice) => writeln!(f, "The browser '{}' was not found.", choice)?,
+            None => writeln!(f, "No Chromium-based browser was found.")?,
+        }
+        writeln!(f, "Searched:")?;
+        for place in &self.searched {
+            writeln!(f, "  {}", place)?;
+        }
+        if self.installed.is_empty() {
+            write!(
+                f,
+                "Install Chrome, Chromium, Brave, Edge or Vivaldi, or point KUVPN_CHROME_PATH at a Chromium-based browser."
+            )
+        } else {
+            writeln!(f, "Installed:")?;
+            for browser in &self.installed {
+                writeln!(f, "  {}", browser)?;
+            }
+            write!(f, "Choose one of them in the browser setting.")
+        }
+    }
+}
+
+impl std::error::Error for BrowserNotFound {}
+
+/// The `browser` setting applied to every browser launch in this process.
+static BROWSER_CHOICE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Sets which browser later launches use: a path, the id of a
+/// [`KnownBrowser`], or `None` (or empty) for the first one installed.
+pub fn set_browser_choice(choice: Option<String>) {
+    let choice = choice.filter(|c| !c.trim().is_empty());
+    *BROWSER_CHOICE.lock().unwrap_or_else(|e| e.into_inner()) = choice;
+}
+
+/// The choice last given to [`set_browser_choice`].
+pub fn browser_choice() -> Option<String> {
+    BROWSER_CHOICE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Every known browser installed on this system, in order of preference.
+pub fn discover_browsers() -> Vec<InstalledBrowser> {
+    let mut found = Vec::new();
+    for known in KNOWN_BROWSERS {
+        for (source, path) in candidates(known) {
+            if path.is_file() && !found.iter().any(|b: &InstalledBrowser| b.path == path) {
+                found.push(InstalledBrowser {
+                    id: known.id,
+                    name: known.name,
+                    source,
+                    path,
+                });
+            }
+        }
+    }
+    found
+}
+
+/// Resolves the browser to launch for `choice` (see the module docs).
+pub fn resolve_browser(choice: Option<&str>) -> Result<PathBuf, BrowserNotFound> {
+    if let Ok(path) = std::env::var("KUVPN_CHROME_PATH") {
+        return Ok(PathBuf::from(path));
+    }
+    let choice = choice.map(str::trim).filter(|c| !c.is_empty());
+    let installed = discover_browsers();
+
+    let found = match choice {
+        Some(choice) if Path::new(choice).is_absolute() => {
+            Path::new(choice).is_file().then(|| PathBuf::from(choice))
+        }
+        Some(choice) => installed
+            .iter()
+            .find(|b| b.id.eq_ignore_ascii_case(choice) || b.name.eq_ignore_ascii_case(choice))
+            .map(|b| b.path.clone())
+            .or_else(|| which::which(choice).ok()),
+        None => installed
+            .first()
+            .map(|b| b.path.clone())
+            .or_else(|| headless_chrome::browser::default_executable().ok()),
+    };
+    found.ok_or_else(|| BrowserNotFound {
+        choice: choice.map(str::to_string),
+        searched: searched_places(),
+        installed,
+    })
+}
+
+/// Resolves the browser chosen with [`set_browser_choice`].
+pub fn resolve_chosen_browser() -> Result<PathBuf, BrowserNotFound> {
+    resolve_browser(browser_choice().as_deref())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn candidates(known: &KnownBrowser) -> Vec<(BrowserSource, PathBuf)> {
+    let mut paths: Vec<(BrowserSource, PathBuf)> = known
+        .commands
+        .iter()
+        .filter_map(|cmd| which::which(cmd).ok())
+        .map(|p| (BrowserSource::System, p))
+        .collect();
+    if let Some(app) = known.flatpak {
+        paths.extend(
+            flatpak_export_dirs()
+                .into_iter()
+                .map(|dir| (BrowserSource::Flatpak, dir.join(app))),
+        );
+    }
+    if let Some(snap) = known.snap {
+        paths.push((BrowserSource::Snap, Path::new("/snap/bin").join(snap)));
+    }
+    paths
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn flatpak_export_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from("/var/lib/flatpak/exports/bin")];
+    if let Ok(home) = std::env::var("HOME") {
+        dirs.push(Path::new(&home).join(".local/share/flatpak/exports/bin"));
+    }
+    dirs
+}
+
+#[cfg(target_os = "macos")]
+fn candidates(known: &KnownBrowser) -> Vec<(BrowserSource, PathBuf)> {
+    if known.mac_app.is_empty() {
+        return Vec::new();
+    }
+    let mut roots = vec![PathBuf::from("/Applications")];
+    if let Ok(home) = std::env::var("HOME") {
+        roots.push(Path::new(&home).join("Applications"));
+    }
+    roots
+        .into_iter()
+        .map(|root| (BrowserSource::System, root.join(known.mac_app)))
+        .collect()
+}
+
+#[cfg(windows)]
+fn candidates(known: &KnownBrowser) -> Vec<(BrowserSource, PathBuf)> {
+    if known.windows_exe.is_empty() {
+        return Vec::new();
+    }
+    windows_roots()
+        .into_iter()
+        .map(|root| (BrowserSource::System, root.join(known.windows_exe)))
+        .collect()
+}
+
+#[cfg(windows)]
+fn windows_roots() -> Vec<PathBuf> {
+    ["ProgramFiles", "ProgramFiles(x86)", "LOCALAPPDATA"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Human-readable list of the places [`discover_browsers`] looks in.
+fn searched_places() -> Vec<String> {
+    let mut places = vec!["KUVPN_CHROME_PATH (not set)".to_string()];
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let commands: Vec<&str> = KNOWN_BROWSERS
+            .iter()
+            .flat_map(|b| b.commands.iter().copied())
+            .collect();
+        places.push(format!("PATH: {}", commands.join(", ")));
+        for dir in flatpak_export_dirs() {
+            let apps: Vec<&str> = KNOWN_BROWSERS.iter().filter_map(|b| b.flatpak).collect();
+            places.push(format!("{}: {}", dir.display(), apps.join(", ")));
+        }
+        let snaps: Vec<&str> = KNOWN_BROWSERS.iter().filter_map(|b| b.snap).collect();
+        places.push(format!("/snap/bin: {}", snaps.join(", ")));
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let apps: Vec<&str> = KNOWN_BROWSERS
+            .iter()
+            .map(|b| b.mac_app)
+            .filter(|a| !a.is_empty())
+            .collect();
+        places.push(format!(
+            "/Applications and ~/Applications: {}",
+            apps.join(", ")
+        ));
+    }
+    #[cfg(windows)]
+    for root in windows_roots() {
+        let exes: Vec<&str> = KNOWN_BROWSERS
+            .iter()
+            .map(|b| b.windows_exe)
+            .filter(|e| !e.is_empty())
+            .collect();
+        places.push(format!("{}: {}", root.display(), exes.join(", ")));
+    }
+    places
+}
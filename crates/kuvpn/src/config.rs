@@ -0,0 +1,437 @@
+//! Connection settings file shared by the CLI and the GUI.
+//!
+//! The file is `config.toml` in the platform config directory
+//! (`$XDG_CONFIG_HOME/kuvpn` or `~/.config/kuvpn` on Linux,
+//! `~/Library/Application Support/kuvpn` on macOS,
+//! `%USERPROFILE%\AppData\Roaming\kuvpn` on Windows).  Every key is
+//! optional; unset keys keep the built-in default.  Each key can also be set
+//! through a `KUVPN_*` environment variable named after it (`login-mode` →
+//! `KUVPN_LOGIN_MODE`), which wins over the file.
+//!
+//! ```toml
+//! url = "https://vpn.ku.edu.tr"
+//! domain = "vpn.ku.edu.tr"
+//! email = "jdoe24@ku.edu.tr"
+//! login-mode = "full-auto"        # full-auto | visual | manual
+//! login-engine = "browser"        # browser | http
+//! browser = "brave"               # known browser id or a path; first found if unset
+//! tunnel-mode = "full"            # full | manual
+//! vpnc-script = "/etc/vpnc/vpnc-script"
+//! escalation-tool = "sudo"
+//! openconnect-path = "openconnect"
+//! interface-name = "kuvpn0"
+//! mfa-preference = ["push", "sms", "first-available"]
+//! max-push-resends = 2
+//! lockout-threshold = 3
+//! lockout-cooldown = 15           # minutes
+//! session-conflict = "ask"        # ask | close-all | keep-all
+//! visible-fallback = "allow"      # allow | deny | ask
+//! session-lifetime = 720          # minutes; learned from history if unset
+//! expiry-warning = 10             # minutes; 0 disables
+//! renew-session = false
+//! prewarm = 10                    # minutes a pre-warmed browser is kept; 0 disables
+//! on-connect = "notify-send 'VPN up'"
+//! on-disconnect = "notify-send 'VPN down'"
+//! profile = "lab"                 # profile the CLI uses without --profile
+//! ```
+//!
+//! Appearance and window settings of the GUI are not part of this file.
+
+use crate::dsid::{LoginEngine, MfaMethod, SessionConflictPolicy, VisibleFallback};
+use crate::profile::{LoginMode, TunnelKind};
+use serde::de::{DeserializeOwned, IntoDeserializer};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Connection settings read from `config.toml` and the environment.
+/// `None` means "not set here".
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    pub url: Option<String>,
+    pub domain: Option<String>,
+    pub email: Option<String>,
+    pub login_mode: Option<LoginMode>,
+    pub login_engine: Option<LoginEngine>,
+    /// Browser for the login: a path or a known browser id (`brave`).
+    pub browser: Option<String>,
+    pub tunnel_mode: Option<TunnelKind>,
+    pub vpnc_script: Option<String>,
+    pub escalation_tool: Option<String>,
+    pub openconnect_path: Option<String>,
+    pub interface_name: Option<String>,
+    pub mfa_preference: Option<Vec<MfaMethod>>,
+    pub max_push_resends: Option<u32>,
+    pub lockout_threshold: Option<u32>,
+    /// Minutes.
+    pub lockout_cooldown: Option<u64>,
+    pub session_conflict: Option<SessionConflictPolicy>,
+    pub visible_fallback: Option<VisibleFallback>,
+    /// Minutes.
+    pub session_lifetime: Option<u64>,
+    /// Minutes.
+    pub expiry_warning: Option<u64>,
+    pub renew_session: Option<bool>,
+    /// Minutes.
+    pub prewarm: Option<u64>,
+    pub on_connect: Option<String>,
+    pub on_disconnect: Option<String>,
+    /// Profile to use when none is given explicitly.
+    pub profile: Option<String>,
+}
+
+/// Declares [`Setting`] and the per-key helpers on [`Config`] from one table
+/// of `field => Variant, "label"` rows.
+macro_rules! settings {
+    ($($field:ident => $variant:ident, $label:literal;)*) => {
+        /// A connection setting, named as its key in `config.toml`.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(rename_all = "kebab-case")]
+        pub enum Setting {
+            $($variant,)*
+        }
+
+        impl Setting {
+            /// The key as written in `config.toml` (`login-mode`).
+            pub fn key(&self) -> String {
+                match self {
+                    $(Setting::$variant => stringify!($field).replace('_', "-"),)*
+                }
+            }
+
+            /// Human-readable name for settings screens.
+            pub fn label(&self) -> &'static str {
+                match self {
+                    $(Setting::$variant => $label,)*
+                }
+            }
+        }
+
+        impl Config {
+            /// The settings that have a value here.
+            pub fn keys(&self) -> Vec<Setting> {
+                let mut keys = Vec::new();
+                $(if self.$field.is_some() {
+                    keys.push(Setting::$variant);
+                })*
+                keys
+            }
+
+            /// A copy with only the settings in `keys` kept.
+            pub fn only(&self, keys: &[Setting]) -> Config {
+                Config {
+                    $($field: if keys.contains(&Setting::$variant) {
+                        self.$field.clone()
+                    } else {
+                        None
+                    },)*
+                    profile: None,
+                }
+            }
+        }
+    };
+}
+
+settings! {
+    url => Url, "Gateway URL";
+    domain => Domain, "DSID domain";
+    email => Email, "Email";
+    login_mode => LoginMode, "Login mode";
+    login_engine => LoginEngine, "Login engine";
+    browser => Browser, "Browser";
+    tunnel_mode => TunnelMode, "Tunnel mode";
+    vpnc_script => VpncScript, "VPN script";
+    escalation_tool => EscalationTool, "Elevation tool";
+    openconnect_path => OpenconnectPath, "OpenConnect path";
+    interface_name => InterfaceName, "Interface name";
+    mfa_preference => MfaPreference, "MFA method";
+    max_push_resends => MaxPushResends, "MFA resends";
+    lockout_threshold => LockoutThreshold, "Lockout guard";
+    lockout_cooldown => LockoutCooldown, "Lockout cooldown";
+    session_conflict => SessionConflict, "Open sessions";
+    visible_fallback => VisibleFallback, "Show browser when stuck";
+    session_lifetime => SessionLifetime, "Session limit";
+    expiry_warning => ExpiryWarning, "Expiry warning";
+    renew_session => RenewSession, "Renew session";
+    prewarm => Prewarm, "Pre-warm browser";
+    on_connect => OnConnect, "On connect";
+    on_disconnect => OnDisconnect, "On disconnect";
+}
+
+/// Returns the kuvpn config directory path without creating it.
+fn config_dir_path() -> Result<PathBuf, Box<dyn Error>> {
+    #[cfg(target_os = "windows")]
+    {
+        let home = env::var("USERPROFILE")?;
+        Ok(PathBuf::from(home).join("AppData/Roaming/kuvpn"))
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let home = env::var("HOME")?;
+        Ok(PathBuf::from(home).join("Library/Application Support/kuvpn"))
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        match env::var("XDG_CONFIG_HOME") {
+            Ok(dir) if !dir.is_empty() => Ok(PathBuf::from(dir).join("kuvpn")),
+            _ => Ok(PathBuf::from(env::var("HOME")?).join(".config/kuvpn")),
+        }
+    }
+}
+
+/// Returns the kuvpn config directory, creating it if needed.  Besides
+/// `config.toml` it holds frontend-specific files such as the GUI settings.
+pub fn config_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let dir = config_dir_path()?;
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Reads `KUVPN_<KEY>`, treating an empty value as unset.
+fn env_var(key: &str) -> Option<String> {
+    env::var(format!("KUVPN_{}", key))
+        .ok()
+        .filter(|v| !v.is_empty())
+}
+
+/// Parses `value` as a kebab-case enum such as `"close-all"`.
+fn parse_enum<T: DeserializeOwned>(key: &str, value: &str) -> Option<T> {
+    let de: serde::de::value::StrDeserializer<'_, serde::de::value::Error> =
+        value.trim().into_deserializer();
+    match T::deserialize(de) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            log::warn!("Ignoring KUVPN_{}={:?}: {}", key, value, e);
+            None
+        }
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(key: &str, value: &str) -> Option<T> {
+    let parsed = value.trim().parse().ok();
+    if parsed.is_none() {
+        log::warn!("Ignoring KUVPN_{}={:?}: not a valid value", key, value);
+    }
+    parsed
+}
+
+impl Config {
+    /// Full path of the config file.
+    pub fn path() -> Result<PathBuf, Box<dyn Error>> {
+        Ok(config_dir_path()?.join("config.toml"))
+    }
+
+    /// Whether the config file exists.
+    pub fn exists() -> bool {
+        Self::path().is_ok_and(|p| p.exists())
+    }
+
+    /// Reads the config file.  A missing file yields an empty config; a file
+    /// that does not parse is an error, so a typo is not silently ignored.
+    pub fn from_file() -> Result<Self, Box<dyn Error>> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// Reads the `KUVPN_*` environment overrides.  Values that do not parse
+    /// are logged and ignored.
+    pub fn from_env() -> Self {
+        Self {
+            url: env_var("URL"),
+            domain: env_var("DOMAIN"),
+            email: env_var("EMAIL"),
+            login_mode: env_var("LOGIN_MODE").and_then(|v| parse_enum("LOGIN_MODE", &v)),
+            login_engine: env_var("LOGIN_ENGINE").and_then(|v| parse_enum("LOGIN_ENGINE", &v)),
+            browser: env_var("BROWSER"),
+            tunnel_mode: env_var("TUNNEL_MODE").and_then(|v| parse_enum("TUNNEL_MODE", &v)),
+            vpnc_script: env_var("VPNC_SCRIPT"),
+            escalation_tool: env_var("ESCALATION_TOOL"),
+            openconnect_path: env_var("OPENCONNECT_PATH"),
+            interface_name: env_var("INTERFACE_NAME"),
+            mfa_preference: env_var("MFA_PREFERENCE").and_then(|v| {
+                v.split(',')
+                    .map(|m| parse_enum("MFA_PREFERENCE", m))
+                    .collect()
+            }),
+            max_push_resends: env_var("MAX_PUSH_RESENDS")
+                .and_then(|v| parse_value("MAX_PUSH_RESENDS", &v)),
+            lockout_threshold: env_var("LOCKOUT_THRESHOLD")
+                .and_then(|v| parse_value("LOCKOUT_THRESHOLD", &v)),
+            lockout_cooldown: env_var("LOCKOUT_COOLDOWN")
+                .and_then(|v| parse_value("LOCKOUT_COOLDOWN", &v)),
+            session_conflict: env_var("SESSION_CONFLICT")
+                .and_then(|v| parse_enum("SESSION_CONFLICT", &v)),
+            visible_fallback: env_var("VISIBLE_FALLBACK")
+                .and_then(|v| parse_enum("VISIBLE_FALLBACK", &v)),
+            session_lifetime: env_var("SESSION_LIFETIME")
+                .and_then(|v| parse_value("SESSION_LIFETIME", &v)),
+            expiry_warning: env_var("EXPIRY_WARNING")
+                .and_then(|v| parse_value("EXPIRY_WARNING", &v)),
+            renew_session: env_var("RENEW_SESSION").and_then(|v| parse_value("RENEW_SESSION", &v)),
+            prewarm: env_var("PREWARM").and_then(|v| parse_value("PREWARM", &v)),
+            on_connect: env_var("ON_CONNECT"),
+            on_disconnect: env_var("ON_DISCONNECT"),
+            profile: env_var("PROFILE"),
+        }
+    }
+
+    /// The config file with the environment overrides applied.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        Ok(Self::from_file()?.overlay(Self::from_env()))
+    }
+
+    /// Returns `self` with every key set in `other` replaced by its value.
+    pub fn overlay(self, other: Config) -> Config {
+        Config {
+            url: other.url.or(self.url),
+            domain: other.domain.or(self.domain),
+            email: other.email.or(self.email),
+            login_mode: other.login_mode.or(self.login_mode),
+            login_engine: other.login_engine.or(self.login_engine),
+            browser: other.browser.or(self.browser),
+            tunnel_mode: other.tunnel_mode.or(self.tunnel_mode),
+            vpnc_script: other.vpnc_script.or(self.vpnc_script),
+            escalation_tool: other.escalation_tool.or(self.escalation_tool),
+            openconnect_path: other.openconnect_path.or(self.openconnect_path),
+            interface_name: other.interface_name.or(self.interface_name),
+            mfa_preference: other.mfa_preference.or(self.mfa_preference),
+            max_push_resends: other.max_push_resends.or(self.max_push_resends),
+            lockout_threshold: other.lockout_threshold.or(self.lockout_threshold),
+            lockout_cooldown: other.lockout_cooldown.or(self.lockout_cooldown),
+            session_conflict: other.session_conflict.or(self.session_conflict),
+            visible_fallback: other.visible_fallback.or(self.visible_fallback),
+            session_lifetime: other.session_lifetime.or(self.session_lifetime),
+            expiry_warning: other.expiry_warning.or(self.expiry_warning),
+            renew_session: other.renew_session.or(self.renew_session),
+            prewarm: other.prewarm.or(self.prewarm),
+            on_connect: other.on_connect.or(self.on_connect),
+            on_disconnect: other.on_disconnect.or(self.on_disconnect),
+            profile: other.profile.or(self.profile),
+        }
+    }
+
+    /// Writes the config file, creating the config directory if needed.
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = config_dir()?.join("config.toml");
+        std::fs::write(&path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlay_prefers_the_other_config() {
+        let file = Config {
+            url: Some("https://file.example".into()),
+            email: Some("file@example.com".into()),
+            max_push_resends: Some(1),
+            ..Config::default()
+        };
+        let env = Config {
+            url: Some("https://env.example".into()),
+            login_mode: Some(LoginMode::Manual),
+            ..Config::default()
+        };
+        let merged = file.overlay(env);
+        assert_eq!(merged.url.as_deref(), Some("https://env.example"));
+        assert_eq!(merged.email.as_deref(), Some("file@example.com"));
+        assert_eq!(merged.login_mode, Some(LoginMode::Manual));
+        assert_eq!(merged.max_push_resends, Some(1));
+        assert_eq!(merged.domain, None);
+    }
+
+    #[test]
+    fn keys_and_only_follow_the_set_values() {
+        let config = Config {
+            email: Some("jdoe@example.com".into()),
+            session_conflict: Some(SessionConflictPolicy::KeepAll),
+            profile: Some("lab".into()),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.keys(),
+            vec![Setting::Email, Setting::SessionConflict]
+        );
+        let only = config.only(&[Setting::SessionConflict]);
+        assert_eq!(
+            only,
+            Config {
+                session_conflict: Some(SessionConflictPolicy::KeepAll),
+                ..Config::default()
+            }
+        );
+    }
+
+    #[test]
+    fn setting_keys_are_kebab_case() {
+        assert_eq!(Setting::LoginMode.key(), "login-mode");
+        assert_eq!(Setting::MaxPushResends.key(), "max-push-resends");
+    }
+
+    #[test]
+    fn file_format_uses_kebab_case_keys() {
+        let config: Config = toml::from_str(
+            r#"
+            login-mode = "visual"
+            mfa-preference = ["sms", "first-available"]
+            lockout-cooldown = 30
+            renew-session = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.login_mode, Some(LoginMode::Visual));
+        assert_eq!(
+            config.mfa_preference,
+            Some(vec![MfaMethod::Sms, MfaMethod::FirstAvailable])
+        );
+        assert_eq!(config.lockout_cooldown, Some(30));
+        assert_eq!(config.renew_session, Some(true));
+        assert!(toml::from_str::<Config>("login-mode = \"bogus\"").is_err());
+    }
+
+    // The only test touching `KUVPN_*`, so it cannot race with another.
+    #[test]
+    fn env_overrides_parse_and_skip_bad_values() {
+        let vars = [
+            ("KUVPN_URL", ""),
+            ("KUVPN_EMAIL", "jdoe@example.com"),
+            ("KUVPN_LOGIN_MODE", "manual"),
+            ("KUVPN_MFA_PREFERENCE", "sms, voice"),
+            ("KUVPN_MAX_PUSH_RESENDS", "lots"),
+            ("KUVPN_SESSION_CONFLICT", "close-all"),
+            ("KUVPN_RENEW_SESSION", "true"),
+            ("KUVPN_TUNNEL_MODE", "sideways"),
+        ];
+        for (key, value) in vars {
+            env::set_var(key, value);
+        }
+        let config = Config::from_env();
+        for (key, _) in vars {
+            env::remove_var(key);
+        }
+
+        assert_eq!(config.url, None);
+        assert_eq!(config.email.as_deref(), Some("jdoe@example.com"));
+        assert_eq!(config.login_mode, Some(LoginMode::Manual));
+        assert_eq!(
+            config.mfa_preference,
+            Some(vec![MfaMethod::Sms, MfaMethod::Voice])
+        );
+        assert_eq!(config.max_push_resends, None);
+        assert_eq!(
+            config.session_conflict,
+            Some(SessionConflictPolicy::CloseAll)
+        );
+        assert_eq!(config.renew_session, Some(true));
+        assert_eq!(config.tunnel_mode, None);
+    }
+}
@@ -21,13 +21,10 @@ pub struct DiagnosticBundle {
 }
 
 impl DiagnosticBundle {
-    /// Saves this bundle to `<user-data-parent>/diagnostics/<timestamp>.json`.
+    /// Saves this bundle to `<kuvpn-data-dir>/diagnostics/<timestamp>.json`.
     /// Returns the path to the saved file.
     pub fn save(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let dir = crate::utils::get_user_data_dir()?
-            .parent()
-            .ok_or("user data dir has no parent")?
-            .join("diagnostics");
+        let dir = crate::utils::kuvpn_data_dir()?.join("diagnostics");
         std::fs::create_dir_all(&dir)?;
 
         let filename = format!("{}.json", self.timestamp.replace(':', "-"));
@@ -0,0 +1,551 @@
+//! The page operations the login handlers need from a browser.
+//!
+//! [`crate::handlers::AuthTab`] talks to the page only through
+//! [`PageDriver`], so the handlers do not depend on `headless_chrome`.
+//! [`ChromePage`] is the implementation used for real logins;
+//! in tests, `fake::ScriptedPage` answers scripts from a table and records
+//! what was evaluated, for exercising handlers without a browser.
+//!
+//! Rather than polling, the login loop blocks in
+//! [`PageDriver::wait_for_change`].  [`ChromePage`] wakes it on navigations
+//! (`Page.frameNavigated`), load progress (`Page.lifecycleEvent`) and DOM
+//! mutations, which an injected `MutationObserver` reports through a page
+//! binding.  It also reads cookies off `Set-Cookie` response headers
+//! (`Network.responseReceivedExtraInfo`), so the DSID is seen as soon as the
+//! gateway sends it.
+//!
+//! When a page stops answering, [`PageDriver::loss`] tells a page the user
+//! closed from a browser that crashed, from the page's own
+//! `Inspector.detached`/`Inspector.targetCrashed` events and the browser
+//! process's exit status.
+
+use headless_chrome::protocol::cdp::types::Event;
+use headless_chrome::protocol::cdp::{Inspector, Network, Page};
+use headless_chrome::Tab;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// A cookie as seen by the page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageCookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+}
+
+/// Why a page stopped answering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageLoss {
+    /// The page or its browser was closed normally, e.g. by the user.
+    Closed,
+    /// The browser or the page's renderer died, or the connection to a
+    /// browser that is still running broke; says what happened.
+    Crashed(String),
+    /// The browser is gone and nothing tells how it went.
+    Unknown,
+}
+
+/// One browser page (tab) that the login flow drives.
+pub trait PageDriver: Send + Sync {
+    /// Evaluates `js` in the page and returns its value, `None` for
+    /// `null`/`undefined`.
+    fn evaluate(&self, js: &str) -> anyhow::Result<Option<Value>>;
+
+    /// Navigates to `url` and waits for the page to load.  A load that does
+    /// not finish in time is logged, not returned as an error.
+    fn navigate(&self, url: &str) -> anyhow::Result<()>;
+
+    /// The URL of the current page.
+    fn get_url(&self) -> String;
+
+    /// Cookies visible to the current page.
+    fn get_cookies(&self) -> anyhow::Result<Vec<PageCookie>>;
+
+    /// Cookies set by the page's responses so far, oldest first, as sent by
+    /// the server.  Empty for pages that cannot see response headers.
+    fn set_cookies(&self) -> Vec<PageCookie> {
+        Vec::new()
+    }
+
+    /// Blocks until the page changes (navigation, load progress, DOM
+    /// mutation) or `timeout` passes, and returns whether it changed.  Pages
+    /// that do not report changes just wait out the timeout.
+    fn wait_for_change(&self, timeout: Duration) -> bool {
+        std::thread::sleep(timeout);
+        false
+    }
+
+    /// Why the page went away, once it has stopped answering.  Only
+    /// meaningful after a call on the page failed.
+    fn loss(&self) -> PageLoss {
+        PageLoss::Unknown
+    }
+
+    /// Raises the window holding the page, where that means anything.
+    fn bring_to_front(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Closes the page.
+    fn close(&self) -> anyhow::Result<()>;
+
+    /// Process ID of the browser showing the page, if known.
+    fn pid(&self) -> Option<u32>;
+}
+
+/// Name of the page binding the mutation observer calls.
+const CHANGED_BINDING: &str = "__kuvpnPageChanged";
+
+/// Reports DOM changes through [`CHANGED_BINDING`], at most every 100 ms.
+/// Observes `document` itself so it can be installed before the document
+/// element exists.
+const OBSERVER_JS: &str = r#"(function() {
+    if (window.__kuvpn_observer) return;
+    var pending = false;
+    window.__kuvpn_observer = new MutationObserver(function() {
+        if (pending) return;
+        pending = true;
+        setTimeout(function() {
+            pending = false;
+            try { window.__kuvpnPageChanged(''); } catch (e) {}
+        }, 100);
+    });
+    window.__kuvpn_observer.observe(document, {
+        childList: true,
+        subtree: true,
+        characterData: true,
+        attributes: true,
+        attributeFilter: ['style', 'class', 'hidden', 'aria-hidden', 'disabled'],
+    });
+})()"#;
+
+/// What the page has reported through protocol events.
+#[derive(Default)]
+struct PageEvents {
+    state: Mutex<EventState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct EventState {
+    /// Set on every change, cleared by [`PageDriver::wait_for_change`].
+    changed: bool,
+    /// Host of each request in flight, for cookies without a `Domain`.
+    request_hosts: HashMap<String, String>,
+    set_cookies: Vec<PageCookie>,
+    /// Set when the page reports it was closed or crashed.
+    loss: Option<PageLoss>,
+}
+
+impl PageEvents {
+    fn state(&self) -> std::sync::MutexGuard<'_, EventState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn signal(&self) {
+        self.state().changed = true;
+        self.changed.notify_all();
+    }
+
+    fn wait(&self, timeout: Duration) -> bool {
+        let state = self.state();
+        let (mut state, _) = self
+            .changed
+            .wait_timeout_while(state, timeout, |s| !s.changed)
+            .unwrap_or_else(|e| e.into_inner());
+        std::mem::take(&mut state.changed)
+    }
+
+    fn on_event(&self, event: &Event) {
+        match event {
+            Event::PageFrameNavigated(_) | Event::PageLifecycleEvent(_) => self.signal(),
+            Event::InspectorTargetCrashed(_) => {
+                self.state().loss = Some(PageLoss::Crashed("the page crashed".to_string()));
+                self.signal();
+            }
+            Event::InspectorDetached(ev) => {
+                let loss = match ev.params.reason.as_str() {
+                    "target_closed" | "canceled_by_user" => PageLoss::Closed,
+                    reason => PageLoss::Crashed(format!("the page was detached: {}", reason)),
+                };
+                self.state().loss.get_or_insert(loss);
+                self.signal();
+            }
+            Event::NetworkRequestWillBeSent(ev) => {
+                if let Some(host) = url::Url::parse(&ev.params.request.url)
+                    .ok()
+                    .and_then(|u| u.host_str().map(str::to_string))
+                {
+                    self.state()
+                        .request_hosts
+                        .insert(ev.params.request_id.clone(), host);
+                }
+            }
+            Event::NetworkResponseReceivedExtraInfo(ev) => {
+                let params = &ev.params;
+                let mut state = self.state();
+                let host = state
+                    .request_hosts
+                    .remove(&params.request_id)
+                    .unwrap_or_default();
+                let blocked: Vec<&str> = params
+                    .blocked_cookies
+                    .iter()
+                    .map(|b| b.cookie_line.as_str())
+                    .collect();
+                let cookies: Vec<PageCookie> = set_cookie_lines(&params.headers)
+                    .filter(|line| !blocked.contains(line))
+                    .filter_map(|line| parse_set_cookie(line, &host))
+                    .collect();
+                if !cookies.is_empty() {
+                    state.set_cookies.extend(cookies);
+                    drop(state);
+                    self.signal();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The `Set-Cookie` lines among response `headers`.  Chrome joins repeated
+/// headers with newlines.
+fn set_cookie_lines(headers: &Network::Headers) -> impl Iterator<Item = &str> {
+    headers
+        .0
+        .as_ref()
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+        .filter_map(|(_, value)| value.as_str())
+        .flat_map(str::lines)
+}
+
+/// Parses one `Set-Cookie` line; `host` stands in for a missing `Domain`.
+fn parse_set_cookie(line: &str, host: &str) -> Option<PageCookie> {
+    let mut parts = line.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let domain = parts
+        .filter_map(|attr| attr.split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("domain"))
+        .map(|(_, domain)| domain.trim().to_string())
+        .unwrap_or_else(|| host.to_string());
+    Some(PageCookie {
+        name: name.trim().to_string(),
+        value: value.trim().to_string(),
+        domain,
+    })
+}
+
+/// [`PageDriver`] for a `headless_chrome` tab.
+pub struct ChromePage {
+    tab: Arc<Tab>,
+    pid: Option<u32>,
+    events: Arc<PageEvents>,
+}
+
+impl ChromePage {
+    /// Wraps `tab`; `pid` is the process ID of the browser it belongs to.
+    pub fn new(tab: Arc<Tab>, pid: Option<u32>) -> Self {
+        Self {
+            tab,
+            pid,
+            events: Arc::default(),
+        }
+    }
+
+    /// The underlying tab, for Chrome-specific protocol calls.
+    pub fn tab(&self) -> &Arc<Tab> {
+        &self.tab
+    }
+
+    /// Subscribes to the tab's navigation, network and DOM mutation events
+    /// (see the module docs).  Until this is called the page reports no
+    /// changes.
+    pub fn watch_events(&self) -> anyhow::Result<()> {
+        let events = Arc::clone(&self.events);
+        self.tab
+            .add_event_listener(Arc::new(move |event: &Event| events.on_event(event)))?;
+        self.tab.call_method(Network::Enable {
+            max_total_buffer_size: None,
+            max_resource_buffer_size: None,
+            max_post_data_size: None,
+            report_direct_socket_traffic: None,
+            enable_durable_messages: None,
+        })?;
+        self.tab.call_method(Inspector::Enable(None))?;
+        let events = Arc::clone(&self.events);
+        self.tab
+            .expose_function(CHANGED_BINDING, Arc::new(move |_: Value| events.signal()))?;
+        self.tab
+            .call_method(Page::AddScriptToEvaluateOnNewDocument {
+                source: OBSERVER_JS.to_string(),
+                world_name: None,
+                include_command_line_api: None,
+                run_immediately: None,
+            })?;
+        // The script above only runs in documents loaded from now on.
+        self.tab.evaluate(OBSERVER_JS, false)?;
+        Ok(())
+    }
+}
+
+impl PageDriver for ChromePage {
+    fn evaluate(&self, js: &str) -> anyhow::Result<Option<Value>> {
+        Ok(self.tab.evaluate(js, false)?.value)
+    }
+
+    fn navigate(&self, url: &str) -> anyhow::Result<()> {
+        self.tab.navigate_to(url)?;
+        if let Err(e) = self.tab.wait_until_navigated() {
+            log::warn!("Navigation wait timed out: {}, continuing...", e);
+        }
+        Ok(())
+    }
+
+    fn get_url(&self) -> String {
+        self.tab.get_url()
+    }
+
+    fn get_cookies(&self) -> anyhow::Result<Vec<PageCookie>> {
+        Ok(self
+            .tab
+            .get_cookies()?
+            .into_iter()
+            .map(|c| PageCookie {
+                name: c.name,
+                value: c.value,
+                domain: c.domain,
+            })
+            .collect())
+    }
+
+    fn set_cookies(&self) -> Vec<PageCookie> {
+        self.events.state().set_cookies.clone()
+    }
+
+    fn wait_for_change(&self, timeout: Duration) -> bool {
+        self.events.wait(timeout)
+    }
+
+    fn loss(&self) -> PageLoss {
+        if let Some(loss) = self.events.state().loss.clone() {
+            return loss;
+        }
+        self.pid.map_or(PageLoss::Unknown, process_loss)
+    }
+
+    fn bring_to_front(&self) -> anyhow::Result<()> {
+        self.tab.bring_to_front()?;
+        Ok(())
+    }
+
+    fn close(&self) -> anyhow::Result<()> {
+        self.tab.close(true)?;
+        Ok(())
+    }
+
+    fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+}
+
+/// Classifies a lost page by the state of its browser process `pid`, a
+/// child of ours.  The exit status is read without reaping the process, so
+/// `headless_chrome` can still wait for it.
+#[cfg(target_os = "linux")]
+fn process_loss(pid: u32) -> PageLoss {
+    use nix::sys::wait::{waitid, Id, WaitPidFlag, WaitStatus};
+    use nix::unistd::Pid;
+
+    let flags = WaitPidFlag::WEXITED | WaitPidFlag::WNOHANG | WaitPidFlag::WNOWAIT;
+    match waitid(Id::Pid(Pid::from_raw(pid as i32)), flags) {
+        Ok(WaitStatus::StillAlive) => {
+            PageLoss::Crashed("lost the connection to the browser".to_string())
+        }
+        Ok(WaitStatus::Exited(_, 0)) => PageLoss::Closed,
+        Ok(WaitStatus::Exited(_, code)) => {
+            PageLoss::Crashed(format!("the browser exited with status {}", code))
+        }
+        Ok(WaitStatus::Signaled(_, signal, _)) => {
+            PageLoss::Crashed(format!("the browser was killed by {}", signal.as_str()))
+        }
+        _ => PageLoss::Unknown,
+    }
+}
+
+/// Classifies a lost page by the exit code of its browser process `pid`.
+#[cfg(windows)]
+fn process_loss(pid: u32) -> PageLoss {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    /// Exit code reported for a process that is still running.
+    const STILL_ACTIVE: u32 = 259;
+
+    let mut code = 0u32;
+    let known = unsafe {
+        match OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) {
+            Ok(handle) => {
+                let known = GetExitCodeProcess(handle, &mut code).is_ok();
+                let _ = CloseHandle(handle);
+                known
+            }
+            Err(_) => false,
+        }
+    };
+    match code {
+        _ if !known => PageLoss::Unknown,
+        STILL_ACTIVE => PageLoss::Crashed("lost the connection to the browser".to_string()),
+        0 => PageLoss::Closed,
+        code => PageLoss::Crashed(format!("the browser exited with code {:#x}", code)),
+    }
+}
+
+/// Exit statuses cannot be read here without reaping the process.
+#[cfg(not(any(target_os = "linux", windows)))]
+fn process_loss(_pid: u32) -> PageLoss {
+    PageLoss::Unknown
+}
+
+#[cfg(test)]
+pub(crate) mod fake {
+    //! An in-memory page for exercising handlers without a browser.
+
+    use super::{PageCookie, PageDriver, PageLoss};
+    use serde_json::Value;
+    use std::sync::{Condvar, Mutex};
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct State {
+        url: String,
+        scripts: Vec<(String, Value)>,
+        cookies: Vec<PageCookie>,
+        evaluated: Vec<String>,
+        closed: bool,
+        changed: bool,
+    }
+
+    /// A [`PageDriver`] whose scripts return scripted values.
+    ///
+    /// A script evaluates to the value of the most recently added rule whose
+    /// fragment it contains, or to `undefined` when none matches.
+    /// Navigating sets the URL.  Changing the URL or the scripts counts as
+    /// a page change for [`PageDriver::wait_for_change`].
+    #[derive(Default)]
+    pub(crate) struct ScriptedPage {
+        state: Mutex<State>,
+        changed: Condvar,
+    }
+
+    impl ScriptedPage {
+        /// A page showing `url`.
+        pub(crate) fn new(url: &str) -> Self {
+            let page = Self::default();
+            page.set_url(url);
+            page
+        }
+
+        fn state(&self) -> std::sync::MutexGuard<'_, State> {
+            self.state.lock().unwrap_or_else(|e| e.into_inner())
+        }
+
+        /// Makes scripts containing `fragment` evaluate to `result`.
+        pub(crate) fn on_script(self, fragment: &str, result: Value) -> Self {
+            self.set_script(fragment, result);
+            self
+        }
+
+        /// Adds a cookie to the page.
+        pub(crate) fn with_cookie(self, name: &str, value: &str, domain: &str) -> Self {
+            self.state().cookies.push(PageCookie {
+                name: name.to_string(),
+                value: value.to_string(),
+                domain: domain.to_string(),
+            });
+            self
+        }
+
+        /// Like [`ScriptedPage::on_script`], for a page already in use.
+        pub(crate) fn set_script(&self, fragment: &str, result: Value) {
+            self.state().scripts.push((fragment.to_string(), result));
+            self.signal();
+        }
+
+        /// Changes the current URL, as if the page had moved on by itself.
+        pub(crate) fn set_url(&self, url: &str) {
+            self.state().url = url.to_string();
+            self.signal();
+        }
+
+        fn signal(&self) {
+            self.state().changed = true;
+            self.changed.notify_all();
+        }
+
+        /// Every script evaluated so far, in order.
+        pub(crate) fn evaluated(&self) -> Vec<String> {
+            self.state().evaluated.clone()
+        }
+    }
+
+    impl PageDriver for ScriptedPage {
+        fn evaluate(&self, js: &str) -> anyhow::Result<Option<Value>> {
+            let mut state = self.state();
+            if state.closed {
+                anyhow::bail!("page is closed");
+            }
+            state.evaluated.push(js.to_string());
+            Ok(state
+                .scripts
+                .iter()
+                .rev()
+                .find(|(fragment, _)| js.contains(fragment.as_str()))
+                .map(|(_, result)| result.clone())
+                .filter(|v| !v.is_null()))
+        }
+
+        fn navigate(&self, url: &str) -> anyhow::Result<()> {
+            self.set_url(url);
+            Ok(())
+        }
+
+        fn get_url(&self) -> String {
+            self.state().url.clone()
+        }
+
+        fn get_cookies(&self) -> anyhow::Result<Vec<PageCookie>> {
+            Ok(self.state().cookies.clone())
+        }
+
+        fn wait_for_change(&self, timeout: Duration) -> bool {
+            let (mut state, _) = self
+                .changed
+                .wait_timeout_while(self.state(), timeout, |s| !s.changed)
+                .unwrap_or_else(|e| e.into_inner());
+            std::mem::take(&mut state.changed)
+        }
+
+        fn loss(&self) -> PageLoss {
+            if self.state().closed {
+                PageLoss::Closed
+            } else {
+                PageLoss::Unknown
+            }
+        }
+
+        fn close(&self) -> anyhow::Result<()> {
+            self.state().closed = true;
+            Ok(())
+        }
+
+        fn pid(&self) -> Option<u32> {
+            None
+        }
+    }
+}
@@ -1,14 +1,67 @@
-use crate::browser::create_browser;
+use crate::browser::{
+    can_show_window, create_browser, create_hidden_browser, launch_warm_browser, Window,
+};
+use crate::driver::{ChromePage, PageLoss};
 use crate::error::AuthError;
-use crate::handlers::AuthTab;
+use crate::handlers::{AuthTab, WAKE_INTERVAL};
+use crate::history::{append_event, ConnectionEvent, EventKind};
+use crate::http_login::HttpLoginUnsupported;
+use crate::lockout::{self, LockoutPolicy};
 use crate::utils::{CancellationToken, CredentialsProvider};
+use headless_chrome::types::Bounds;
 use headless_chrome::{Browser, Tab};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 
+/// Records an incorrect password for the lockout guard; failures to persist
+/// it are logged rather than masking the authentication error.
+pub(crate) fn record_password_failure() {
+    if let Err(e) = lockout::record_failure() {
+        log::warn!("Could not record password failure: {}", e);
+    }
+}
+
+/// Refuses the password step while the lockout guard is active, unless the
+/// user explicitly chooses to re-enter the password.
+pub(crate) fn check_lockout(
+    policy: &LockoutPolicy,
+    provider: &dyn CredentialsProvider,
+) -> Result<(), AuthError> {
+    let Some(status) = lockout::lockout_status(policy) else {
+        return Ok(());
+    };
+    log::warn!(
+        "{} recent incorrect passwords, automated sign-in paused for {}s",
+        status.failures,
+        status.retry_after.as_secs()
+    );
+    if provider.confirm_password_retry(status.failures, status.retry_after) {
+        log::info!("User chose to re-enter the password despite the lockout guard");
+        return Ok(());
+    }
+    Err(AuthError::CredentialLockout {
+        failures: status.failures,
+        retry_after_secs: status.retry_after.as_secs(),
+    })
+}
+
+/// Clears the lockout history and stamps the login time after a DSID was
+/// obtained; failures are logged only.
+pub(crate) fn record_login_success(mfa_seen: bool) {
+    if let Err(e) = lockout::clear_failures() {
+        log::warn!("Could not reset password failure history: {}", e);
+    }
+    if let Err(e) = crate::session_info::record_login(mfa_seen) {
+        log::warn!("Could not record the login time: {}", e);
+    }
+}
+
 /// Configuration for the browser-based login process.
+#[derive(Clone)]
 pub struct LoginConfig {
     pub headless: bool,
     pub url: String,
@@ -16,6 +69,271 @@ pub struct LoginConfig {
     pub user_agent: String,
     pub no_auto_login: bool,
     pub email: Option<String>,
+    /// MFA methods to pick, in order, whenever Microsoft offers a choice.
+    pub mfa_preference: Vec<MfaMethod>,
+    /// How many times an unanswered push or call is re-sent before giving up.
+    pub max_push_resends: u32,
+    /// When to stop submitting the password after repeated failures.
+    pub lockout: LockoutPolicy,
+    /// What to do with existing sessions listed on the gateway's
+    /// session-conflict page.
+    pub session_conflict: SessionConflictPolicy,
+    /// Whether a headless login that needs the user may show its browser.
+    pub visible_fallback: VisibleFallback,
+    /// Which engine performs a headless, automated login.
+    pub engine: LoginEngine,
+}
+
+/// Signals (through `anyhow`) that a headless login cannot go on without the
+/// user.  [`run_login_and_get_dsid`] shows the browser's hidden window at the
+/// same page and carries on, with automation off when `manual` is set.
+/// Where the browser has no window to show, `error` is reported instead.
+#[derive(Debug)]
+pub(crate) struct VisibleBrowserRequired {
+    pub(crate) reason: String,
+    pub(crate) manual: bool,
+    pub(crate) error: AuthError,
+}
+
+impl std::fmt::Display for VisibleBrowserRequired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
+impl std::error::Error for VisibleBrowserRequired {}
+
+/// What a login carries over to a browser relaunched after a crash.
+///
+/// The relaunched browser starts the flow over from the gateway URL, and
+/// every page it shows is handled again; only the MFA bookkeeping survives,
+/// so the resend limit covers both browsers.
+#[derive(Debug, Default)]
+pub(crate) struct LoginProgress {
+    mfa_seen: bool,
+    mfa_resends: u32,
+}
+
+/// Signals (through `anyhow`) that the browser crashed during the login.
+/// [`run_login_and_get_dsid`] relaunches it once on the same profile and
+/// restarts the login from the gateway URL with `progress`.
+#[derive(Debug)]
+pub(crate) struct BrowserCrashed {
+    pub(crate) reason: String,
+    pub(crate) progress: LoginProgress,
+}
+
+impl std::fmt::Display for BrowserCrashed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
+impl std::error::Error for BrowserCrashed {}
+
+/// Default for [`LoginConfig::max_push_resends`].
+pub const DEFAULT_MAX_PUSH_RESENDS: u32 = 2;
+
+/// A second-factor method the login flow can choose on the user's behalf.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MfaMethod {
+    /// Approve a push notification in the Authenticator app.
+    Push,
+    /// Type a code from an authenticator app (TOTP).
+    Totp,
+    /// Receive a code by text message.
+    Sms,
+    /// Answer a phone call.
+    Voice,
+    /// Whatever proof Microsoft lists first.
+    FirstAvailable,
+}
+
+impl MfaMethod {
+    /// Order used when the user has not configured a preference: Authenticator
+    /// push, then SMS, then whatever is offered.
+    pub fn default_order() -> Vec<MfaMethod> {
+        vec![MfaMethod::Push, MfaMethod::Sms, MfaMethod::FirstAvailable]
+    }
+
+    /// Every method, in the order they are listed in settings UIs.
+    pub const ALL: [MfaMethod; 5] = [
+        MfaMethod::Push,
+        MfaMethod::Totp,
+        MfaMethod::Sms,
+        MfaMethod::Voice,
+        MfaMethod::FirstAvailable,
+    ];
+
+    /// Short identifier used in config files, CLI flags and logs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MfaMethod::Push => "push",
+            MfaMethod::Totp => "totp",
+            MfaMethod::Sms => "sms",
+            MfaMethod::Voice => "voice",
+            MfaMethod::FirstAvailable => "first-available",
+        }
+    }
+
+    /// `data-value` attributes Microsoft uses for this method on the proof
+    /// choice page (`#idDiv_SAOTCS_Proofs`).
+    pub(crate) fn proof_values(&self) -> &'static [&'static str] {
+        match self {
+            MfaMethod::Push => &["PhoneAppNotification", "CompanionAppsNotification"],
+            MfaMethod::Totp => &["PhoneAppOTP", "CompanionAppsOTP", "OneWayOTP"],
+            MfaMethod::Sms => &["OneWaySMS"],
+            MfaMethod::Voice => &[
+                "TwoWayVoiceMobile",
+                "TwoWayVoiceAlternateMobile",
+                "TwoWayVoiceOffice",
+            ],
+            MfaMethod::FirstAvailable => &[],
+        }
+    }
+}
+
+impl std::fmt::Display for MfaMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MfaMethod::Push => "Authenticator push",
+            MfaMethod::Totp => "Authenticator code",
+            MfaMethod::Sms => "Text message",
+            MfaMethod::Voice => "Phone call",
+            MfaMethod::FirstAvailable => "First available",
+        })
+    }
+}
+
+/// How to resolve the gateway's session-conflict page (`DSIDConfirmForm`),
+/// shown when the user already has sessions open on the gateway.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SessionConflictPolicy {
+    /// Ask the user which of the listed sessions to end.
+    #[default]
+    Ask,
+    /// End every listed session.
+    CloseAll,
+    /// Keep the listed sessions and open another one.
+    KeepAll,
+}
+
+impl SessionConflictPolicy {
+    /// Every policy, in the order they are listed in settings UIs.
+    pub const ALL: [SessionConflictPolicy; 3] = [
+        SessionConflictPolicy::Ask,
+        SessionConflictPolicy::CloseAll,
+        SessionConflictPolicy::KeepAll,
+    ];
+
+    /// Short identifier used in config files, CLI flags and logs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionConflictPolicy::Ask => "ask",
+            SessionConflictPolicy::CloseAll => "close-all",
+            SessionConflictPolicy::KeepAll => "keep-all",
+        }
+    }
+}
+
+impl std::fmt::Display for SessionConflictPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SessionConflictPolicy::Ask => "Ask",
+            SessionConflictPolicy::CloseAll => "Close others",
+            SessionConflictPolicy::KeepAll => "Keep others",
+        })
+    }
+}
+
+/// What a headless login does when it cannot finish without the user
+/// (a page only they can complete, or automation that got stuck).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VisibleFallback {
+    /// Show the browser window and let the user finish there.
+    #[default]
+    Allow,
+    /// Fail the login with the error that stopped it; the browser is kept
+    /// truly headless.
+    Deny,
+    /// Ask the user whether to show the browser window.
+    Ask,
+}
+
+impl VisibleFallback {
+    /// Every policy, in the order they are listed in settings UIs.
+    pub const ALL: [VisibleFallback; 3] = [
+        VisibleFallback::Allow,
+        VisibleFallback::Deny,
+        VisibleFallback::Ask,
+    ];
+
+    /// Short identifier used in config files, CLI flags and logs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VisibleFallback::Allow => "allow",
+            VisibleFallback::Deny => "deny",
+            VisibleFallback::Ask => "ask",
+        }
+    }
+}
+
+impl std::fmt::Display for VisibleFallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            VisibleFallback::Allow => "Show the browser",
+            VisibleFallback::Deny => "Fail the login",
+            VisibleFallback::Ask => "Ask",
+        })
+    }
+}
+
+/// How an automated headless login talks to the gateway and Microsoft.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LoginEngine {
+    /// Drive a Chrome/Chromium instance.
+    #[default]
+    Browser,
+    /// Plain HTTP requests, falling back to the browser on pages it does not
+    /// know.
+    Http,
+}
+
+impl LoginEngine {
+    /// Every engine, in the order they are listed in settings UIs.
+    pub const ALL: [LoginEngine; 2] = [LoginEngine::Browser, LoginEngine::Http];
+
+    /// Short identifier used in config files, CLI flags and logs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginEngine::Browser => "browser",
+            LoginEngine::Http => "http",
+        }
+    }
+}
+
+impl std::fmt::Display for LoginEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LoginEngine::Browser => "Browser",
+            LoginEngine::Http => "HTTP",
+        })
+    }
+}
+
+/// Returns `true` when the first applicable preference is an Authenticator
+/// push, i.e. the flow should stay on (or switch to) app-based sign-in rather
+/// than typing the password first.  An empty list keeps the historical
+/// push-first behaviour.
+pub(crate) fn prefers_push(preference: &[MfaMethod]) -> bool {
+    matches!(
+        preference.first(),
+        None | Some(MfaMethod::Push) | Some(MfaMethod::FirstAvailable)
+    )
 }
 
 // ── Private implementation ────────────────────────────────────────────────────
@@ -32,21 +350,77 @@ fn get_initial_tab(browser: &Browser) -> anyhow::Result<Arc<Tab>> {
     browser.new_tab()
 }
 
-struct BrowserSession {
+/// Whether a headless browser for `config` should get a hidden window that
+/// can be shown to the user later.
+pub(crate) fn may_show_window(config: &LoginConfig) -> bool {
+    config.visible_fallback != VisibleFallback::Deny && can_show_window()
+}
+
+pub(crate) struct BrowserSession {
     browser: Browser,
-    tab: AuthTab,
+    /// The login tab, for Chrome-specific protocol calls.
+    raw_tab: Arc<Tab>,
+    pub(crate) tab: AuthTab,
+    /// Whether the browser has a hidden window that [`Self::reveal`] can show.
+    hidden: bool,
+    /// Unanswered MFA requests re-sent so far in this login.
+    mfa_resends: Cell<u32>,
 }
 
 impl BrowserSession {
+    /// The login tab, for protocol calls outside the login.
+    pub(crate) fn raw_tab(&self) -> &Tab {
+        &self.raw_tab
+    }
+
+    /// Opens a session for `config`.  Headless logins get a hidden window
+    /// where one can be shown and [`VisibleFallback`] does not forbid it, so
+    /// they can hand over to the user in the same browser.
     fn open(config: &LoginConfig) -> anyhow::Result<Self> {
+        if config.headless && may_show_window(config) {
+            match create_hidden_browser(&config.user_agent) {
+                Ok(browser) => return Self::with_browser(browser, config, true),
+                Err(e) => log::warn!(
+                    "Could not open a hidden browser window ({}), using headless",
+                    e
+                ),
+            }
+        }
         let browser = create_browser(&config.user_agent, config.headless, config.no_auto_login)
             .map_err(|e| anyhow::anyhow!("{}", e))?;
+        Self::with_browser(browser, config, false)
+    }
 
+    /// Opens a session for [`crate::prewarm`] that stays connected while it
+    /// sits unused for up to `idle`, with a hidden window like [`Self::open`].
+    pub(crate) fn open_warm(config: &LoginConfig, idle: Duration) -> anyhow::Result<Self> {
+        if may_show_window(config) {
+            match launch_warm_browser(&config.user_agent, Window::Hidden, idle) {
+                Ok(browser) => return Self::with_browser(browser, config, true),
+                Err(e) => log::warn!(
+                    "Could not open a hidden browser window ({}), using headless",
+                    e
+                ),
+            }
+        }
+        let browser = launch_warm_browser(&config.user_agent, Window::Headless, idle)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        Self::with_browser(browser, config, false)
+    }
+
+    fn with_browser(browser: Browser, config: &LoginConfig, hidden: bool) -> anyhow::Result<Self> {
         let raw_tab = get_initial_tab(&browser)?;
+        if hidden {
+            // Off-screen already; minimized it does not flash up either.
+            if let Err(e) = raw_tab.set_bounds(Bounds::Minimized) {
+                log::debug!("Could not minimize the hidden browser window: {}", e);
+            }
+        }
 
-        // Force English Accept-Language at the protocol level, overriding OS locale.
-        // Azure AD uses this header to pick the UI language; if it's Turkish the
-        // English text checks in the automation handlers will all fail.
+        // Ask for English at the protocol level, overriding the OS locale.  Azure
+        // AD uses this header to pick the UI language, but tenants can still
+        // force their own, so the handlers match text per `<html lang>` via the
+        // phrase tables in `handlers::locale`.
         raw_tab.call_method(
             headless_chrome::protocol::cdp::Emulation::SetUserAgentOverride {
                 user_agent: config.user_agent.clone(),
@@ -57,13 +431,40 @@ impl BrowserSession {
         )?;
 
         raw_tab.set_default_timeout(Duration::from_secs(30));
+        let page = ChromePage::new(Arc::clone(&raw_tab), browser.get_process_id());
+        if let Err(e) = page.watch_events() {
+            log::warn!(
+                "Could not subscribe to page events ({}), checking the page every {}s instead",
+                e,
+                WAKE_INTERVAL.as_secs()
+            );
+        }
         Ok(Self {
             browser,
-            tab: AuthTab::new(raw_tab),
+            raw_tab,
+            tab: AuthTab::new(Arc::new(page)),
+            hidden,
+            mfa_resends: Cell::new(0),
         })
     }
 
-    fn close(self) {
+    /// Brings the hidden browser window on screen, at the page the login is
+    /// on, for the user to take over.  Fails for a headless browser.
+    fn reveal(&self) -> anyhow::Result<()> {
+        if !self.hidden {
+            anyhow::bail!("the browser is headless and has no window to show");
+        }
+        self.raw_tab.set_bounds(Bounds::Normal {
+            left: Some(100),
+            top: Some(100),
+            width: Some(800.0),
+            height: Some(800.0),
+        })?;
+        self.raw_tab.bring_to_front()?;
+        Ok(())
+    }
+
+    pub(crate) fn close(self) {
         if let Ok(tabs) = self.browser.get_tabs().lock() {
             for tab in tabs.iter() {
                 // We don't want to hang here if the browser is already dead.
@@ -74,10 +475,7 @@ impl BrowserSession {
     }
 
     fn setup_page_guard(&self, provider: &dyn CredentialsProvider) {
-        self.tab
-            .0
-            .evaluate("window.__kuvpn_input_gone = false;", false)
-            .ok();
+        self.tab.eval("window.__kuvpn_input_gone = false;").ok();
 
         let tab_for_guard = std::sync::Arc::clone(&self.tab.0);
         let guard_url = self.tab.get_url();
@@ -86,9 +484,9 @@ impl BrowserSession {
                 return false;
             }
             let gone = tab_for_guard
-                .evaluate("window.__kuvpn_input_gone === true", false)
+                .evaluate("window.__kuvpn_input_gone === true")
                 .ok()
-                .and_then(|r| r.value)
+                .flatten()
                 .and_then(|v| v.as_bool())
                 .unwrap_or(true); // eval failure → assume page changed
             !gone
@@ -98,36 +496,49 @@ impl BrowserSession {
     fn try_handle_page(
         &self,
         handled: &mut HashSet<&'static str>,
-        email: Option<&String>,
+        config: &LoginConfig,
         provider: &dyn CredentialsProvider,
         cancel_token: Option<&CancellationToken>,
     ) -> anyhow::Result<(bool, bool)> {
         // Returns (handler_matched, is_mfa_handler)
-        if !handled.contains("pick_account") && self.tab.handle_pick_account()? {
+        let page = self.tab.snapshot()?;
+
+        if !handled.contains("pick_account")
+            && self
+                .tab
+                .handle_pick_account(&page, config.email.as_ref(), provider)?
+        {
             handled.insert("pick_account");
             return Ok((true, false));
         }
 
-        if !handled.contains("session_conflict") && self.tab.handle_session_conflict()? {
+        if !handled.contains("session_conflict")
+            && self
+                .tab
+                .handle_session_conflict(&page, config.session_conflict, provider)?
+        {
             handled.insert("session_conflict");
             return Ok((true, false));
         }
 
         // Check for Azure AD error pages (ConvergedError)
         // This must be checked early as it indicates a fatal authentication error
-        if self.tab.is_azure_error_page()? {
+        if let Some(error) = &page.azure_error {
             handled.insert("azure_error");
-            let error_msg = self.tab.get_azure_error_details()?;
-            log::warn!("Azure AD error page detected: {}", error_msg);
+            log::warn!("Azure AD error page detected: {}", error.details);
+            // 50126: invalid credentials, 50053: locked after too many attempts.
+            if matches!(error.code, Some(50126 | 50053)) {
+                record_password_failure();
+            }
             return Err(AuthError::AuthenticationFailed {
-                reason: error_msg,
+                reason: error.details.clone(),
                 suggest_manual_mode: false, // This is often a simple credential error
                 suggest_clear_cache: true,
             }
             .into());
         }
 
-        if self.tab.is_invalid_username_visible()? {
+        if page.invalid_username {
             handled.insert("invalid_username");
             return Err(AuthError::InvalidUsername {
                 message: "The username you entered may be incorrect or the account does not exist."
@@ -137,7 +548,7 @@ impl BrowserSession {
         }
 
         // Check for username warning (softer error that suggests potential typo)
-        if let Some(warning_text) = self.tab.is_username_warning_visible()? {
+        if let Some(warning_text) = &page.username_warning {
             handled.insert("username_warning");
             return Err(AuthError::UsernameWarning {
                 warning_text: warning_text.trim().to_string(),
@@ -145,28 +556,70 @@ impl BrowserSession {
             .into());
         }
 
-        if self.tab.is_incorrect_password_visible()? {
+        if page.incorrect_password {
             handled.insert("incorrect_password");
             log::warn!("Incorrect password detected");
+            record_password_failure();
             return Err(AuthError::IncorrectPassword {
                 message: "Your account or password is incorrect.".to_string(),
             }
             .into());
         }
 
-        if self.tab.handle_remote_ngc_denied_next()? {
+        if page.update_password {
+            if handled.contains("update_password") {
+                if let Some((error, wrong_current)) = &page.update_password_error {
+                    log::warn!("Password update rejected: {}", error);
+                    if *wrong_current {
+                        record_password_failure();
+                    }
+                    return Err(AuthError::AuthenticationFailed {
+                        reason: format!(
+                            "Your password has expired and could not be updated.\n\n{}",
+                            error
+                        ),
+                        suggest_manual_mode: false,
+                        suggest_clear_cache: false,
+                    }
+                    .into());
+                }
+            } else {
+                check_lockout(&config.lockout, provider)?;
+                if self.tab.handle_update_password(&page, provider)? {
+                    handled.insert("update_password");
+                    // Microsoft may ask to sign in again with the new password.
+                    handled.remove("password");
+                    return Ok((true, false));
+                }
+            }
+        }
+
+        if self.tab.handle_remote_ngc_denied_next(&page)? {
             handled.remove("ngc_push");
             return Ok((true, false));
         }
 
-        if !handled.contains("username") && self.tab.is_input_visible("input[name=\"loginfmt\"]")? {
+        // Unanswered push/call: resend and let the MFA handlers run again.
+        let attempt = self.mfa_resends.get() + 1;
+        if self
+            .tab
+            .handle_mfa_timeout(&page, provider, attempt, config.max_push_resends)?
+        {
+            self.mfa_resends.set(attempt);
+            handled.remove("push");
+            handled.remove("ngc_push");
+            handled.remove("voice_call");
+            return Ok((true, false));
+        }
+
+        if !handled.contains("username") && page.has_input("loginfmt") {
             self.tab.fill_on_screen_and_click(
                 "input[name=\"loginfmt\"]",
                 "Username (email): ",
                 "#idSIButton9",
                 false,
                 true,
-                email,
+                config.email.as_ref(),
                 provider,
             )?;
             handled.insert("username");
@@ -174,13 +627,19 @@ impl BrowserSession {
         }
 
         if !handled.contains("ngc_error_use_password")
-            && self.tab.handle_ngc_error_use_password(handled)?
+            && self
+                .tab
+                .handle_ngc_error_use_password(&page, handled, &config.mfa_preference)?
         {
             handled.insert("ngc_error_use_password");
             return Ok((true, false));
         }
 
-        if !handled.contains("use_app_instead") && self.tab.handle_use_app_instead()? {
+        if !handled.contains("use_app_instead")
+            && self
+                .tab
+                .handle_use_app_instead(&page, &config.mfa_preference)?
+        {
             handled.insert("use_app_instead");
             return Ok((true, false));
         }
@@ -188,13 +647,14 @@ impl BrowserSession {
         if !handled.contains("ngc_push")
             && self
                 .tab
-                .handle_authenticator_ngc_push(provider, cancel_token)?
+                .handle_authenticator_ngc_push(&page, provider, cancel_token)?
         {
             handled.insert("ngc_push");
             return Ok((true, true)); // MFA handler
         }
 
-        if !handled.contains("password") && self.tab.is_input_visible("input[name=\"passwd\"]")? {
+        if !handled.contains("password") && page.has_input("passwd") {
+            check_lockout(&config.lockout, provider)?;
             self.tab.fill_on_screen_and_click(
                 "input[name=\"passwd\"]",
                 "KU Password: ",
@@ -208,39 +668,62 @@ impl BrowserSession {
             return Ok((true, false));
         }
 
-        if !handled.contains("kmsi") && self.tab.click_kmsi_if_present()? {
+        if !handled.contains("kmsi") && self.tab.click_kmsi_if_present(&page)? {
             handled.insert("kmsi");
             return Ok((true, false));
         }
 
+        if !handled.contains("voice_call")
+            && self
+                .tab
+                .handle_voice_call_approval(&page, provider, cancel_token)?
+        {
+            handled.insert("voice_call");
+            return Ok((true, true)); // MFA handler
+        }
+
         if !handled.contains("push")
             && self
                 .tab
-                .handle_authenticator_push_approval(provider, cancel_token)?
+                .handle_authenticator_push_approval(&page, provider, cancel_token)?
         {
             handled.insert("push");
             return Ok((true, true)); // MFA handler
         }
 
-        if !handled.contains("verification_code") && self.tab.handle_verification_code_choice()? {
+        if !handled.contains("verification_code")
+            && self
+                .tab
+                .handle_verification_code_choice(&page, &config.mfa_preference, provider)?
+        {
             handled.insert("verification_code");
             return Ok((true, false));
         }
 
         // Handle OTP/code entry pages (SMS, email, TOTP from authenticator app).
-        // Must run before detect_generic_error, as the code-entry page contains instructional
+        // Must run before the generic error check, as the code-entry page contains instructional
         // text in aria-live regions that would otherwise trigger a false-positive error.
         // Not added to `handled`: if the user enters a wrong code the page stays at the same
         // URL and we need to re-prompt (the handler itself blocks on user input, so it won't
         // loop without user interaction).
-        if self.tab.handle_otp_entry(provider)? {
+        if self.tab.handle_otp_entry(&page, provider)? {
+            return Ok((true, false));
+        }
+
+        // Conditional Access interrupts (proof-up, Terms of Use, consent, ...).
+        // Their pages carry error-like text, so this too runs before the
+        // generic detection.
+        if self
+            .tab
+            .handle_interrupt(&page, config.headless, provider, cancel_token)?
+        {
             return Ok((true, false));
         }
 
         // Generic fallback detection for unexpected errors or page states
         // This catches scenarios we haven't explicitly coded for
         if !handled.contains("generic_error") {
-            if let Some(error_msg) = self.tab.detect_generic_error()? {
+            if let Some(error_msg) = &page.generic_error {
                 handled.insert("generic_error");
                 log::warn!("Generic error detected: {}", error_msg);
                 return Err(AuthError::AuthenticationFailed {
@@ -253,7 +736,7 @@ impl BrowserSession {
         }
 
         if !handled.contains("unexpected_state") {
-            if let Some(state_msg) = self.tab.detect_unexpected_page_state()? {
+            if let Some(state_msg) = &page.unexpected_state {
                 handled.insert("unexpected_state");
                 log::warn!("Unexpected page state: {}", state_msg);
                 return Err(AuthError::AuthenticationFailed {
@@ -285,28 +768,76 @@ impl BrowserSession {
         }
     }
 
+    /// Turns `error` from a page that stopped answering into
+    /// [`BrowserCrashed`] when the browser crashed, or into a browser error
+    /// when it was closed.  A headless browser that went away without saying
+    /// how counts as crashed, as nobody could have closed it.
+    fn lost(
+        &self,
+        config: &LoginConfig,
+        error: anyhow::Error,
+        progress: LoginProgress,
+        cancel_token: Option<&CancellationToken>,
+    ) -> anyhow::Error {
+        if cancel_token.is_some_and(|t| t.is_cancelled()) {
+            return AuthError::Cancelled.into();
+        }
+        let crash = match self.tab.0.loss() {
+            PageLoss::Crashed(how) => Some(how),
+            PageLoss::Unknown if config.headless => Some("the browser went away".to_string()),
+            PageLoss::Closed | PageLoss::Unknown => None,
+        };
+        let Some(how) = crash else {
+            log::warn!("Browser heartbeat lost (manual close?): {}", error);
+            return AuthError::BrowserError {
+                message: format!("Browser connection lost: {}", error),
+            }
+            .into();
+        };
+        log::warn!("Browser crashed during login: {} ({})", how, error);
+        BrowserCrashed {
+            reason: format!("Browser crashed: {}", how),
+            progress,
+        }
+        .into()
+    }
+
+    /// Drives the login from `start_url` (the gateway URL), or from the page
+    /// already open when it is `None`, as after a headless session shows its
+    /// window to hand over to the user.  `progress` carries over the MFA
+    /// state of a login whose browser crashed.
+    ///
+    /// The page is re-examined whenever it reports a change (see
+    /// [`crate::driver`]).  Automation gives up when no handler knows the
+    /// page and it has stopped changing, or when nothing has been handled for
+    /// a long while even though the page keeps changing.
     fn run_login(
         &self,
         config: &LoginConfig,
+        start_url: Option<&str>,
+        progress: LoginProgress,
         provider: &dyn CredentialsProvider,
         cancel_token: Option<&CancellationToken>,
     ) -> anyhow::Result<String> {
-        const MAX_RETRIES: usize = 20;
-        const STUCK_DURATION: Duration = Duration::from_secs(3);
-        const MAX_RESETS: usize = 2; // maximum page resets allowed
-
-        log::info!("Navigating to: {}", config.url);
-        self.tab.0.navigate_to(&config.url)?;
+        /// How long an unhandled page may sit without changing.
+        const QUIET_LIMIT: Duration = Duration::from_secs(10);
+        /// How long the flow may go without handling anything.
+        const STALL_LIMIT: Duration = Duration::from_secs(60);
 
-        if let Err(e) = self.tab.0.wait_until_navigated() {
-            log::warn!("Initial navigation wait timed out: {}, continuing...", e);
+        if let Some(start_url) = start_url {
+            log::info!("Navigating to: {}", start_url);
+            self.tab.0.navigate(start_url)?;
         }
 
-        let mut handled: HashSet<&'static str> = HashSet::new();
+        let LoginProgress {
+            mut mfa_seen,
+            mfa_resends,
+        } = progress;
         let mut last_url = String::new();
-        let mut retries = 0;
-        let mut reset_count = 0;
-        let mut stuck_since: Option<Instant> = None;
+        let mut handled = HashSet::new();
+        let mut last_change = Instant::now();
+        let mut last_progress = Instant::now();
+        self.mfa_resends.set(mfa_resends);
 
         loop {
             if let Some(token) = cancel_token {
@@ -320,15 +851,16 @@ impl BrowserSession {
             match self.tab.poll_dsid(&config.domain) {
                 Ok(Some(dsid)) => {
                     log::info!("Found valid DSID, quitting.");
+                    record_login_success(mfa_seen);
                     return Ok(dsid);
                 }
                 Ok(None) => {} // Keep going
                 Err(e) => {
-                    log::warn!("Browser heartbeat lost (manual close?): {}", e);
-                    return Err(AuthError::BrowserError {
-                        message: format!("Browser connection lost: {}", e),
-                    }
-                    .into());
+                    let progress = LoginProgress {
+                        mfa_seen,
+                        mfa_resends: self.mfa_resends.get(),
+                    };
+                    return Err(self.lost(config, e, progress, cancel_token));
                 }
             }
 
@@ -336,8 +868,7 @@ impl BrowserSession {
             if current_url != last_url {
                 log::info!("Page: {}", current_url);
                 last_url = current_url;
-                retries = 0;
-                stuck_since = None;
+                last_progress = Instant::now();
                 handled.clear(); // Allow re-authentication if Microsoft loops back
             }
 
@@ -350,126 +881,295 @@ impl BrowserSession {
                 // Here we just reset the flag and wire up the guard to read it.
                 self.setup_page_guard(provider);
 
-                let is_in_mfa_wait = match self.try_handle_page(
-                    &mut handled,
-                    config.email.as_ref(),
-                    provider,
-                    cancel_token,
-                ) {
+                match self.try_handle_page(&mut handled, config, provider, cancel_token) {
                     Ok((true, is_mfa)) => {
-                        retries = 0;
-                        stuck_since = None;
-                        is_mfa
-                    }
-                    Ok((false, _)) => {
-                        retries += 1;
-                        if stuck_since.is_none() {
-                            stuck_since = Some(Instant::now());
-                        }
-                        false
+                        last_change = Instant::now();
+                        last_progress = Instant::now();
+                        mfa_seen |= is_mfa;
                     }
+                    Ok((false, _)) => {}
                     Err(e) => {
                         log::warn!("Handler error: {}", e);
                         let cancelled = cancel_token.is_some_and(|t| t.is_cancelled());
-                        if !cancelled {
+                        let handover = e.downcast_ref::<VisibleBrowserRequired>().is_some();
+                        // A handler that failed on a dead page hit a crash or a close.
+                        if !cancelled
+                            && !handover
+                            && e.downcast_ref::<AuthError>().is_none()
+                            && self.tab.0.evaluate("1").is_err()
+                        {
+                            let progress = LoginProgress {
+                                mfa_seen,
+                                mfa_resends: self.mfa_resends.get(),
+                            };
+                            return Err(self.lost(config, e, progress, cancel_token));
+                        }
+                        if !cancelled && !handover {
                             self.capture_and_save_diagnostics(&e.to_string());
                         }
                         return Err(e);
                     }
-                };
+                }
 
                 provider.clear_page_guard();
 
-                // Page reset logic for stuck automation (only in Full Auto mode)
-                let is_stuck = stuck_since
-                    .map(|t| t.elapsed() >= STUCK_DURATION)
-                    .unwrap_or(false);
-                if is_stuck && !is_in_mfa_wait {
-                    reset_count += 1;
-                    stuck_since = None;
-
-                    if reset_count > MAX_RESETS {
-                        let reason = format!(
-                            "Full Auto mode unable to complete login after {} page resets. \
-                            The authentication flow may have changed or network issues occurred.",
-                            MAX_RESETS
-                        );
-                        self.capture_and_save_diagnostics(&reason);
-                        return Err(AuthError::AuthenticationFailed {
-                            reason,
-                            suggest_manual_mode: true,
-                            suggest_clear_cache: true,
-                        }
-                        .into());
-                    }
-
-                    log::warn!(
-                        "Authentication stuck (no progress for {}s). Resetting page... (attempt {}/{})",
-                        STUCK_DURATION.as_secs(),
-                        reset_count,
-                        MAX_RESETS
-                    );
-
+                let stuck = if last_change.elapsed() >= QUIET_LIMIT {
+                    Some(format!(
+                        "no handler for {} and the page has not changed for {}s",
+                        last_url,
+                        QUIET_LIMIT.as_secs()
+                    ))
+                } else if last_progress.elapsed() >= STALL_LIMIT {
+                    Some(format!(
+                        "no progress on {} for {}s",
+                        last_url,
+                        STALL_LIMIT.as_secs()
+                    ))
+                } else {
+                    None
+                };
+                if let Some(stuck) = stuck {
+                    log::warn!("Authentication stuck: {}", stuck);
                     // Log current page state to help diagnose the issue
                     if let Err(e) = self.tab.log_page_state() {
                         log::debug!("Failed to log page state: {}", e);
                     }
-
-                    // Navigate back to initial login URL
-                    if let Err(e) = self.tab.0.navigate_to(&config.url) {
-                        log::warn!(
-                            "Reset navigation failed: {}, continuing with current page",
-                            e
-                        );
-                    } else {
-                        let _ = self.tab.0.wait_until_navigated();
-                        handled.clear(); // Clear handler history to allow re-triggering
-                        retries = 0;
-                        stuck_since = None;
-                        last_url = String::new();
-                    }
-                }
-
-                if retries > MAX_RETRIES {
-                    return Err(AuthError::AuthenticationFailed {
-                        reason: format!(
-                            "Could not find a handler for the current page after {} retries: {}",
-                            MAX_RETRIES, last_url
-                        ),
+                    let reason = format!(
+                        "Full Auto mode unable to complete login: {}. \
+                        The authentication flow may have changed or network issues occurred.",
+                        stuck
+                    );
+                    self.capture_and_save_diagnostics(&reason);
+                    let error = AuthError::AuthenticationFailed {
+                        reason,
                         suggest_manual_mode: true,
-                        suggest_clear_cache: false,
+                        suggest_clear_cache: true,
+                    };
+                    if config.headless {
+                        return Err(VisibleBrowserRequired {
+                            reason: format!("Full Auto got stuck: {}", stuck),
+                            manual: true,
+                            error,
+                        }
+                        .into());
                     }
-                    .into());
+                    return Err(error.into());
                 }
             }
 
-            sleep(Duration::from_millis(400));
+            if self.tab.0.wait_for_change(WAKE_INTERVAL) {
+                last_change = Instant::now();
+            }
         }
     }
 }
 
 // ── Public API ────────────────────────────────────────────────────────────────
 
-/// Runs the browser-based login flow and returns the DSID cookie on success.
+/// Runs the login flow and returns the DSID cookie on success.
+///
+/// With [`LoginEngine::Http`] a headless automated login is first tried
+/// without a browser; if that engine cannot finish, the browser takes over.
+/// A browser pre-warmed for the same settings (see [`crate::prewarm`]) is
+/// used instead of launching a new one.  If the browser crashes mid-login it
+/// is relaunched once on the same profile and the login starts over from
+/// the gateway URL.
 pub fn run_login_and_get_dsid(
     config: &LoginConfig,
     provider: &dyn CredentialsProvider,
     cancel_token: Option<CancellationToken>,
     browser_pid_out: Option<Arc<Mutex<Option<u32>>>>,
 ) -> anyhow::Result<String> {
+    if config.engine == LoginEngine::Http && config.headless && !config.no_auto_login {
+        let result = crate::http_login::run_http_login(config, provider, cancel_token.as_ref());
+        match result.map_err(|e| e.downcast::<HttpLoginUnsupported>()) {
+            Ok(dsid) => return Ok(dsid),
+            Err(Ok(unsupported)) => {
+                log::warn!(
+                    "HTTP login could not finish ({}), using the browser",
+                    unsupported
+                );
+                let mut event = ConnectionEvent::now(EventKind::BrowserFallback);
+                event.message = Some(format!("HTTP login: {}", unsupported));
+                if let Err(e) = append_event(&event) {
+                    log::warn!("Could not record the browser fallback in history: {}", e);
+                }
+            }
+            Err(Err(e)) => return Err(e),
+        }
+    }
+
+    let (session, start_url) = match crate::prewarm::take_warm_browser(config) {
+        Some((session, fresh)) => {
+            log::info!("Using the pre-warmed browser");
+            (session, (!fresh).then_some(config.url.as_str()))
+        }
+        None => {
+            let session = BrowserSession::open(config).map_err(|e| AuthError::BrowserError {
+                message: format!("Failed to create browser: {}", e),
+            })?;
+            (session, Some(config.url.as_str()))
+        }
+    };
+    publish_browser_pid(&session, browser_pid_out.as_ref());
+
+    let mut relaunched = false;
+    let (session, result) = run_recovering(
+        session,
+        config,
+        start_url,
+        provider,
+        cancel_token.as_ref(),
+        browser_pid_out.as_ref(),
+        &mut relaunched,
+    )?;
+    let required = match result.map_err(|e| e.downcast::<VisibleBrowserRequired>()) {
+        Err(Ok(required)) => required,
+        Ok(dsid) => {
+            session.close();
+            return Ok(dsid);
+        }
+        Err(Err(e)) => {
+            session.close();
+            return Err(e);
+        }
+    };
+
+    // Headless automation cannot finish: show the browser and carry on from
+    // where it stopped instead of failing the whole login, if allowed.
+    let manual = required.manual;
+    let allowed = match config.visible_fallback {
+        VisibleFallback::Allow => true,
+        VisibleFallback::Deny => false,
+        VisibleFallback::Ask => {
+            let options = vec![
+                "Show the browser window and finish there".to_string(),
+                "Cancel the login".to_string(),
+            ];
+            let msg = format!("{}. Continue in the browser?", required);
+            provider.request_choice(&msg, &options) == Some(0)
+        }
+    };
+    if !allowed {
+        log::warn!("{}, and showing the browser is not allowed", required);
+        session.close();
+        return Err(required.error.into());
+    }
+    if let Err(e) = session.reveal() {
+        log::warn!("{}, but the browser cannot be shown: {}", required, e);
+        session.close();
+        return Err(required.error.into());
+    }
+    log::warn!(
+        "{}, continuing in the browser window ({} mode)",
+        required,
+        if manual { "manual" } else { "visual" }
+    );
+    let mut event = ConnectionEvent::now(EventKind::BrowserFallback);
+    event.message = Some(required.to_string());
+    if let Err(e) = append_event(&event) {
+        log::warn!("Could not record the browser fallback in history: {}", e);
+    }
+
+    let fallback = LoginConfig {
+        headless: false,
+        no_auto_login: manual,
+        ..config.clone()
+    };
+    if manual {
+        provider.on_browser_action("Sign-in");
+    }
+    let recovered = run_recovering(
+        session,
+        &fallback,
+        None,
+        provider,
+        cancel_token.as_ref(),
+        browser_pid_out.as_ref(),
+        &mut relaunched,
+    );
+    if manual {
+        provider.on_mfa_complete();
+    }
+    let (session, result) = recovered?;
+    session.close();
+    result
+}
+
+/// Runs the login on `session`.  If its browser crashes, the browser is
+/// relaunched on the same profile and the login starts over from the
+/// gateway URL, unless `relaunched` says this login already used its one relaunch.
+///
+/// Returns the session the login ended on, or an error if the relaunch
+/// failed.
+fn run_recovering(
+    session: BrowserSession,
+    config: &LoginConfig,
+    start_url: Option<&str>,
+    provider: &dyn CredentialsProvider,
+    cancel_token: Option<&CancellationToken>,
+    pid_out: Option<&Arc<Mutex<Option<u32>>>>,
+    relaunched: &mut bool,
+) -> anyhow::Result<(BrowserSession, anyhow::Result<String>)> {
+    let result = session.run_login(
+        config,
+        start_url,
+        LoginProgress::default(),
+        provider,
+        cancel_token,
+    );
+    let crashed = match result.map_err(|e| e.downcast::<BrowserCrashed>()) {
+        Ok(dsid) => return Ok((session, Ok(dsid))),
+        Err(Ok(crashed)) if !*relaunched => crashed,
+        Err(Ok(crashed)) => return Ok((session, Err(gave_up(crashed)))),
+        Err(Err(e)) => return Ok((session, Err(e))),
+    };
+
+    *relaunched = true;
+    log::warn!("{}, relaunching it", crashed);
+    let mut event = ConnectionEvent::now(EventKind::BrowserCrash);
+    event.message = Some(crashed.reason.clone());
+    if let Err(e) = append_event(&event) {
+        log::warn!("Could not record the browser crash in history: {}", e);
+    }
+
+    // Dropping the old browser kills it, should it still hold the profile.
+    session.close();
     let session = BrowserSession::open(config).map_err(|e| AuthError::BrowserError {
-        message: format!("Failed to create browser: {}", e),
+        message: format!("Failed to relaunch the browser: {}", e),
     })?;
+    publish_browser_pid(&session, pid_out);
+
+    let result = session
+        .run_login(
+            config,
+            Some(&config.url),
+            crashed.progress,
+            provider,
+            cancel_token,
+        )
+        .map_err(|e| match e.downcast::<BrowserCrashed>() {
+            Ok(crashed) => gave_up(crashed),
+            Err(e) => e,
+        });
+    Ok((session, result))
+}
 
-    if let Some(ref pid_holder) = browser_pid_out {
-        if let Some(pid) = session.browser.get_process_id() {
+/// The error for a browser that crashed again after its relaunch.
+fn gave_up(crashed: BrowserCrashed) -> anyhow::Error {
+    AuthError::BrowserError {
+        message: format!("{} (again after a relaunch)", crashed.reason),
+    }
+    .into()
+}
+
+/// Stores the session's browser PID so callers can kill it on cancel.
+fn publish_browser_pid(session: &BrowserSession, pid_out: Option<&Arc<Mutex<Option<u32>>>>) {
+    if let Some(pid_holder) = pid_out {
+        if let Some(pid) = session.tab.0.pid() {
             if let Ok(mut guard) = pid_holder.lock() {
                 *guard = Some(pid);
             }
         }
     }
-
-    let result = session.run_login(config, provider, cancel_token.as_ref());
-    session.close();
-    result
 }
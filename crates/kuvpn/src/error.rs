@@ -23,6 +23,12 @@ pub enum AuthError {
     IncorrectPassword {
         message: String,
     },
+    /// Too many recent incorrect passwords; automated sign-in is paused so
+    /// the account does not get locked.
+    CredentialLockout {
+        failures: u32,
+        retry_after_secs: u64,
+    },
 
     /// Errors that may require manual mode or session cleanup
     AuthenticationFailed {
@@ -53,6 +59,7 @@ impl AuthError {
             AuthError::InvalidUsername { .. }
             | AuthError::UsernameWarning { .. }
             | AuthError::IncorrectPassword { .. }
+            | AuthError::CredentialLockout { .. }
             | AuthError::AuthenticationFailed { .. } => ErrorCategory::Authentication,
             AuthError::BrowserError { .. } | AuthError::Timeout { .. } | AuthError::Cancelled => {
                 ErrorCategory::System
@@ -68,6 +75,7 @@ impl AuthError {
             AuthError::InvalidUsername { .. }
                 | AuthError::UsernameWarning { .. }
                 | AuthError::IncorrectPassword { .. }
+                | AuthError::CredentialLockout { .. }
         )
     }
 
@@ -116,6 +124,16 @@ impl AuthError {
                     message
                 )
             }
+            AuthError::CredentialLockout {
+                failures,
+                retry_after_secs,
+            } => {
+                format!(
+                    "Sign-in paused.\n\n{} recent incorrect password attempts. To avoid locking your account, the password will not be submitted automatically for another {}.\n\nWait, or re-enter the password yourself to try again now.",
+                    failures,
+                    crate::history::format_duration_secs(*retry_after_secs)
+                )
+            }
             AuthError::AuthenticationFailed { reason, .. } => {
                 format!("Authentication failed.\n\n{}", reason)
             }
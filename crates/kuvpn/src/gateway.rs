@@ -0,0 +1,25 @@
+//! Direct HTTP requests to the Pulse Secure / Ivanti gateway.
+
+use std::time::Duration;
+
+/// Ends the gateway session identified by `dsid`.
+///
+/// Killing openconnect leaves the session open on the gateway until it times
+/// out, and the next login then lands on the session-conflict page (or
+/// openconnect is rejected outright).  Logging out explicitly frees the slot.
+pub fn logout(url: &str, dsid: &str) -> anyhow::Result<()> {
+    let logout_url = format!("{}/dana-na/auth/logout.cgi", url.trim_end_matches('/'));
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(5)))
+        .max_redirects(0)
+        .http_status_as_error(false)
+        .build()
+        .into();
+
+    let response = agent
+        .get(&logout_url)
+        .header("Cookie", &format!("DSID={}", dsid))
+        .call()?;
+    log::debug!("Gateway logout returned HTTP {}", response.status());
+    Ok(())
+}
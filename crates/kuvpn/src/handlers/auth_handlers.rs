@@ -1,10 +1,48 @@
+use super::locale::{phrases_js, MATCHES_JS};
+use super::snapshot::PageSnapshot;
 use super::AuthTab;
+use crate::dsid::SessionConflictPolicy;
 use crate::utils::CredentialsProvider;
 use std::thread::sleep;
 use std::time::Duration;
 
+/// Classifier fragment for the pages handled here: the account picker, the
+/// session conflict, "Stay signed in?" and the Authenticator "Request
+/// denied" page.
+pub(super) fn classify_js() -> String {
+    // The "Use another account" tile only exists on the picker, so the
+    // header text is just a fallback for tenants that hide it.
+    let pick_account = phrases_js(|p| p.pick_account);
+    let kmsi_yes = phrases_js(|p| p.kmsi_yes);
+    let denied = phrases_js(|p| p.request_denied);
+    let denied_detail = phrases_js(|p| p.request_denied_detail);
+    format!(
+        r#"
+    var header = byId('loginHeader');
+    var form = byId('i0281');
+    var tiles = document.querySelectorAll(
+        '#tilesHolder .tile[role="listitem"], #tilesHolder .tile-container .table[role="button"]'
+    );
+    snap.pickAccount = !!(form && tiles.length > 0 && (byId('otherTile')
+        || (header && {MATCHES_JS}(header.innerText, {pick_account}))));
+
+    snap.sessionConflict = !!(document.querySelector('#DSIDConfirmForm')
+        && document.querySelector('#btnContinue'));
+
+    var next = byId('idSIButton9');
+    snap.kmsi = visible(next) && !!(pageId === 'KmsiInterrupt' || byId('KmsiCheckboxField')
+        || {MATCHES_JS}(next.value, {kmsi_yes}));
+
+    var desc = byId('idDiv_RemoteNGC_PageDescription');
+    snap.ngcDenied = !!(form && next
+        && header && {MATCHES_JS}(header.innerText, {denied})
+        && desc && {MATCHES_JS}(desc.innerText, {denied_detail}));"#
+    )
+}
+
 impl AuthTab {
-    /// Fills an input field on screen and clicks a button.
+    /// Fills an input field the snapshot found visible and clicks a button.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn fill_on_screen_and_click(
         &self,
         input_selector: &str,
@@ -15,55 +53,119 @@ impl AuthTab {
         value: Option<&String>,
         provider: &dyn CredentialsProvider,
     ) -> anyhow::Result<()> {
-        if self.is_input_visible(input_selector)? {
-            let value = if let Some(v) = value {
-                v.to_owned()
-            } else {
-                // Inject a watcher that flags when THIS input disappears.
-                // The page guard (set in dsid.rs) reads window.__kuvpn_input_gone
-                // and dismisses the prompt if it becomes true.
-                self.inject_input_watcher(input_selector);
-
-                let result = if is_password {
-                    provider.request_password(msg)
-                } else if is_email {
-                    provider.request_email(msg)
-                } else {
-                    provider.request_text(msg)
-                };
-
-                // Clean up the watcher regardless of outcome
-                self.clear_input_watcher();
+        let value = if let Some(v) = value {
+            v.to_owned()
+        } else {
+            // Inject a watcher that flags when THIS input disappears.
+            // The page guard (set in dsid.rs) reads window.__kuvpn_input_gone
+            // and dismisses the prompt if it becomes true.
+            self.inject_input_watcher(input_selector);
 
-                match result {
-                    Some(v) => v,
-                    None => return Ok(()), // prompt dismissed (page changed)
-                }
+            let result = if is_password {
+                provider.request_password(msg)
+            } else if is_email {
+                provider.request_email(msg)
+            } else {
+                provider.request_text(msg)
             };
 
-            self.fill_input_value(input_selector, &value)?;
-            sleep(Duration::from_millis(250));
+            // Clean up the watcher regardless of outcome
+            self.clear_input_watcher();
 
-            let js_btn = format!(
-                "var btn=document.querySelector('{btn}'); if(btn){{btn.focus();btn.click();}}",
-                btn = button_selector
-            );
-            self.eval(&js_btn)?;
-        }
+            match result {
+                Some(v) => v,
+                None => return Ok(()), // prompt dismissed (page changed)
+            }
+        };
+
+        self.fill_input_value(input_selector, &value)?;
+        sleep(Duration::from_millis(250));
+
+        let js_btn = format!(
+            "var btn=document.querySelector('{btn}'); if(btn){{btn.focus();btn.click();}}",
+            btn = button_selector
+        );
+        self.eval(&js_btn)?;
 
         Ok(())
     }
 
-    /// Clicks the "Keep me signed in" button if present.
-    pub(crate) fn click_kmsi_if_present(&self) -> anyhow::Result<bool> {
-        let visible = self.eval_bool(
-            r#"(function() {
-    var btn = document.querySelector('#idSIButton9');
-    return !!(btn && btn.offsetParent !== null && btn.value === 'Yes');
-})()"#,
+    /// Fills Azure's "Update your password" page for an expired password.
+    ///
+    /// Asks for the current password (when the page has a field for it), the
+    /// new password and its confirmation, re-asking until the two new entries
+    /// match, then submits.  Returns `false` if a prompt was dismissed.
+    pub(crate) fn handle_update_password(
+        &self,
+        snapshot: &PageSnapshot,
+        provider: &dyn CredentialsProvider,
+    ) -> anyhow::Result<bool> {
+        const CURRENT: &str = "input[name=\"oldpasswd\"], #currentPassword";
+        const NEW: &str = "input[name=\"newpasswd\"], #newPassword";
+        const CONFIRM: &str = "input[name=\"confirmnewpasswd\"], #confirmNewPassword";
+
+        log::info!("Password expired, asking for a new one");
+        self.inject_input_watcher(NEW);
+        let ask_current = snapshot.has_input("oldpasswd") || snapshot.has_input("currentPassword");
+        let entered = self.ask_for_new_password(ask_current, provider);
+        self.clear_input_watcher();
+
+        let Some((current, new)) = entered else {
+            return Ok(false); // prompt dismissed (page changed)
+        };
+
+        if let Some(current) = current {
+            self.fill_input_value(CURRENT, &current)?;
+        }
+        self.fill_input_value(NEW, &new)?;
+        self.fill_input_value(CONFIRM, &new)?;
+        sleep(Duration::from_millis(250));
+        self.eval(
+            "var btn=document.querySelector('#idSIButton9'); \
+             if(btn){btn.focus();btn.click();}",
         )?;
+        log::info!("Submitted the new password");
+        sleep(Duration::from_millis(500));
+        Ok(true)
+    }
 
-        if visible {
+    /// Prompts for the passwords of the update page.  Returns the current
+    /// password (if `ask_current`) and the confirmed new one, or `None` once
+    /// any prompt is dismissed.
+    fn ask_for_new_password(
+        &self,
+        ask_current: bool,
+        provider: &dyn CredentialsProvider,
+    ) -> Option<(Option<String>, String)> {
+        let current = if ask_current {
+            Some(provider.request_password("Your password has expired. Current password: ")?)
+        } else {
+            None
+        };
+
+        let mut msg = "New password: ";
+        loop {
+            let new = provider.request_password(msg)?;
+            if new.is_empty() {
+                msg = "The new password cannot be empty. New password: ";
+                continue;
+            }
+            if current.as_deref() == Some(new.as_str()) {
+                msg = "The new password must differ from the current one. New password: ";
+                continue;
+            }
+            let confirm = provider.request_password("Confirm new password: ")?;
+            if confirm == new {
+                return Some((current, new));
+            }
+            log::warn!("New password entries do not match, asking again");
+            msg = "Passwords do not match. New password: ";
+        }
+    }
+
+    /// Clicks the "Keep me signed in" button if present.
+    pub(crate) fn click_kmsi_if_present(&self, snapshot: &PageSnapshot) -> anyhow::Result<bool> {
+        if snapshot.kmsi {
             log::info!("Detected KMSI – pressing Yes...");
             self.eval(
                 "var chk=document.querySelector('#KmsiCheckboxField'); \
@@ -80,44 +182,97 @@ impl AuthTab {
         Ok(false)
     }
 
-    /// Handles the session-conflict page (existing VPN session detected).
-    pub(crate) fn handle_session_conflict(&self) -> anyhow::Result<bool> {
-        let is_conflict_page = self.eval_bool(
-            r#"(function() {
-    var form = document.querySelector('#DSIDConfirmForm');
-    var btn = document.querySelector('#btnContinue');
-    return !!(form && btn);
-})()"#,
-        )?;
-
-        if is_conflict_page {
-            log::info!("Detected existing VPN session. Continuing...");
-            self.eval(r#"var btn=document.getElementById('btnContinue'); if(btn){btn.click();}"#)?;
-            sleep(Duration::from_millis(500));
-            return Ok(true);
+    /// Handles the session-conflict page (existing VPN sessions detected).
+    ///
+    /// The gateway lists the open sessions with a checkbox each; the checked
+    /// ones are ended when the form is continued.  `policy` decides which to
+    /// check, asking the user through `provider` for
+    /// [`SessionConflictPolicy::Ask`].
+    pub(crate) fn handle_session_conflict(
+        &self,
+        snapshot: &PageSnapshot,
+        policy: SessionConflictPolicy,
+        provider: &dyn CredentialsProvider,
+    ) -> anyhow::Result<bool> {
+        if !snapshot.session_conflict {
+            return Ok(false);
         }
 
-        Ok(false)
+        let sessions = self.conflicting_sessions()?;
+        log::info!(
+            "Detected {} existing VPN session(s) on the gateway",
+            sessions.len()
+        );
+        let close: Vec<usize> = match policy {
+            SessionConflictPolicy::CloseAll => (0..sessions.len()).collect(),
+            SessionConflictPolicy::KeepAll => Vec::new(),
+            SessionConflictPolicy::Ask if sessions.is_empty() => Vec::new(),
+            SessionConflictPolicy::Ask => {
+                let mut options: Vec<String> =
+                    sessions.iter().map(|s| format!("End {}", s)).collect();
+                options.push("End all listed sessions".to_string());
+                options.push("Keep all and continue".to_string());
+                self.inject_input_watcher("#DSIDConfirmForm");
+                let picked = provider.request_choice(
+                    "You already have a VPN session open. Which one should be ended?",
+                    &options,
+                );
+                self.clear_input_watcher();
+                match picked {
+                    Some(i) if i < sessions.len() => vec![i],
+                    Some(i) if i == sessions.len() => (0..sessions.len()).collect(),
+                    Some(_) => Vec::new(),
+                    None => return Ok(false), // prompt dismissed (page changed)
+                }
+            }
+        };
+
+        for &i in &close {
+            log::info!("Ending existing session: {}", sessions[i]);
+        }
+        self.eval(&format!(
+            r#"(function() {{
+    var close = {close};
+    var boxes = {SESSION_CHECKBOXES_JS};
+    boxes.forEach(function(box, i) {{
+        if (box.checked !== (close.indexOf(i) !== -1)) {{ box.click(); }}
+    }});
+    var btn = document.getElementById('btnContinue');
+    if (btn) {{ btn.click(); }}
+}})()"#,
+            close = serde_json::json!(close),
+        ))?;
+        sleep(Duration::from_millis(500));
+        Ok(true)
     }
 
-    /// Detects the "Request denied" Authenticator page and presses Next.
-    pub(crate) fn handle_remote_ngc_denied_next(&self) -> anyhow::Result<bool> {
-        let is_denied_page = self.eval_bool(
-            r#"(function() {
-            var header = document.getElementById('loginHeader');
-            var desc = document.getElementById('idDiv_RemoteNGC_PageDescription');
-            var form = document.getElementById('i0281');
-            var btn = document.getElementById('idSIButton9');
-            return !!(
-                form && btn &&
-                header &&
-                header.innerText.trim().toLowerCase() === "request denied" &&
-                desc && desc.innerText.toLowerCase().includes("but you denied it")
-            );
-        })()"#,
+    /// Describes each session listed on the session-conflict page, in page
+    /// order, from the text of its table row.
+    fn conflicting_sessions(&self) -> anyhow::Result<Vec<String>> {
+        let json = self.eval_string_or(
+            &format!(
+                r#"(function() {{
+            var boxes = {SESSION_CHECKBOXES_JS};
+            return JSON.stringify(boxes.map(function(box, i) {{
+                var row = box.closest('tr');
+                var cells = row ? Array.prototype.slice.call(row.querySelectorAll('td')) : [];
+                var text = cells.map(function(td) {{ return td.innerText.trim(); }})
+                    .filter(Boolean).join(' · ');
+                return text || ('session ' + (i + 1));
+            }}));
+        }})()"#
+            ),
+            "[]",
         )?;
+        Ok(serde_json::from_str(&json)?)
+    }
 
-        if is_denied_page {
+    /// Detects the "Request denied" Authenticator page and presses Next.
+    pub(crate) fn handle_remote_ngc_denied_next(
+        &self,
+        snapshot: &PageSnapshot,
+    ) -> anyhow::Result<bool> {
+        if snapshot.ngc_denied {
             log::info!("Authenticator denied page detected. Pressing Next...");
             self.eval(
                 "var btn=document.getElementById('idSIButton9'); \
@@ -130,39 +285,237 @@ impl AuthTab {
         Ok(false)
     }
 
-    /// Detects the "Pick an account" picker and selects the first account.
-    pub(crate) fn handle_pick_account(&self) -> anyhow::Result<bool> {
-        let is_picker = self.eval_bool(
-            r#"(function() {
-            var header = document.getElementById('loginHeader');
-            var form = document.getElementById('i0281');
-            var tiles = document.querySelectorAll(
-                '#tilesHolder .tile[role="listitem"], #tilesHolder .tile-container .table[role="button"]'
-            );
-            return !!(
-                form &&
-                header &&
-                header.innerText.trim().toLowerCase() === "pick an account" &&
-                tiles.length > 0
-            );
-        })()"#,
-        )?;
+    /// Detects the "Pick an account" picker and selects the configured account.
+    ///
+    /// Tiles are matched against `email` by their `data-test-id` or the UPN
+    /// shown on the tile.  With an email but no matching tile, clicks "Use
+    /// another account"; without an email, a single tile is picked directly and
+    /// several tiles are offered to the user as a choice.
+    pub(crate) fn handle_pick_account(
+        &self,
+        snapshot: &PageSnapshot,
+        email: Option<&String>,
+        provider: &dyn CredentialsProvider,
+    ) -> anyhow::Result<bool> {
+        if !snapshot.pick_account {
+            return Ok(false);
+        }
 
-        if is_picker {
-            log::info!("'Pick an account' page detected. Selecting the first account...");
-            self.eval(
-                r#"(function() {
-                var btn = document.querySelector(
-                    '#tilesHolder .tile-container .table[role="button"], \
-                     #tilesHolder .tile[role="listitem"] .table[role="button"]'
+        let accounts = self.account_tiles()?;
+        let choice = match email {
+            Some(email) => accounts
+                .iter()
+                .position(|a| a.upn.eq_ignore_ascii_case(email.trim())),
+            None if accounts.len() == 1 => Some(0),
+            None => {
+                let mut options: Vec<String> = accounts.iter().map(|a| a.label.clone()).collect();
+                options.push("Use another account".to_string());
+                self.inject_input_watcher("#tilesHolder");
+                let picked = provider.request_choice("Pick an account", &options);
+                self.clear_input_watcher();
+                match picked {
+                    Some(i) if i < accounts.len() => Some(i),
+                    Some(_) => None,
+                    None => return Ok(false), // prompt dismissed (page changed)
+                }
+            }
+        };
+
+        match choice {
+            Some(index) => {
+                log::info!(
+                    "'Pick an account' page detected. Selecting {}...",
+                    accounts[index].upn
                 );
-                if (btn) { btn.focus(); btn.click(); }
+                self.eval(&format!(
+                    r#"(function() {{
+                var tiles = {ACCOUNT_TILES_JS};
+                var btn = tiles[{index}];
+                if (btn) {{ btn.focus(); btn.click(); }}
+            }})()"#
+                ))?;
+            }
+            None => {
+                log::info!("'Pick an account' page detected. Choosing 'Use another account'...");
+                self.eval(
+                    r#"(function() {
+                var other = document.querySelector('#otherTile .table[role="button"], #otherTile, #otherTileText');
+                if (other) { other.focus(); other.click(); }
             })()"#,
-            )?;
-            sleep(Duration::from_millis(500));
-            return Ok(true);
+                )?;
+            }
         }
+        sleep(Duration::from_millis(500));
+        Ok(true)
+    }
 
-        Ok(false)
+    /// Lists the account tiles on the "Pick an account" page, excluding the
+    /// "Use another account" tile, in display order.
+    fn account_tiles(&self) -> anyhow::Result<Vec<AccountTile>> {
+        let json = self.eval_string_or(
+            &format!(
+                r#"(function() {{
+            var tiles = {ACCOUNT_TILES_JS};
+            return JSON.stringify(tiles.map(function(btn) {{
+                var holder = btn.closest('[data-test-id]') || btn.querySelector('[data-test-id]');
+                var upn = holder ? holder.getAttribute('data-test-id') : '';
+                var text = btn.innerText.trim();
+                if (!upn) {{
+                    var match = text.match(/[^\s@]+@[^\s@]+\.[^\s@]+/);
+                    upn = match ? match[0] : '';
+                }}
+                return {{ upn: upn, label: text.split('\n').filter(Boolean).join(' — ') }};
+            }}));
+        }})()"#
+            ),
+            "[]",
+        )?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// One account tile on the "Pick an account" page.
+#[derive(serde::Deserialize)]
+struct AccountTile {
+    upn: String,
+    label: String,
+}
+
+/// JS expression evaluating to the per-session checkboxes of the
+/// session-conflict page.
+const SESSION_CHECKBOXES_JS: &str = r#"Array.prototype.slice.call(document.querySelectorAll(
+    '#DSIDConfirmForm input[type="checkbox"][name="postfixSID"]'
+))"#;
+
+/// JS expression evaluating to the clickable account tiles, excluding
+/// "Use another account".
+const ACCOUNT_TILES_JS: &str = r#"Array.prototype.slice.call(document.querySelectorAll(
+    '#tilesHolder .tile-container .table[role="button"], #tilesHolder .tile[role="listitem"] .table[role="button"]'
+)).filter(function(el, i, all) {
+    return !el.closest('#otherTile') && all.indexOf(el) === i;
+})"#;
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{tab, Chooser};
+    use super::*;
+    use crate::driver::fake::ScriptedPage;
+    use serde_json::json;
+
+    const LOGIN: &str = "https://login.microsoftonline.com/common/oauth2/authorize";
+
+    /// A picker page listing `upns`.
+    fn picker(upns: &[&str]) -> ScriptedPage {
+        let tiles: Vec<_> = upns
+            .iter()
+            .map(|upn| json!({ "upn": upn, "label": format!("Student — {upn}") }))
+            .collect();
+        ScriptedPage::new(LOGIN).on_script(
+            "JSON.stringify(tiles.map",
+            json!(serde_json::Value::Array(tiles).to_string()),
+        )
+    }
+
+    fn picker_snapshot() -> PageSnapshot {
+        PageSnapshot {
+            pick_account: true,
+            ..Default::default()
+        }
+    }
+
+    /// The tile the handler clicked, `None` for "Use another account".
+    fn clicked_tile(page: &ScriptedPage) -> Option<usize> {
+        let last = page.evaluated().pop().expect("nothing was clicked");
+        if last.contains("#otherTileText") {
+            return None;
+        }
+        let start = last.find("tiles[").expect("no tile clicked") + "tiles[".len();
+        let end = start + last[start..].find(']').unwrap();
+        Some(last[start..end].parse().unwrap())
+    }
+
+    #[test]
+    fn keep_signed_in_is_confirmed_only_when_shown() {
+        let (auth, page) = tab(ScriptedPage::new(LOGIN));
+        assert!(!auth
+            .click_kmsi_if_present(&PageSnapshot::default())
+            .unwrap());
+        assert!(page.evaluated().is_empty());
+
+        let kmsi = PageSnapshot {
+            kmsi: true,
+            ..Default::default()
+        };
+        assert!(auth.click_kmsi_if_present(&kmsi).unwrap());
+        let evaluated = page.evaluated();
+        assert!(evaluated[0].contains("#KmsiCheckboxField"));
+        assert!(evaluated[1].contains("#idSIButton9"));
+    }
+
+    #[test]
+    fn pick_account_selects_the_configured_email() {
+        let (auth, page) = tab(picker(&["a@ku.edu.tr", "b@ku.edu.tr"]));
+        let chooser = Chooser::new(None);
+        let email = " B@KU.edu.tr ".to_string();
+        assert!(auth
+            .handle_pick_account(&picker_snapshot(), Some(&email), &chooser)
+            .unwrap());
+        assert_eq!(clicked_tile(&page), Some(1));
+        assert!(chooser.asked().is_empty());
+    }
+
+    #[test]
+    fn pick_account_uses_another_account_for_an_unlisted_email() {
+        let (auth, page) = tab(picker(&["a@ku.edu.tr"]));
+        let email = "c@ku.edu.tr".to_string();
+        assert!(auth
+            .handle_pick_account(&picker_snapshot(), Some(&email), &Chooser::new(None))
+            .unwrap());
+        assert_eq!(clicked_tile(&page), None);
+    }
+
+    #[test]
+    fn pick_account_without_email_takes_a_single_tile() {
+        let (auth, page) = tab(picker(&["a@ku.edu.tr"]));
+        let chooser = Chooser::new(None);
+        assert!(auth
+            .handle_pick_account(&picker_snapshot(), None, &chooser)
+            .unwrap());
+        assert_eq!(clicked_tile(&page), Some(0));
+        assert!(chooser.asked().is_empty());
+    }
+
+    #[test]
+    fn pick_account_without_email_asks_between_several_tiles() {
+        let (auth, page) = tab(picker(&["a@ku.edu.tr", "b@ku.edu.tr"]));
+        let chooser = Chooser::new(Some(1));
+        assert!(auth
+            .handle_pick_account(&picker_snapshot(), None, &chooser)
+            .unwrap());
+        assert_eq!(clicked_tile(&page), Some(1));
+        let asked = chooser.asked();
+        assert_eq!(
+            asked[0].1,
+            [
+                "Student — a@ku.edu.tr",
+                "Student — b@ku.edu.tr",
+                "Use another account"
+            ]
+        );
+
+        let (auth, page) = tab(picker(&["a@ku.edu.tr", "b@ku.edu.tr"]));
+        assert!(auth
+            .handle_pick_account(&picker_snapshot(), None, &Chooser::new(Some(2)))
+            .unwrap());
+        assert_eq!(clicked_tile(&page), None);
+    }
+
+    #[test]
+    fn pick_account_clicks_nothing_when_the_prompt_is_dismissed() {
+        let (auth, page) = tab(picker(&["a@ku.edu.tr", "b@ku.edu.tr"]));
+        assert!(!auth
+            .handle_pick_account(&picker_snapshot(), None, &Chooser::new(None))
+            .unwrap());
+        assert!(!page.evaluated().iter().any(|js| js.contains("click()")));
     }
 }
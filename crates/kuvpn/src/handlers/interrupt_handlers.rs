@@ -0,0 +1,125 @@
+//! Conditional Access interrupts shown between sign-in and the VPN portal:
+//! security-info registration (proof-up), Terms of Use, app consent and the
+//! "Is this your organization?" confirmation.
+
+use super::locale::{phrases_js, MATCHES_JS};
+use super::snapshot::PageSnapshot;
+use super::{AuthTab, WAKE_INTERVAL};
+use crate::dsid::VisibleBrowserRequired;
+use crate::error::AuthError;
+use crate::utils::{CancellationToken, CredentialsProvider};
+use std::thread::sleep;
+use std::time::Duration;
+
+/// Classifier fragment for `interrupt`: the name of the interrupt page
+/// currently shown, if any.
+pub(super) fn classify_js() -> String {
+    let proof_up = phrases_js(|p| p.interrupt_proof_up);
+    let terms = phrases_js(|p| p.interrupt_terms);
+    let consent = phrases_js(|p| p.interrupt_consent);
+    let organization = phrases_js(|p| p.interrupt_organization);
+    format!(
+        r#"
+    var url = window.location.href.toLowerCase();
+    if (pageId === 'ConvergedProofUpRedirect' || visible(byId('idSubmit_ProofUp_Redirect'))
+        || visible(byId('btnAskLater')) || {MATCHES_JS}(headings, {proof_up})) {{
+        snap.interrupt = 'Security info registration';
+    }} else if (url.indexOf('termsofuse') !== -1 || pageId.indexOf('TermsOfUse') !== -1
+        || {MATCHES_JS}(headings, {terms})) {{
+        snap.interrupt = 'Terms of Use';
+    }} else if (pageId.indexOf('Consent') !== -1 || {MATCHES_JS}(headings, {consent})) {{
+        snap.interrupt = 'App consent';
+    }} else if ({MATCHES_JS}(headings, {organization})) {{
+        snap.interrupt = 'Organization confirmation';
+    }}"#
+    )
+}
+
+impl AuthTab {
+    /// Clicks "Ask later" / "Skip for now" on the current page.  Returns
+    /// `false` if the page offers no way to skip.
+    fn click_interrupt_skip(&self) -> anyhow::Result<bool> {
+        let skip = phrases_js(|p| p.interrupt_skip);
+        self.eval_bool(&format!(
+            r#"(function() {{
+    var later = document.getElementById('btnAskLater');
+    if (later && later.offsetParent !== null) {{ later.click(); return true; }}
+    var phrases = {skip};
+    var links = document.querySelectorAll('a, button, [role="button"], input[type="button"], input[type="submit"]');
+    for (var i = 0; i < links.length; i++) {{
+        var text = links[i].innerText || links[i].value || '';
+        if (links[i].offsetParent !== null && {MATCHES_JS}(text, phrases)) {{
+            links[i].click();
+            return true;
+        }}
+    }}
+    return false;
+}})()"#
+        ))
+    }
+
+    /// Handles a Conditional Access interrupt page.
+    ///
+    /// Skippable pages are skipped.  Blocking ones are brought to the front
+    /// for the user to finish, waiting until the page goes away; in a
+    /// headless browser a [`VisibleBrowserRequired`] error is returned instead.
+    pub(crate) fn handle_interrupt(
+        &self,
+        snapshot: &PageSnapshot,
+        headless: bool,
+        provider: &dyn CredentialsProvider,
+        cancel_token: Option<&CancellationToken>,
+    ) -> anyhow::Result<bool> {
+        let Some(page) = snapshot.interrupt.clone() else {
+            return Ok(false);
+        };
+
+        if self.click_interrupt_skip()? {
+            log::info!("{} interrupt: skipped", page);
+            sleep(Duration::from_millis(500));
+            return Ok(true);
+        }
+
+        if headless {
+            log::warn!(
+                "{} interrupt needs the user, a visible browser is required",
+                page
+            );
+            return Err(VisibleBrowserRequired {
+                reason: format!("{} needs the user", page),
+                manual: false,
+                error: AuthError::AuthenticationFailed {
+                    reason: format!(
+                        "{} needs to be completed in the browser. \
+                        Sign in once in Visual Auto mode to finish it.",
+                        page
+                    ),
+                    suggest_manual_mode: true,
+                    suggest_clear_cache: false,
+                },
+            }
+            .into());
+        }
+
+        log::info!(
+            "{} interrupt needs the user, waiting for it in the browser",
+            page
+        );
+        let _ = self.0.bring_to_front();
+        provider.on_browser_action(&page);
+        let prev_url = self.get_url();
+        loop {
+            if cancel_token.is_some_and(|t| t.is_cancelled()) {
+                provider.on_mfa_complete();
+                return Err(AuthError::Cancelled.into());
+            }
+            self.0.wait_for_change(WAKE_INTERVAL);
+            if self.get_url() != prev_url || self.snapshot()?.interrupt.as_ref() != Some(&page) {
+                break;
+            }
+        }
+        provider.on_mfa_complete();
+        log::info!("{} interrupt finished, moving on...", page);
+        Ok(true)
+    }
+}
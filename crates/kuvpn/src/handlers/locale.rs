@@ -0,0 +1,406 @@
+//! Per-locale phrase tables for the text-based page matchers.
+//!
+//! Most pages are recognised by element ids; the remaining checks compare
+//! visible text against these tables.  The page language is read from
+//! `<html lang>` in the page itself, and English is always tried as a
+//! fallback because Microsoft leaves some strings untranslated.
+
+use crate::dsid::MfaMethod;
+
+/// Phrases for one UI language.  Matching is case- and accent-insensitive
+/// (see [`MATCHES_JS`]), so entries only need to be recognisable fragments.
+pub(crate) struct Phrases {
+    /// Primary language subtag as found in `<html lang>` (`en`, `tr`, …).
+    pub(crate) lang: &'static str,
+    /// Header of the "Pick an account" page.
+    pub(crate) pick_account: &'static [&'static str],
+    /// Header of the Authenticator "Request denied" page.
+    pub(crate) request_denied: &'static [&'static str],
+    /// Description on the "Request denied" page.
+    pub(crate) request_denied_detail: &'static [&'static str],
+    /// `#usernameError` texts for an unknown or malformed username.
+    pub(crate) invalid_username: &'static [&'static str],
+    /// Softer "this username may be incorrect" warnings.
+    pub(crate) username_warning: &'static [&'static str],
+    /// `#passwordError` text for a wrong password.
+    pub(crate) incorrect_password: &'static [&'static str],
+    /// Title of the proof choice page.
+    pub(crate) verify_identity: &'static [&'static str],
+    /// Header of the "We didn't hear from you" page.
+    pub(crate) mfa_timeout: &'static [&'static str],
+    /// Labels of the resend links on the timeout page.
+    pub(crate) mfa_resend: &'static [&'static str],
+    /// Description shown while Microsoft is placing a phone call.
+    pub(crate) mfa_calling: &'static [&'static str],
+    /// "Request wasn't sent" texts on the passwordless sign-in page.
+    pub(crate) ngc_not_sent: &'static [&'static str],
+    /// Label of the "Yes" button on the "Stay signed in?" page.
+    pub(crate) kmsi_yes: &'static [&'static str],
+    /// Headings of the security-info registration (proof-up) interrupt.
+    pub(crate) interrupt_proof_up: &'static [&'static str],
+    /// Headings of the Terms of Use interrupt.
+    pub(crate) interrupt_terms: &'static [&'static str],
+    /// Headings of the app consent interrupt.
+    pub(crate) interrupt_consent: &'static [&'static str],
+    /// Headings of the "Is this your organization?" interrupt.
+    pub(crate) interrupt_organization: &'static [&'static str],
+    /// Labels of the links that postpone a skippable interrupt.
+    pub(crate) interrupt_skip: &'static [&'static str],
+    /// Proof button labels, per MFA method.
+    pub(crate) proof_push: &'static [&'static str],
+    pub(crate) proof_totp: &'static [&'static str],
+    pub(crate) proof_sms: &'static [&'static str],
+    pub(crate) proof_voice: &'static [&'static str],
+    /// Words that mark an alert as an error.
+    pub(crate) error_keywords: &'static [&'static str],
+    /// MFA instructions that look like errors but are not.
+    pub(crate) mfa_safe: &'static [&'static str],
+    /// Page title words of error or block pages.
+    pub(crate) page_blocked: &'static [&'static str],
+    /// Body words of minimal error pages.
+    pub(crate) page_error: &'static [&'static str],
+}
+
+impl Phrases {
+    /// Proof button labels for `method`.
+    pub(crate) fn proof_hints(&self, method: MfaMethod) -> &'static [&'static str] {
+        match method {
+            MfaMethod::Push => self.proof_push,
+            MfaMethod::Totp => self.proof_totp,
+            MfaMethod::Sms => self.proof_sms,
+            MfaMethod::Voice => self.proof_voice,
+            MfaMethod::FirstAvailable => &[],
+        }
+    }
+}
+
+const EN: Phrases = Phrases {
+    lang: "en",
+    pick_account: &["pick an account"],
+    request_denied: &["request denied"],
+    request_denied_detail: &["but you denied it"],
+    invalid_username: &[
+        "we couldn't find an account with that username",
+        "enter a valid email address",
+        "enter a valid phone number",
+        "enter a valid skype name",
+        "this username may be incorrect",
+        "make sure you typed it correctly",
+    ],
+    username_warning: &[
+        "this username may be incorrect",
+        "make sure you typed it correctly",
+    ],
+    incorrect_password: &["your account or password is incorrect"],
+    verify_identity: &["verify your identity"],
+    mfa_timeout: &["didn't hear from you"],
+    mfa_resend: &["send another request", "call again", "resend"],
+    mfa_calling: &["calling", "call "],
+    ngc_not_sent: &["request wasn't sent", "couldn't send"],
+    kmsi_yes: &["yes"],
+    interrupt_proof_up: &[
+        "more information required",
+        "needs more information",
+        "keep your account secure",
+    ],
+    interrupt_terms: &["terms of use"],
+    interrupt_consent: &["permissions requested"],
+    interrupt_organization: &["is this your organization"],
+    interrupt_skip: &["skip for now", "ask later", "skip setup", "not now"],
+    proof_push: &["approve a request", "notification"],
+    proof_totp: &["verification code"],
+    proof_sms: &["text "],
+    proof_voice: &["call "],
+    error_keywords: &[
+        "error",
+        "failed",
+        "failure",
+        "invalid",
+        "incorrect",
+        "denied",
+        "blocked",
+        "expired",
+        "unable",
+        "problem",
+        "went wrong",
+        "try again",
+        "couldn't",
+        "could not",
+        "not recognized",
+        "too many",
+        "locked",
+        "suspended",
+    ],
+    mfa_safe: &[
+        "enter the code",
+        "enter code",
+        "enter the number",
+        "enter number",
+        "verification code",
+        "verify your",
+        "we sent",
+        "we texted",
+        "we emailed",
+        "approve sign",
+        "approve the sign",
+        "approve a sign",
+        "open your authenticator",
+        "check your authenticator",
+        "open your microsoft",
+        "check your microsoft",
+        "notification was sent",
+        "notification to",
+        "authenticator app",
+        "sign in request",
+        "waiting for",
+        "checking",
+        "contacting",
+    ],
+    page_blocked: &[
+        "error",
+        "blocked",
+        "forbidden",
+        "denied",
+        "unauthorized",
+        "not found",
+        "404",
+        "403",
+        "500",
+        "unavailable",
+        "maintenance",
+    ],
+    page_error: &["error"],
+};
+
+const TR: Phrases = Phrases {
+    lang: "tr",
+    pick_account: &["hesap seçin"],
+    request_denied: &["istek reddedildi"],
+    request_denied_detail: &["reddettiniz"],
+    invalid_username: &[
+        "bu kullanıcı adına sahip bir hesap bulamadık",
+        "geçerli bir e-posta adresi girin",
+        "geçerli bir telefon numarası girin",
+        "geçerli bir skype adı girin",
+        "bu kullanıcı adı yanlış olabilir",
+        "doğru yazdığınızdan emin olun",
+    ],
+    username_warning: &[
+        "bu kullanıcı adı yanlış olabilir",
+        "doğru yazdığınızdan emin olun",
+    ],
+    incorrect_password: &["hesabınız veya parolanız yanlış"],
+    verify_identity: &["kimliğinizi doğrulayın"],
+    mfa_timeout: &["sizden yanıt alamadık"],
+    mfa_resend: &["başka bir istek gönder", "yeniden ara", "yeniden gönder"],
+    mfa_calling: &["arıyoruz", "numarasını ara"],
+    ngc_not_sent: &["istek gönderilmedi", "gönderemedik"],
+    kmsi_yes: &["evet"],
+    interrupt_proof_up: &[
+        "daha fazla bilgi gerekiyor",
+        "daha fazla bilgiye ihtiyacı var",
+        "hesabınızın güvenliğini koruyun",
+    ],
+    interrupt_terms: &["kullanım koşulları"],
+    interrupt_consent: &["istenen izinler"],
+    interrupt_organization: &["kuruluşunuz bu mu"],
+    interrupt_skip: &[
+        "şimdilik atla",
+        "daha sonra sor",
+        "kurulumu atla",
+        "şimdi değil",
+    ],
+    proof_push: &["isteği onayla", "bildirim"],
+    proof_totp: &["doğrulama kodu"],
+    proof_sms: &["kısa mesaj", "sms"],
+    proof_voice: &["numarasını ara"],
+    error_keywords: &[
+        "hata",
+        "başarısız",
+        "geçersiz",
+        "yanlış",
+        "reddedildi",
+        "engellendi",
+        "süresi doldu",
+        "yapılamadı",
+        "sorun",
+        "tekrar deneyin",
+        "tanınmadı",
+        "çok fazla",
+        "kilitlendi",
+        "askıya alındı",
+    ],
+    mfa_safe: &[
+        "kodu girin",
+        "numarayı girin",
+        "doğrulama kodu",
+        "kimliğinizi doğrulayın",
+        "gönderdik",
+        "oturum açma isteğini onaylayın",
+        "authenticator uygulaması",
+        "bildirim gönderildi",
+        "oturum açma isteği",
+        "bekleniyor",
+        "denetleniyor",
+        "iletişim kuruluyor",
+    ],
+    page_blocked: &[
+        "hata",
+        "engellendi",
+        "yasak",
+        "reddedildi",
+        "yetkisiz",
+        "bulunamadı",
+        "404",
+        "403",
+        "500",
+        "kullanılamıyor",
+        "bakım",
+    ],
+    page_error: &["hata"],
+};
+
+/// Every supported table.  English must stay first: it is the fallback.
+const TABLES: &[Phrases] = &[EN, TR];
+
+/// JS function `(text, phrases) -> bool` that reports whether `text` contains
+/// any of `phrases`, ignoring case and diacritics (so `İ`, `ı` and `i` compare
+/// equal regardless of how the browser lower-cases Turkish).
+pub(crate) const MATCHES_JS: &str = r#"(function(text, phrases) {
+    var fold = function(s) {
+        return (s || '').toLowerCase().normalize('NFD')
+            .replace(/[\u0300-\u036f]/g, '').replace(/ı/g, 'i');
+    };
+    var folded = fold(text);
+    return phrases.some(function(p) { return folded.includes(fold(p)); });
+})"#;
+
+/// JS function taking a `{lang: [phrases]}` object and returning the list for
+/// the page's `<html lang>` followed by the English fallback.
+pub(crate) const PAGE_PHRASES_JS: &str = r#"(function(table) {
+    var lang = ((document.documentElement && document.documentElement.lang) || '')
+        .toLowerCase().split('-')[0];
+    return lang !== 'en' && table[lang] ? table[lang].concat(table.en) : table.en;
+})"#;
+
+/// Builds a `{lang: [phrases]}` JSON object from the field chosen by `pick`.
+pub(crate) fn table_json(pick: impl Fn(&Phrases) -> &'static [&'static str]) -> serde_json::Value {
+    let map: serde_json::Map<String, serde_json::Value> = TABLES
+        .iter()
+        .map(|t| (t.lang.to_string(), serde_json::json!(pick(t))))
+        .collect();
+    serde_json::Value::Object(map)
+}
+
+/// JS expression evaluating to the phrases chosen by `pick` for the current
+/// page language, for use with [`MATCHES_JS`].
+pub(crate) fn phrases_js(pick: impl Fn(&Phrases) -> &'static [&'static str]) -> String {
+    format!("{PAGE_PHRASES_JS}({})", table_json(pick))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every phrase list of `t`, named for failure messages.
+    fn lists(t: &Phrases) -> Vec<(&'static str, &'static [&'static str])> {
+        vec![
+            ("pick_account", t.pick_account),
+            ("request_denied", t.request_denied),
+            ("request_denied_detail", t.request_denied_detail),
+            ("invalid_username", t.invalid_username),
+            ("username_warning", t.username_warning),
+            ("incorrect_password", t.incorrect_password),
+            ("verify_identity", t.verify_identity),
+            ("mfa_timeout", t.mfa_timeout),
+            ("mfa_resend", t.mfa_resend),
+            ("mfa_calling", t.mfa_calling),
+            ("ngc_not_sent", t.ngc_not_sent),
+            ("kmsi_yes", t.kmsi_yes),
+            ("interrupt_proof_up", t.interrupt_proof_up),
+            ("interrupt_terms", t.interrupt_terms),
+            ("interrupt_consent", t.interrupt_consent),
+            ("interrupt_organization", t.interrupt_organization),
+            ("interrupt_skip", t.interrupt_skip),
+            ("proof_push", t.proof_push),
+            ("proof_totp", t.proof_totp),
+            ("proof_sms", t.proof_sms),
+            ("proof_voice", t.proof_voice),
+            ("error_keywords", t.error_keywords),
+            ("mfa_safe", t.mfa_safe),
+            ("page_blocked", t.page_blocked),
+            ("page_error", t.page_error),
+        ]
+    }
+
+    #[test]
+    fn english_is_the_first_table() {
+        assert_eq!(TABLES[0].lang, "en");
+    }
+
+    #[test]
+    fn languages_are_unique() {
+        for (i, t) in TABLES.iter().enumerate() {
+            assert!(
+                TABLES[i + 1..].iter().all(|o| o.lang != t.lang),
+                "{} listed twice",
+                t.lang
+            );
+        }
+    }
+
+    #[test]
+    fn every_table_fills_every_list() {
+        for t in TABLES {
+            for (name, phrases) in lists(t) {
+                assert!(!phrases.is_empty(), "{}.{} is empty", t.lang, name);
+                for p in phrases {
+                    assert!(
+                        !p.trim().is_empty(),
+                        "{}.{} has a blank phrase",
+                        t.lang,
+                        name
+                    );
+                    assert_eq!(
+                        *p,
+                        p.to_lowercase(),
+                        "{}.{} is not lower-case",
+                        t.lang,
+                        name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn username_warnings_are_invalid_usernames() {
+        for t in TABLES {
+            for w in t.username_warning {
+                assert!(t.invalid_username.contains(w), "{}: {:?}", t.lang, w);
+            }
+        }
+    }
+
+    #[test]
+    fn proof_hints_follow_the_method() {
+        assert_eq!(EN.proof_hints(MfaMethod::Sms), EN.proof_sms);
+        assert_eq!(TR.proof_hints(MfaMethod::Push), TR.proof_push);
+        assert!(EN.proof_hints(MfaMethod::FirstAvailable).is_empty());
+    }
+
+    #[test]
+    fn table_json_maps_each_language() {
+        let json = table_json(|t| t.kmsi_yes);
+        assert_eq!(json["en"], serde_json::json!(["yes"]));
+        assert_eq!(json["tr"], serde_json::json!(["evet"]));
+        assert_eq!(json.as_object().map(|m| m.len()), Some(TABLES.len()));
+    }
+
+    #[test]
+    fn phrases_js_calls_the_page_language_picker() {
+        let js = phrases_js(|t| t.kmsi_yes);
+        assert!(js.starts_with(PAGE_PHRASES_JS));
+        assert!(js.ends_with(&format!("({})", table_json(|t| t.kmsi_yes))));
+    }
+}
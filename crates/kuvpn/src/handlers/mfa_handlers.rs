@@ -1,26 +1,97 @@
-use super::AuthTab;
+use super::locale::{phrases_js, table_json, MATCHES_JS, PAGE_PHRASES_JS};
+use super::snapshot::PageSnapshot;
+use super::{AuthTab, WAKE_INTERVAL};
+use crate::dsid::{prefers_push, MfaMethod};
+use crate::error::AuthError;
 use crate::utils::{CancellationToken, CredentialsProvider};
 use std::collections::HashSet;
 use std::thread::sleep;
 use std::time::Duration;
 
+/// Classifier fragment for the MFA fields of the snapshot.
+pub(super) fn classify_js() -> String {
+    let calling = phrases_js(|p| p.mfa_calling);
+    let timeout = phrases_js(|p| p.mfa_timeout);
+    let verify_identity = phrases_js(|p| p.verify_identity);
+    let not_sent = phrases_js(|p| p.ngc_not_sent);
+    format!(
+        r#"
+    // The number is optional: some push variants just ask to tap "Approve".
+    var ngcSign = byId('idRemoteNGC_DisplaySign');
+    var sign = byId('idRichContext_DisplaySign');
+    snap.mfaNumber = (visible(ngcSign) && text(ngcSign)) || (visible(sign) && text(sign)) || null;
+
+    // SAOTCAS: push approval, or a phone call when there is no number and
+    // the description says Microsoft is calling.
+    var title = byId('idDiv_SAOTCAS_Title');
+    var desc = byId('idDiv_SAOTCAS_Description');
+    snap.pushApproval = visible(title);
+    snap.voiceCall = visible(title) && !visible(sign)
+        && {MATCHES_JS}((desc && desc.innerText) || title.innerText, {calling});
+
+    var header = byId('loginHeader');
+    snap.mfaTimeout = visible(byId('idDiv_SAASTO_Title'))
+        || (visible(header) && {MATCHES_JS}(header.innerText, {timeout}));
+
+    // Passwordless (NGC) sign-in: waiting for the app, or the request was
+    // not sent.
+    var errorBlock = byId('idDiv_RemoteNGC_PageDescription');
+    snap.ngcPolling = visible(byId('idDiv_RemoteNGC_PollingDescription'));
+    var notSent = {not_sent};
+    snap.ngcError = !!((header && {MATCHES_JS}(header.innerText, notSent))
+        || (errorBlock && {MATCHES_JS}(errorBlock.innerText, notSent))
+        || (visible(errorBlock) && !snap.ngcPolling));
+    snap.useAppInstead = visible(byId('idA_PWD_SwitchToRemoteNGC'));
+
+    var proofTitle = byId('idDiv_SAOTCS_Title');
+    var proofs = byId('idDiv_SAOTCS_Proofs');
+    snap.proofChoice = !!(proofTitle && (visible(proofs)
+        || {MATCHES_JS}(proofTitle.innerText, {verify_identity})));
+    snap.proofs = {PROOFS_JS}.map(text);
+
+    // One-time code entry (SMS, email, TOTP).
+    if (visible(document.querySelector('input[name="otc"]'))) {{
+        var otcError = byId('idSpan_SAOTCC_Error_OTC');
+        snap.otpError = (visible(otcError) && text(otcError)) || null;
+        var otcDesc = byId('idDiv_SAOTCC_Description') || byId('idDiv_SAOTCS_Description');
+        var otcTitle = byId('idDiv_SAOTCC_Title') || byId('idDiv_SAOTCS_Title');
+        snap.otpPrompt = text(otcDesc) || text(otcTitle) || null;
+    }}"#
+    )
+}
+
+/// JS expression evaluating to the visible proof buttons on the proof
+/// choice page.
+const PROOFS_JS: &str = r#"Array.prototype.slice.call(
+        document.querySelectorAll('#idDiv_SAOTCS_Proofs .table[role="button"]')
+    ).filter(function(el) { return el.offsetParent !== null; })"#;
+
 impl AuthTab {
     /// Shared polling loop for both push-approval variants.
     ///
-    /// Reads the display number via `number_js`, notifies the provider, then polls
-    /// `still_showing_js` every `poll_interval` until the push page is gone
-    /// (element hidden or URL changes) or the operation is cancelled.
+    /// Passes the display number to the provider, then waits for the push
+    /// page to go away (see [`Self::wait_for_mfa_page`]).
     fn poll_mfa_push(
         &self,
         provider: &dyn CredentialsProvider,
         cancel_token: Option<&CancellationToken>,
-        number_js: &str,
-        still_showing_js: &str,
-        poll_interval: Duration,
+        number: &str,
+        still_showing: fn(&PageSnapshot) -> bool,
     ) -> anyhow::Result<()> {
-        let number = self.eval_string_or(number_js, "")?;
-        provider.on_mfa_push(&number);
+        provider.on_mfa_push(number);
+        self.wait_for_mfa_page(provider, cancel_token, still_showing)
+    }
 
+    /// Checks `still_showing` on a fresh snapshot whenever the page changes
+    /// (and at least every [`WAKE_INTERVAL`]) until the MFA page is gone (or
+    /// the URL changes) or the operation is cancelled, then tells the
+    /// provider the MFA step is over.
+    fn wait_for_mfa_page(
+        &self,
+        provider: &dyn CredentialsProvider,
+        cancel_token: Option<&CancellationToken>,
+        still_showing: fn(&PageSnapshot) -> bool,
+    ) -> anyhow::Result<()> {
         let prev_url = self.get_url();
         loop {
             if let Some(token) = cancel_token {
@@ -30,13 +101,13 @@ impl AuthTab {
                 }
             }
 
-            sleep(poll_interval);
+            self.0.wait_for_change(WAKE_INTERVAL);
 
-            let still_showing = self.eval_bool(still_showing_js)?;
+            let still_showing = still_showing(&self.snapshot()?);
             let new_url = self.get_url();
             if !still_showing || new_url != prev_url {
                 provider.on_mfa_complete();
-                log::info!("Push page finished, moving on...");
+                log::info!("MFA page finished, moving on...");
                 break;
             }
         }
@@ -44,35 +115,113 @@ impl AuthTab {
         Ok(())
     }
 
+    /// Handles the phone-call proof (SAOTCAS page without a display number
+    /// whose description says Microsoft is calling).
+    pub(crate) fn handle_voice_call_approval(
+        &self,
+        snapshot: &PageSnapshot,
+        provider: &dyn CredentialsProvider,
+        cancel_token: Option<&CancellationToken>,
+    ) -> anyhow::Result<bool> {
+        if snapshot.voice_call {
+            log::info!("Phone-call verification in progress, waiting for the call to be answered");
+            provider.on_mfa_call();
+            self.wait_for_mfa_page(provider, cancel_token, |page| {
+                page.push_approval && !page.mfa_timeout
+            })?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Handles the "We didn't hear from you" page shown when a push
+    /// notification or phone call goes unanswered, by clicking
+    /// "Send another request" (or its call equivalent).
+    ///
+    /// `attempt` is the resend about to be made (1-based).  Returns a
+    /// [`AuthError::Timeout`] once `attempt` exceeds `max_resends`.
+    pub(crate) fn handle_mfa_timeout(
+        &self,
+        snapshot: &PageSnapshot,
+        provider: &dyn CredentialsProvider,
+        attempt: u32,
+        max_resends: u32,
+    ) -> anyhow::Result<bool> {
+        if !snapshot.mfa_timeout {
+            return Ok(false);
+        }
+
+        if attempt > max_resends {
+            log::warn!(
+                "MFA request went unanswered {} time(s), giving up",
+                max_resends + 1
+            );
+            return Err(AuthError::Timeout {
+                message: format!(
+                    "No response to the MFA request after {} resend(s). \
+                     Approve the request on your device or choose another MFA method.",
+                    max_resends
+                ),
+            }
+            .into());
+        }
+
+        let resend = phrases_js(|p| p.mfa_resend);
+        let clicked = self.eval_string(&format!(
+            r#"(function() {{
+    var ids = ['idA_SAASTO_Resend', 'idA_SAASTO_Call', 'idA_RemoteNGC_Resend'];
+    for (var i = 0; i < ids.length; i++) {{
+        var el = document.getElementById(ids[i]);
+        if (el && el.offsetParent !== null) {{ el.click(); return ids[i]; }}
+    }}
+    var phrases = {resend};
+    var links = document.querySelectorAll('a, button, [role="button"], input[type="button"]');
+    for (var j = 0; j < links.length; j++) {{
+        var text = links[j].innerText || links[j].value || '';
+        if (links[j].offsetParent !== null && {MATCHES_JS}(text, phrases)) {{
+            links[j].click();
+            return text.trim();
+        }}
+    }}
+    return null;
+}})()"#
+        ))?;
+
+        match clicked {
+            Some(via) => {
+                log::info!(
+                    "MFA request timed out, resending ({}/{}) via {}",
+                    attempt,
+                    max_resends,
+                    via
+                );
+                provider.on_mfa_resend(attempt, max_resends);
+                sleep(Duration::from_millis(500));
+                Ok(true)
+            }
+            None => {
+                log::warn!("MFA timeout page detected but no resend link found");
+                Ok(false)
+            }
+        }
+    }
+
     /// Handles authenticator push approval (SAOTCAS flow).
     pub(crate) fn handle_authenticator_push_approval(
         &self,
+        snapshot: &PageSnapshot,
         provider: &dyn CredentialsProvider,
         cancel_token: Option<&CancellationToken>,
     ) -> anyhow::Result<bool> {
         // Structural detection: the SAOTCAS title element is unique to this
-        // push-approval flow.  The number display is optional — some push
-        // variants just ask the user to tap "Approve" without a number.
-        let is_push_page = self.eval_bool(
-            r#"(function() {
-    var title = document.getElementById('idDiv_SAOTCAS_Title');
-    return !!(title && title.offsetParent !== null);
-})()"#,
-        )?;
-
-        if is_push_page {
+        // push-approval flow.
+        if snapshot.push_approval {
             self.poll_mfa_push(
                 provider,
                 cancel_token,
-                r#"(function() {
-    var el = document.getElementById('idRichContext_DisplaySign');
-    return el ? el.innerText.trim() : '';
-})()"#,
-                r#"(function() {
-    var el = document.getElementById('idRichContext_DisplaySign');
-    return !!(el && el.offsetParent !== null);
-})()"#,
-                Duration::from_secs(1),
+                snapshot.mfa_number.as_deref().unwrap_or_default(),
+                |page| page.mfa_number.is_some() && !page.mfa_timeout,
             )?;
             return Ok(true);
         }
@@ -82,42 +231,69 @@ impl AuthTab {
 
     /// Handles the verification-code choice page ("Verify your identity").
     ///
-    /// Prefers the Authenticator app; falls back to SMS, then the first available
-    /// proof button when only telephony options (SMS/voice) are offered.
-    pub(crate) fn handle_verification_code_choice(&self) -> anyhow::Result<bool> {
-        let is_proof_choice_page = self.eval_bool(
-            r#"(function() {
-    var title = document.getElementById('idDiv_SAOTCS_Title');
-    return !!(title && title.innerText.trim().toLowerCase().includes('verify your identity'));
-})()"#,
-        )?;
-
-        if is_proof_choice_page {
-            let selected = self.eval_string(
-                r#"(function() {
-    // Prefer authenticator / mobile app
-    var els = document.querySelectorAll('div[role="button"], .table[role="button"], button, input[type="button"]');
-    for (var i = 0; i < els.length; i++) {
-        var text = els[i].innerText.toLowerCase();
-        if (text.includes('mobile app') || text.includes('authenticator')) {
-            els[i].click();
-            return 'authenticator';
-        }
-    }
-    // Fall back to SMS
-    var sms = document.querySelector('[data-value="OneWaySMS"]');
-    if (sms) { sms.click(); return 'sms'; }
-    // Fall back to first available proof button
-    var first = document.querySelector('#idDiv_SAOTCS_Proofs .table[role="button"]');
-    if (first) { first.click(); return 'first_proof'; }
+    /// Walks `preference` in order and clicks the first proof that matches,
+    /// identified by its `data-value` attribute or, failing that, its label.
+    /// `FirstAvailable` clicks whichever proof Microsoft lists first.  When
+    /// none of the preferred methods is offered the user picks one.
+    pub(crate) fn handle_verification_code_choice(
+        &self,
+        snapshot: &PageSnapshot,
+        preference: &[MfaMethod],
+        provider: &dyn CredentialsProvider,
+    ) -> anyhow::Result<bool> {
+        if snapshot.proof_choice {
+            let methods = preference
+                .iter()
+                .map(|m| {
+                    serde_json::json!({
+                        "name": m.as_str(),
+                        "values": m.proof_values(),
+                        "hints": table_json(|p| p.proof_hints(*m)),
+                        "first": *m == MfaMethod::FirstAvailable,
+                    })
+                })
+                .collect::<Vec<_>>();
+            let methods = serde_json::Value::Array(methods).to_string();
+
+            let selected = self.eval_string(&format!(
+                r#"(function() {{
+    var methods = {methods};
+    var pagePhrases = {PAGE_PHRASES_JS};
+    var proofs = Array.prototype.slice.call(
+        document.querySelectorAll('#idDiv_SAOTCS_Proofs .table[role="button"], #idDiv_SAOTCS_Proofs [data-value]')
+    ).filter(function(el) {{ return el.offsetParent !== null; }});
+    for (var m = 0; m < methods.length; m++) {{
+        var method = methods[m];
+        if (method.first) {{
+            if (proofs.length > 0) {{ proofs[0].click(); return method.name; }}
+            continue;
+        }}
+        for (var i = 0; i < proofs.length; i++) {{
+            var holder = proofs[i].closest('[data-value]') || proofs[i];
+            var value = holder.getAttribute('data-value') || '';
+            var byValue = method.values.indexOf(value) !== -1;
+            var byHint = !value && {MATCHES_JS}(proofs[i].innerText, pagePhrases(method.hints));
+            if (byValue || byHint) {{ proofs[i].click(); return method.name; }}
+        }}
+    }}
     return null;
-})()"#,
-            )?;
-            match selected.as_deref() {
-                Some("authenticator") => log::info!("Proof choice: clicked authenticator"),
-                Some("sms") => log::info!("Proof choice: no authenticator available, selected SMS"),
-                Some("first_proof") => log::info!("Proof choice: selected first available proof"),
-                _ => log::warn!("Proof choice page detected but no proof button found"),
+}})()"#
+            ))?;
+            match selected {
+                Some(method) => log::info!("Proof choice: selected {}", method),
+                None => {
+                    log::info!(
+                        "Proof choice: none of the preferred methods ({}) is offered, asking the user",
+                        preference
+                            .iter()
+                            .map(MfaMethod::as_str)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                    if !self.ask_for_proof(&snapshot.proofs, provider)? {
+                        return Ok(false); // no proofs listed, or prompt dismissed
+                    }
+                }
             }
             sleep(Duration::from_millis(500));
             return Ok(true);
@@ -126,16 +302,49 @@ impl AuthTab {
         Ok(false)
     }
 
+    /// Lets the user pick one of the visible proofs (`labels`) on the proof
+    /// choice page.  Returns `false` if there is nothing to pick or the prompt
+    /// was dismissed.
+    fn ask_for_proof(
+        &self,
+        labels: &[String],
+        provider: &dyn CredentialsProvider,
+    ) -> anyhow::Result<bool> {
+        if labels.is_empty() {
+            log::warn!("Proof choice page detected but no proof button found");
+            return Ok(false);
+        }
+
+        self.inject_input_watcher("#idDiv_SAOTCS_Proofs");
+        let choice = provider.request_choice("How do you want to verify your identity?", labels);
+        self.clear_input_watcher();
+
+        match choice {
+            Some(index) if index < labels.len() => {
+                self.eval(&format!(
+                    "(function() {{ var el = {PROOFS_JS}[{index}]; if (el) {{ el.click(); }} }})()"
+                ))?;
+                log::info!("Proof choice: user selected '{}'", labels[index]);
+                Ok(true)
+            }
+            _ => Ok(false), // prompt dismissed, or no such proof
+        }
+    }
+
     /// Handles the "Use an app instead" link.
-    pub(crate) fn handle_use_app_instead(&self) -> anyhow::Result<bool> {
-        let is_visible = self.eval_bool(
-            r#"(function() {
-    var el = document.getElementById('idA_PWD_SwitchToRemoteNGC');
-    return !!(el && el.offsetParent !== null);
-})()"#,
-        )?;
+    ///
+    /// Only switches to app sign-in when push is the preferred method; otherwise
+    /// the password is entered and the proof choice page picks the method.
+    pub(crate) fn handle_use_app_instead(
+        &self,
+        snapshot: &PageSnapshot,
+        preference: &[MfaMethod],
+    ) -> anyhow::Result<bool> {
+        if !prefers_push(preference) {
+            return Ok(false);
+        }
 
-        if is_visible {
+        if snapshot.use_app_instead {
             self.eval(
                 r#"var el=document.getElementById('idA_PWD_SwitchToRemoteNGC'); if(el){el.click();}"#,
             )?;
@@ -150,35 +359,18 @@ impl AuthTab {
     /// Handles authenticator NGC push notifications.
     pub(crate) fn handle_authenticator_ngc_push(
         &self,
+        snapshot: &PageSnapshot,
         provider: &dyn CredentialsProvider,
         cancel_token: Option<&CancellationToken>,
     ) -> anyhow::Result<bool> {
         // Structural detection: the polling description element indicates an
-        // active NGC push.  The number display is optional — some push
-        // variants just ask the user to tap "Approve" without a number.
-        let is_ngc_push = self.eval_bool(
-            r#"(function() {
-    var polling = document.getElementById('idDiv_RemoteNGC_PollingDescription');
-    return !!(polling && polling.offsetParent !== null);
-})()"#,
-        )?;
-
-        if is_ngc_push {
+        // active NGC push.
+        if snapshot.ngc_polling {
             self.poll_mfa_push(
                 provider,
                 cancel_token,
-                r#"(function() {
-    var el=document.getElementById('idRemoteNGC_DisplaySign');
-    return (el && el.offsetParent !== null) ? el.innerText.trim() : '';
-})()"#,
-                r#"(function() {
-    var num = document.getElementById('idRemoteNGC_DisplaySign');
-    var poll = document.getElementById('idDiv_RemoteNGC_PollingDescription');
-    var numVisible = num && num.offsetParent !== null;
-    var pollVisible = poll && poll.offsetParent !== null;
-    return !!(numVisible || pollVisible);
-})()"#,
-                Duration::from_millis(400),
+                snapshot.mfa_number.as_deref().unwrap_or_default(),
+                |page| page.mfa_number.is_some() || page.ngc_polling,
             )?;
             return Ok(true);
         }
@@ -191,43 +383,17 @@ impl AuthTab {
     /// Detects `input[name="otc"]` which Microsoft uses for all one-time code inputs.
     pub(crate) fn handle_otp_entry(
         &self,
+        snapshot: &PageSnapshot,
         provider: &dyn CredentialsProvider,
     ) -> anyhow::Result<bool> {
-        let is_otp_page = self.eval_bool(
-            r#"(function() {
-    var input = document.querySelector('input[name="otc"]');
-    return !!(input && input.offsetParent !== null);
-})()"#,
-        )?;
-
-        if is_otp_page {
-            let error_text = self.eval_string(
-                r#"(function() {
-    var el = document.getElementById('idSpan_SAOTCC_Error_OTC');
-    if (el && el.offsetParent !== null) {
-        var t = el.innerText.trim();
-        if (t.length > 0) return t;
-    }
-    return null;
-})()"#,
-            )?;
-
-            let prompt = self
-                .eval_string(
-                    r#"(function() {
-    var desc = document.getElementById('idDiv_SAOTCC_Description')
-        || document.getElementById('idDiv_SAOTCS_Description');
-    if (desc && desc.innerText.trim().length > 0) return desc.innerText.trim();
-    var title = document.getElementById('idDiv_SAOTCC_Title')
-        || document.getElementById('idDiv_SAOTCS_Title');
-    if (title && title.innerText.trim().length > 0) return title.innerText.trim();
-    return null;
-})()"#,
-                )?
+        if snapshot.has_input("otc") {
+            let prompt = snapshot
+                .otp_prompt
+                .as_ref()
                 .map(|s| format!("{}: ", s))
                 .unwrap_or_else(|| "Enter verification code: ".to_string());
 
-            let prompt = match error_text {
+            let prompt = match &snapshot.otp_error {
                 Some(err) => format!("{}\n\n{}", err, prompt),
                 None => prompt,
             };
@@ -265,30 +431,19 @@ impl AuthTab {
         Ok(false)
     }
 
-    /// Handles NGC error and switches to password authentication.
+    /// Switches from app (NGC) sign-in to password authentication.
+    ///
+    /// Triggers when the NGC request could not be sent, or when Microsoft
+    /// started an NGC push on its own but the user prefers another method —
+    /// the proof choice page reached after the password honours the preference.
     pub(crate) fn handle_ngc_error_use_password(
         &self,
+        snapshot: &PageSnapshot,
         handled: &mut HashSet<&'static str>,
+        preference: &[MfaMethod],
     ) -> anyhow::Result<bool> {
-        let is_ngc_error = self.eval_bool(
-            r#"(function() {
-    var header = document.getElementById('loginHeader');
-    var errorBlock = document.getElementById('idDiv_RemoteNGC_PageDescription');
-    var pollingIndicator = document.getElementById('idDiv_RemoteNGC_PollingDescription');
-    var pollingActive = pollingIndicator && pollingIndicator.offsetParent !== null;
-
-    var textMatch = (
-        (header && header.innerText.toLowerCase().includes("request wasn't sent")) ||
-        (errorBlock && errorBlock.innerText.toLowerCase().includes("couldn't send"))
-    );
-
-    var structuralMatch = (
-        errorBlock && errorBlock.offsetParent !== null && !pollingActive
-    );
-
-    return !!(textMatch || structuralMatch);
-})()"#,
-        )?;
+        let avoid_push = !prefers_push(preference);
+        let is_ngc_error = snapshot.ngc_error || (avoid_push && snapshot.ngc_polling);
 
         if is_ngc_error {
             let clicked = self
@@ -312,7 +467,14 @@ impl AuthTab {
                 .unwrap_or_default();
 
             if !clicked.is_empty() {
-                log::info!("NGC error page, switching to password via {}", clicked);
+                if avoid_push {
+                    log::info!(
+                        "Leaving app sign-in for the preferred MFA method via {}",
+                        clicked
+                    );
+                } else {
+                    log::info!("NGC error page, switching to password via {}", clicked);
+                }
                 handled.insert("use_app_instead");
                 sleep(Duration::from_millis(400));
                 return Ok(true);
@@ -322,3 +484,111 @@ impl AuthTab {
         Ok(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{tab, Chooser};
+    use super::*;
+    use crate::driver::fake::ScriptedPage;
+    use serde_json::json;
+
+    const LOGIN: &str = "https://login.microsoftonline.com/common/SAS/ProcessAuth";
+
+    fn proof_page() -> PageSnapshot {
+        PageSnapshot {
+            proof_choice: true,
+            proofs: vec![
+                "Approve a request on my Microsoft Authenticator app".to_string(),
+                "Text +X XXXXXXXX12".to_string(),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn proof_choice_clicks_a_preferred_method_without_asking() {
+        let (auth, page) = tab(ScriptedPage::new(LOGIN).on_script("var methods = ", json!("sms")));
+        let chooser = Chooser::new(None);
+        assert!(auth
+            .handle_verification_code_choice(
+                &proof_page(),
+                &[MfaMethod::Sms, MfaMethod::Push],
+                &chooser
+            )
+            .unwrap());
+        assert!(chooser.asked().is_empty());
+
+        let script = &page.evaluated()[0];
+        assert!(script.contains(r#""name":"sms""#));
+        assert!(script.contains("OneWaySMS"));
+        assert!(script.find(r#""name":"sms""#) < script.find(r#""name":"push""#));
+    }
+
+    #[test]
+    fn proof_choice_asks_when_no_preferred_method_is_offered() {
+        let (auth, page) = tab(ScriptedPage::new(LOGIN));
+        let chooser = Chooser::new(Some(1));
+        assert!(auth
+            .handle_verification_code_choice(&proof_page(), &[MfaMethod::Voice], &chooser)
+            .unwrap());
+        let asked = chooser.asked();
+        assert_eq!(asked[0].0, "How do you want to verify your identity?");
+        assert_eq!(asked[0].1, proof_page().proofs);
+        assert!(page
+            .evaluated()
+            .last()
+            .unwrap()
+            .contains("[1]; if (el) { el.click(); }"));
+    }
+
+    #[test]
+    fn proof_choice_gives_up_without_proofs_or_a_valid_answer() {
+        let (auth, page) = tab(ScriptedPage::new(LOGIN));
+        assert!(!auth
+            .handle_verification_code_choice(
+                &proof_page(),
+                &[MfaMethod::Voice],
+                &Chooser::new(None)
+            )
+            .unwrap());
+        assert!(!page
+            .evaluated()
+            .iter()
+            .any(|js| js.contains("if (el) { el.click(); }")));
+
+        assert!(!auth
+            .handle_verification_code_choice(
+                &proof_page(),
+                &[MfaMethod::Voice],
+                &Chooser::new(Some(2))
+            )
+            .unwrap());
+        assert!(!page
+            .evaluated()
+            .iter()
+            .any(|js| js.contains("if (el) { el.click(); }")));
+
+        let chooser = Chooser::new(Some(0));
+        let empty = PageSnapshot {
+            proofs: Vec::new(),
+            ..proof_page()
+        };
+        assert!(!auth
+            .handle_verification_code_choice(&empty, &[MfaMethod::Voice], &chooser)
+            .unwrap());
+        assert!(chooser.asked().is_empty());
+    }
+
+    #[test]
+    fn proof_choice_ignores_other_pages() {
+        let (auth, page) = tab(ScriptedPage::new(LOGIN));
+        assert!(!auth
+            .handle_verification_code_choice(
+                &PageSnapshot::default(),
+                &[MfaMethod::Push],
+                &Chooser::new(Some(0))
+            )
+            .unwrap());
+        assert!(page.evaluated().is_empty());
+    }
+}
@@ -1,19 +1,27 @@
 pub mod auth_handlers;
 pub mod generic_detection;
+pub mod interrupt_handlers;
+pub mod locale;
 pub mod mfa_handlers;
 pub mod page_detection;
+pub mod snapshot;
 
-use headless_chrome::Tab;
+use crate::driver::PageDriver;
 use std::sync::Arc;
+use std::time::Duration;
 
-/// Thin wrapper around a Chrome tab that hosts every authentication handler
+/// Longest a wait for the page to change goes before checking again anyway,
+/// for cancellation and for changes the page does not report.
+pub(crate) const WAKE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Thin wrapper around a browser page that hosts every authentication handler
 /// as a method.  Eliminates the repeating `(tab: &Tab, ...)` first argument
 /// across all handler functions and consolidates the low-level JS helpers.
-pub(crate) struct AuthTab(pub(crate) Arc<Tab>);
+pub(crate) struct AuthTab(pub(crate) Arc<dyn PageDriver>);
 
 impl AuthTab {
-    pub(crate) fn new(tab: Arc<Tab>) -> Self {
-        Self(tab)
+    pub(crate) fn new(page: Arc<dyn PageDriver>) -> Self {
+        Self(page)
     }
 
     // ── Low-level JS evaluation helpers ──────────────────────────────────
@@ -23,8 +31,7 @@ impl AuthTab {
     pub(crate) fn eval_bool(&self, js: &str) -> anyhow::Result<bool> {
         Ok(self
             .0
-            .evaluate(js, false)?
-            .value
+            .evaluate(js)?
             .and_then(|v| v.as_bool())
             .unwrap_or(false))
     }
@@ -33,8 +40,7 @@ impl AuthTab {
     pub(crate) fn eval_string(&self, js: &str) -> anyhow::Result<Option<String>> {
         Ok(self
             .0
-            .evaluate(js, false)?
-            .value
+            .evaluate(js)?
             .and_then(|v| v.as_str().map(|s| s.to_string())))
     }
 
@@ -47,7 +53,7 @@ impl AuthTab {
 
     /// Evaluates JS for its side effects; propagates errors, discards the return value.
     pub(crate) fn eval(&self, js: &str) -> anyhow::Result<()> {
-        self.0.evaluate(js, false)?;
+        self.0.evaluate(js)?;
         Ok(())
     }
 
@@ -82,8 +88,18 @@ impl AuthTab {
         }
     }
 
-    /// Checks for a DSID cookie in the tab's current cookie jar.
+    /// Checks for a DSID cookie, first among those the gateway's responses
+    /// set, then in the tab's current cookie jar.
     pub(crate) fn poll_dsid(&self, domain: &str) -> anyhow::Result<Option<String>> {
+        if let Some(cookie) = self
+            .0
+            .set_cookies()
+            .into_iter()
+            .rev()
+            .find(|c| c.name == "DSID" && c.domain.contains(domain) && !c.value.is_empty())
+        {
+            return Ok(Some(cookie.value));
+        }
         let cookies = self
             .0
             .get_cookies()
@@ -116,16 +132,13 @@ impl AuthTab {
     }}, 50);
 }})()"#
         );
-        self.0.evaluate(&js, false).ok();
+        self.0.evaluate(&js).ok();
     }
 
     /// Clears the input-watcher interval set by `inject_input_watcher`.
     pub(crate) fn clear_input_watcher(&self) {
         self.0
-            .evaluate(
-                "if(window.__kuvpn_watch_iv){clearInterval(window.__kuvpn_watch_iv);}",
-                false,
-            )
+            .evaluate("if(window.__kuvpn_watch_iv){clearInterval(window.__kuvpn_watch_iv);}")
             .ok();
     }
 
@@ -142,7 +155,78 @@ if (el) {{
     el.dispatchEvent(new Event('change', {{ bubbles: true }}));
 }}"#
         );
-        self.0.evaluate(&js, false)?;
+        self.0.evaluate(&js)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+pub(crate) mod testing {
+    //! Helpers for driving handlers against a [`ScriptedPage`].
+
+    use super::AuthTab;
+    use crate::driver::fake::ScriptedPage;
+    use crate::utils::CredentialsProvider;
+    use std::sync::{Arc, Mutex};
+
+    /// Wraps `page` in an [`AuthTab`], keeping a handle to inspect it.
+    pub(crate) fn tab(page: ScriptedPage) -> (AuthTab, Arc<ScriptedPage>) {
+        let page = Arc::new(page);
+        (AuthTab::new(page.clone()), page)
+    }
+
+    /// Answers every choice with `choice` and records the prompts.
+    pub(crate) struct Chooser {
+        pub(crate) choice: Option<usize>,
+        pub(crate) asked: Mutex<Vec<(String, Vec<String>)>>,
+    }
+
+    impl Chooser {
+        pub(crate) fn new(choice: Option<usize>) -> Self {
+            Self {
+                choice,
+                asked: Mutex::default(),
+            }
+        }
+
+        pub(crate) fn asked(&self) -> Vec<(String, Vec<String>)> {
+            self.asked.lock().unwrap().clone()
+        }
+    }
+
+    impl CredentialsProvider for Chooser {
+        fn request_text(&self, msg: &str) -> Option<String> {
+            panic!("unexpected text prompt: {msg}")
+        }
+
+        fn request_password(&self, msg: &str) -> Option<String> {
+            panic!("unexpected password prompt: {msg}")
+        }
+
+        fn request_choice(&self, msg: &str, options: &[String]) -> Option<usize> {
+            self.asked
+                .lock()
+                .unwrap()
+                .push((msg.to_string(), options.to_vec()));
+            self.choice
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::tab;
+    use crate::driver::fake::ScriptedPage;
+
+    #[test]
+    fn poll_dsid_reads_the_gateway_cookie() {
+        let (tab, _) = tab(ScriptedPage::new("https://vpn.ku.edu.tr/")
+            .with_cookie("DSID", "other", "example.com")
+            .with_cookie("DSID", "abc123", "vpn.ku.edu.tr"));
+        assert_eq!(
+            tab.poll_dsid("vpn.ku.edu.tr").unwrap().as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(tab.poll_dsid("vpn.example.org").unwrap(), None);
+    }
+}
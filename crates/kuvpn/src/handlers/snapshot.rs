@@ -0,0 +1,271 @@
+//! One-shot classification of the current login page.
+//!
+//! Asking the page one question per handler costs a CDP round-trip each and
+//! can give answers about different DOMs when the page changes in between.
+//! Instead every poll tick evaluates a single classifier script that reports
+//! everything the handlers look at as a [`PageSnapshot`]; the handlers then
+//! decide from that one consistent view and only go back to the page to act.
+//!
+//! The script is put together from fragments kept next to the handlers that
+//! use them (`classify_js` in each handler module).  Each fragment fills in
+//! its fields of the `snap` object and may use `visible(el)`, `byId(id)`,
+//! `text(el)`, `pageId` and `headings` (the visible heading texts).
+
+use super::AuthTab;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+/// What the classifier script saw on the page.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct PageSnapshot {
+    /// `<meta name="PageID">` of Microsoft pages (`ConvergedSignIn`, …).
+    pub(crate) page_id: String,
+    pub(crate) title: String,
+    /// `<html lang>`.
+    pub(crate) lang: String,
+    /// Visible text-like inputs.
+    pub(crate) visible_inputs: Vec<VisibleInput>,
+    /// Visible headings, at most three.
+    pub(crate) headers: Vec<String>,
+    /// Labels of the visible buttons, at most five.
+    pub(crate) buttons: Vec<String>,
+
+    // Sign-in errors (page_detection.rs)
+    pub(crate) invalid_username: bool,
+    pub(crate) username_warning: Option<String>,
+    pub(crate) incorrect_password: bool,
+    /// Azure's "Update your password" page.
+    pub(crate) update_password: bool,
+    /// Error on the update page, and whether it is about the current password.
+    pub(crate) update_password_error: Option<(String, bool)>,
+    /// Azure AD ConvergedError page.
+    pub(crate) azure_error: Option<AzureError>,
+
+    // Pages handled in auth_handlers.rs
+    pub(crate) pick_account: bool,
+    pub(crate) session_conflict: bool,
+    pub(crate) kmsi: bool,
+    /// Authenticator "Request denied" page.
+    pub(crate) ngc_denied: bool,
+
+    // MFA (mfa_handlers.rs)
+    /// Number to match in the Authenticator app, if one is shown.
+    pub(crate) mfa_number: Option<String>,
+    /// SAOTCAS push-approval page.
+    pub(crate) push_approval: bool,
+    /// SAOTCAS page while Microsoft is calling.
+    pub(crate) voice_call: bool,
+    /// "We didn't hear from you" page.
+    pub(crate) mfa_timeout: bool,
+    /// Passwordless sign-in waiting for the app.
+    pub(crate) ngc_polling: bool,
+    /// Passwordless sign-in could not send the request.
+    pub(crate) ngc_error: bool,
+    /// "Use an app instead" link.
+    pub(crate) use_app_instead: bool,
+    /// Proof choice page ("Verify your identity").
+    pub(crate) proof_choice: bool,
+    /// Labels of the visible proofs on the proof choice page.
+    pub(crate) proofs: Vec<String>,
+    /// Prompt of the one-time code page.
+    pub(crate) otp_prompt: Option<String>,
+    /// Error shown on the one-time code page.
+    pub(crate) otp_error: Option<String>,
+
+    /// Conditional Access interrupt shown, if any (interrupt_handlers.rs).
+    pub(crate) interrupt: Option<String>,
+
+    // Fallbacks (generic_detection.rs)
+    pub(crate) generic_error: Option<String>,
+    pub(crate) unexpected_state: Option<String>,
+}
+
+/// A visible input element.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub(crate) struct VisibleInput {
+    pub(crate) name: String,
+    pub(crate) id: String,
+    #[serde(rename = "type")]
+    pub(crate) kind: String,
+}
+
+/// Details of an Azure AD error page.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub(crate) struct AzureError {
+    /// `$Config.iErrorCode`.
+    pub(crate) code: Option<i64>,
+    /// Human-readable explanation.
+    pub(crate) details: String,
+}
+
+impl PageSnapshot {
+    /// Whether an input with the given `name` or `id` is visible.
+    pub(crate) fn has_input(&self, name_or_id: &str) -> bool {
+        self.visible_inputs
+            .iter()
+            .any(|i| i.name == name_or_id || i.id == name_or_id)
+    }
+}
+
+/// The classifier script; evaluates to the [`PageSnapshot`] as JSON.
+static CLASSIFY_JS: Lazy<String> = Lazy::new(|| {
+    let fragments = [
+        super::page_detection::classify_js(),
+        super::auth_handlers::classify_js(),
+        super::mfa_handlers::classify_js(),
+        super::interrupt_handlers::classify_js(),
+        super::generic_detection::classify_js(),
+    ];
+    // Each fragment gets its own scope, and one that throws leaves its
+    // fields at their defaults rather than losing the whole snapshot.
+    let body: String = fragments
+        .iter()
+        .map(|f| format!("    try {{ (function() {{{f}\n    }})(); }} catch (e) {{}}\n"))
+        .collect();
+    format!(
+        r#"(function() {{
+    var visible = function(el) {{ return !!(el && el.offsetParent !== null); }};
+    var byId = function(id) {{ return document.getElementById(id); }};
+    var text = function(el) {{ return ((el && el.innerText) || '').trim(); }};
+    var meta = document.querySelector('meta[name="PageID"]');
+    var pageId = (meta && meta.content) || '';
+    var headings = Array.prototype.slice.call(
+        document.querySelectorAll('#loginHeader, h1, h2, [role="heading"]')
+    ).filter(visible).map(function(el) {{ return el.innerText; }}).join(' ');
+    var snap = {{
+        pageId: pageId,
+        title: document.title || '',
+        lang: (document.documentElement && document.documentElement.lang) || '',
+        visibleInputs: Array.prototype.slice.call(document.querySelectorAll('input'))
+            .filter(function(el) {{
+                return visible(el)
+                    && ['hidden', 'submit', 'button', 'checkbox', 'radio'].indexOf(el.type) === -1;
+            }})
+            .map(function(el) {{ return {{ name: el.name || '', id: el.id || '', type: el.type || '' }}; }}),
+        headers: Array.prototype.slice.call(
+            document.querySelectorAll('h1, h2, .heading, [role="heading"]')
+        ).filter(visible).map(text).filter(Boolean).slice(0, 3),
+        buttons: Array.prototype.slice.call(document.querySelectorAll('button, input[type="submit"]'))
+            .filter(visible).map(function(el) {{ return (el.innerText || el.value || '').trim(); }})
+            .slice(0, 5),
+    }};
+{body}    return JSON.stringify(snap);
+}})()"#
+    )
+});
+
+impl AuthTab {
+    /// Classifies the current page in a single evaluation.
+    pub(crate) fn snapshot(&self) -> anyhow::Result<PageSnapshot> {
+        let json = self.eval_string_or(&CLASSIFY_JS, "{}")?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Logs the classified page state for debugging.
+    pub(crate) fn log_page_state(&self) -> anyhow::Result<()> {
+        log::debug!("[Page State] {:#?}", self.snapshot()?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::tab;
+    use super::*;
+    use crate::driver::fake::ScriptedPage;
+    use serde_json::json;
+
+    /// Names of the `snap` fields the classifier script sets.
+    fn script_fields() -> Vec<String> {
+        let mut fields: Vec<String> = [
+            "pageId",
+            "title",
+            "lang",
+            "visibleInputs",
+            "headers",
+            "buttons",
+        ]
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+        for part in CLASSIFY_JS.split("snap.").skip(1) {
+            let name: String = part
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect();
+            let rest = part[name.len()..].trim_start();
+            let assigned = rest.starts_with('=') && !rest.starts_with("==");
+            if assigned && !fields.contains(&name) {
+                fields.push(name);
+            }
+        }
+        fields
+    }
+
+    #[test]
+    fn every_classified_field_reaches_the_snapshot() {
+        let fields = script_fields();
+        assert!(fields.len() > 20, "only found {:?}", fields);
+        for field in fields {
+            // No snapshot field takes a fraction, so a known field fails to
+            // parse while a misspelt one would be silently ignored.
+            assert!(
+                serde_json::from_value::<PageSnapshot>(json!({ field.as_str(): 0.5 })).is_err(),
+                "snap.{} does not match a PageSnapshot field",
+                field
+            );
+        }
+    }
+
+    #[test]
+    fn each_fragment_runs_in_its_own_scope() {
+        assert_eq!(CLASSIFY_JS.matches("    try { (function() {").count(), 5);
+        assert!(CLASSIFY_JS
+            .trim_end()
+            .ends_with("return JSON.stringify(snap);\n})()"));
+    }
+
+    #[test]
+    fn snapshot_parses_what_the_page_reports() {
+        let report = json!({
+            "pageId": "KmsiInterrupt",
+            "kmsi": true,
+            "visibleInputs": [{ "name": "loginfmt", "id": "i0116", "type": "email" }],
+            "updatePasswordError": ["Your current password is incorrect.", true],
+            "azureError": { "code": 50126 },
+            "proofs": ["Text +X XXXXXXXX12"],
+            "addedLater": 1,
+        });
+        let (auth, _) = tab(ScriptedPage::new("https://login.microsoftonline.com/")
+            .on_script("JSON.stringify(snap)", json!(report.to_string())));
+        let snapshot = auth.snapshot().unwrap();
+        assert_eq!(snapshot.page_id, "KmsiInterrupt");
+        assert!(snapshot.kmsi);
+        assert!(!snapshot.pick_account);
+        assert!(snapshot.has_input("loginfmt"));
+        assert!(snapshot.has_input("i0116"));
+        assert!(!snapshot.has_input("passwd"));
+        assert_eq!(snapshot.visible_inputs[0].kind, "email");
+        assert_eq!(
+            snapshot.update_password_error,
+            Some(("Your current password is incorrect.".to_string(), true))
+        );
+        assert_eq!(
+            snapshot.azure_error,
+            Some(AzureError {
+                code: Some(50126),
+                details: String::new()
+            })
+        );
+        assert_eq!(snapshot.proofs, ["Text +X XXXXXXXX12"]);
+    }
+
+    #[test]
+    fn a_silent_page_is_an_empty_snapshot() {
+        let (auth, _) = tab(ScriptedPage::new("about:blank"));
+        assert_eq!(auth.snapshot().unwrap(), PageSnapshot::default());
+    }
+}
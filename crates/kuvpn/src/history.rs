@@ -14,6 +14,10 @@ pub enum EventKind {
     Disconnected,
     Cancelled,
     Error,
+    /// Headless login could not finish and continued in a visible browser.
+    BrowserFallback,
+    /// The browser crashed during login and was relaunched.
+    BrowserCrash,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -148,3 +152,33 @@ pub fn clear_events() -> Result<(), Box<dyn std::error::Error>> {
     }
     Ok(())
 }
+
+/// Estimates the gateway's hard session lifetime from past unexpected drops.
+///
+/// A gateway that expires sessions after a fixed time drops the tunnel after
+/// (nearly) the same duration every time, so this looks for at least three
+/// drops of over an hour that ended within five minutes of each other and
+/// returns the shortest of them.  Returns `None` when there is no such pattern.
+pub fn learned_session_lifetime(events: &[ConnectionEvent]) -> Option<u64> {
+    const MIN_LIFETIME_SECS: u64 = 60 * 60;
+    const TOLERANCE_SECS: u64 = 5 * 60;
+    const MIN_SAMPLES: usize = 3;
+
+    let mut drops: Vec<u64> = events
+        .iter()
+        // Planned renewals carry a message and end before the lifetime does.
+        .filter(|e| e.kind == EventKind::Reconnected && e.message.is_none())
+        .filter_map(|e| e.duration_secs)
+        .filter(|&d| d >= MIN_LIFETIME_SECS)
+        .collect();
+    drops.sort_unstable();
+    (0..drops.len())
+        .find(|&i| {
+            drops[i..]
+                .iter()
+                .take_while(|&&d| d - drops[i] <= TOLERANCE_SECS)
+                .count()
+                >= MIN_SAMPLES
+        })
+        .map(|i| drops[i])
+}
@@ -0,0 +1,54 @@
+//! User commands run when the VPN tunnel comes up or goes down.
+//!
+//! Hooks run through the platform shell (`sh -c` / `cmd /C`) without
+//! waiting for them to finish, with `KUVPN_EVENT` and `KUVPN_INTERFACE` set
+//! in their environment.
+
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+
+/// Commands to run on connection events.  Empty by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionHooks {
+    /// Run once the tunnel is up (again after every reconnect).
+    pub on_connect: Option<String>,
+    /// Run after the tunnel has been torn down on disconnect.
+    pub on_disconnect: Option<String>,
+}
+
+/// Starts `command` for `event` (`"connect"` or `"disconnect"`) and reaps it
+/// on a background thread, logging a non-zero exit.
+pub(crate) fn run_hook(command: &str, event: &'static str, interface_name: &str) {
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(command);
+        c
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(command);
+        c
+    };
+    cmd.env("KUVPN_EVENT", event)
+        .env("KUVPN_INTERFACE", interface_name)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    match cmd.spawn() {
+        Ok(mut child) => {
+            log::info!("Running {} hook: {}", event, command);
+            std::thread::spawn(move || match child.wait() {
+                Ok(status) if !status.success() => {
+                    log::warn!("The {} hook exited with {}", event, status)
+                }
+                Err(e) => log::warn!("Could not wait for the {} hook: {}", event, e),
+                _ => {}
+            });
+        }
+        Err(e) => log::warn!("Could not start the {} hook: {}", event, e),
+    }
+}
@@ -0,0 +1,1543 @@
+//! Browserless login: the Pulse → Azure AD SAML exchange over plain HTTP.
+//!
+//! The engine follows the gateway's redirect to Microsoft, reads the
+//! `$Config` JSON that every Azure AD page embeds, posts the username and
+//! password, drives the MFA endpoints (`BeginAuth` / `EndAuth`), answers
+//! "Stay signed in?" and posts the SAML response back to the gateway, which
+//! then sets the DSID cookie.  Only URLs taken from the responses are
+//! followed, so the whole exchange can be pointed at a local server that
+//! replays recorded responses.
+//!
+//! Pages it does not know (Terms of Use, proof-up, password change, …) end
+//! the attempt with [`HttpLoginUnsupported`] and
+//! [`crate::dsid::run_login_and_get_dsid`] carries on in the browser, as long
+//! as the password has not been sent yet; after that the attempt fails
+//! rather than asking for the password a second time.
+//! Cookies are kept in `http_cookies.json` in the browser profile directory
+//! (readable by the user only), so the Microsoft "stay signed in" session
+//! survives between logins just like the browser's own.
+
+use crate::dsid::{check_lockout, record_login_success, record_password_failure};
+use crate::dsid::{LoginConfig, MfaMethod, SessionConflictPolicy};
+use crate::error::AuthError;
+use crate::utils::{CancellationToken, CredentialsProvider};
+use serde_json::{json, Value};
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Pages the login may pass through before giving up.
+const MAX_STEPS: usize = 25;
+
+/// Redirects followed for one request.
+const MAX_REDIRECTS: usize = 10;
+
+/// How long one push or call is polled before it counts as unanswered.
+const MFA_POLL_LIMIT: Duration = Duration::from_secs(120);
+
+/// The HTTP engine met a page it cannot complete; the browser should take
+/// over.  Carried through `anyhow` like [`crate::dsid::VisibleBrowserRequired`].
+#[derive(Debug)]
+pub(crate) struct HttpLoginUnsupported(pub(crate) String);
+
+impl std::fmt::Display for HttpLoginUnsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for HttpLoginUnsupported {}
+
+fn unsupported(reason: impl Into<String>) -> anyhow::Error {
+    HttpLoginUnsupported(reason.into()).into()
+}
+
+// ── HTTP client ───────────────────────────────────────────────────────────────
+
+/// A fetched page: the final URL after redirects and the body.
+struct Page {
+    url: String,
+    body: String,
+}
+
+struct HttpClient {
+    agent: ureq::Agent,
+    user_agent: String,
+}
+
+/// Cookie file inside the browser profile directory, so wiping the profile
+/// signs the HTTP engine out as well.
+fn cookie_jar_path() -> Option<PathBuf> {
+    crate::utils::profile_dir_path()
+        .ok()
+        .map(|dir| dir.join("http_cookies.json"))
+}
+
+/// Writes `data` to `path` with access for the user only, since the cookie
+/// file holds the Microsoft sign-in.
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // `mode` only applies to new files; tighten one saved earlier as well.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(data)
+}
+
+impl HttpClient {
+    fn new(user_agent: &str) -> Self {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(30)))
+            .http_status_as_error(false)
+            // Redirects are followed by hand: ureq keeps the form body headers
+            // when it turns a redirected POST into a GET.
+            .max_redirects(0)
+            .build()
+            .into();
+        let client = Self {
+            agent,
+            user_agent: user_agent.to_string(),
+        };
+        client.load_cookies();
+        client
+    }
+
+    fn load_cookies(&self) {
+        let Some(path) = cookie_jar_path().filter(|p| p.exists()) else {
+            return;
+        };
+        let result = std::fs::File::open(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| {
+                self.agent
+                    .cookie_jar_lock()
+                    .load_json(BufReader::new(file))
+                    .map_err(anyhow::Error::from)
+            });
+        if let Err(e) = result {
+            log::warn!("Could not load saved HTTP cookies: {}", e);
+        }
+    }
+
+    /// Saves the persistent cookies (the Microsoft sign-in) for next time.
+    fn save_cookies(&self) {
+        let Some(path) = crate::utils::get_user_data_dir()
+            .ok()
+            .and_then(|_| cookie_jar_path())
+        else {
+            return;
+        };
+        let mut buf = Vec::new();
+        let saved = self.agent.cookie_jar_lock().save_json(&mut buf);
+        if let Err(e) = saved
+            .map_err(anyhow::Error::from)
+            .and_then(|_| write_private(&path, &buf).map_err(anyhow::Error::from))
+        {
+            log::warn!("Could not save HTTP cookies: {}", e);
+        }
+    }
+
+    fn dsid(&self, domain: &str) -> Option<String> {
+        let jar = self.agent.cookie_jar_lock();
+        let dsid = jar.get(domain, "/", "DSID").map(|c| c.value().to_string());
+        dsid.filter(|v| !v.is_empty())
+    }
+
+    /// Follows redirects from `response` (to a request for `url`) and reads
+    /// the final page.
+    fn read_page(
+        &self,
+        mut response: ureq::http::Response<ureq::Body>,
+        url: &str,
+    ) -> anyhow::Result<Page> {
+        let mut url = url.to_string();
+        for _ in 0..MAX_REDIRECTS {
+            let status = response.status();
+            log::debug!("HTTP {} {}", status, url);
+            if status.is_redirection() {
+                let location = response
+                    .headers()
+                    .get("location")
+                    .and_then(|v| v.to_str().ok())
+                    .ok_or_else(|| anyhow::anyhow!("{} redirected without a location", url))?;
+                url = resolve(&url, location)?;
+                response = self.request_get(&url)?;
+                continue;
+            }
+            if status.is_server_error() {
+                anyhow::bail!("{} returned HTTP {}", url, status);
+            }
+            let body = response.body_mut().read_to_string()?;
+            return Ok(Page { url, body });
+        }
+        anyhow::bail!("too many redirects, last to {}", url)
+    }
+
+    fn request_get(&self, url: &str) -> anyhow::Result<ureq::http::Response<ureq::Body>> {
+        Ok(self
+            .agent
+            .get(url)
+            .header("User-Agent", &self.user_agent)
+            .header("Accept-Language", "en-US,en")
+            .call()?)
+    }
+
+    fn get(&self, url: &str) -> anyhow::Result<Page> {
+        let response = self.request_get(url)?;
+        self.read_page(response, url)
+    }
+
+    fn post_form(&self, url: &str, fields: &[(String, String)]) -> anyhow::Result<Page> {
+        let response = self
+            .agent
+            .post(url)
+            .header("User-Agent", &self.user_agent)
+            .header("Accept-Language", "en-US,en")
+            .send_form(fields.iter().map(|(k, v)| (k.as_str(), v.as_str())))?;
+        self.read_page(response, url)
+    }
+
+    fn post_json(&self, url: &str, body: &Value) -> anyhow::Result<Value> {
+        let mut response = self
+            .agent
+            .post(url)
+            .header("User-Agent", &self.user_agent)
+            .header("Accept", "application/json")
+            .send_json(body)?;
+        Ok(response.body_mut().read_json()?)
+    }
+}
+
+/// Resolves `target` (possibly relative) against the page it came from.
+fn resolve(base: &str, target: &str) -> anyhow::Result<String> {
+    Ok(url::Url::parse(base)?.join(target)?.to_string())
+}
+
+fn fields<const N: usize>(pairs: [(&str, &str); N]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+// ── HTML scraping ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Default)]
+struct Input {
+    kind: String,
+    name: String,
+    value: String,
+    checked: bool,
+    /// Text of the table row holding the input, for listing choices.
+    row: String,
+}
+
+#[derive(Debug, Default)]
+struct Form {
+    id: String,
+    action: String,
+    inputs: Vec<Input>,
+}
+
+impl Form {
+    fn has_input(&self, name: &str) -> bool {
+        self.inputs.iter().any(|i| i.name == name)
+    }
+
+    /// The fields a browser would submit without user changes.
+    fn default_fields(&self) -> Vec<(String, String)> {
+        self.inputs
+            .iter()
+            .filter(|i| !i.name.is_empty())
+            .filter(|i| match i.kind.as_str() {
+                "checkbox" | "radio" => i.checked,
+                "submit" | "button" | "image" => false,
+                _ => true,
+            })
+            .map(|i| (i.name.clone(), i.value.clone()))
+            .collect()
+    }
+}
+
+fn decode_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';').filter(|&i| i <= 10) else {
+            out.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Byte offset just past the `>` closing the tag that starts at `start`.
+fn tag_end(html: &str, start: usize) -> usize {
+    let mut quote = None;
+    for (i, c) in html[start..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return start + i + 1,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+/// Parses the attributes of a start tag such as `<input name="a" checked>`.
+fn attributes(tag: &str) -> Vec<(String, String)> {
+    let inner = tag
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .trim_end_matches('/');
+    let mut chars = inner.char_indices().peekable();
+    // Skip the tag name.
+    while chars.next_if(|(_, c)| !c.is_whitespace()).is_some() {}
+
+    let mut attrs = Vec::new();
+    loop {
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let Some(&(start, _)) = chars.peek() else {
+            break;
+        };
+        let mut end = start;
+        while let Some((i, c)) = chars.next_if(|(_, c)| !c.is_whitespace() && *c != '=') {
+            end = i + c.len_utf8();
+        }
+        let name = inner[start..end].to_ascii_lowercase();
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let mut value = String::new();
+        if chars.next_if(|(_, c)| *c == '=').is_some() {
+            while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+            match chars.peek().map(|&(_, c)| c) {
+                Some(q @ ('"' | '\'')) => {
+                    chars.next();
+                    for (_, c) in chars.by_ref() {
+                        if c == q {
+                            break;
+                        }
+                        value.push(c);
+                    }
+                }
+                _ => {
+                    while let Some((_, c)) = chars.next_if(|(_, c)| !c.is_whitespace()) {
+                        value.push(c);
+                    }
+                }
+            }
+        }
+        if name.is_empty() {
+            // Stray character (e.g. a lone `=`); skip it.
+            chars.next();
+            continue;
+        }
+        attrs.push((name, decode_entities(&value)));
+    }
+    attrs
+}
+
+fn attr<'a>(attrs: &'a [(String, String)], name: &str) -> &'a str {
+    attrs
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+        .unwrap_or("")
+}
+
+/// Visible text of an HTML fragment, with whitespace collapsed.
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut i = 0;
+    while let Some(open) = html[i..].find('<') {
+        text.push_str(&html[i..i + open]);
+        text.push(' ');
+        i = tag_end(html, i + open);
+    }
+    text.push_str(&html[i..]);
+    decode_entities(&text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn forms(html: &str) -> Vec<Form> {
+    // ASCII lowercasing keeps byte offsets, so indices carry over to `html`.
+    let lower = html.to_ascii_lowercase();
+    let mut forms = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = lower[pos..].find("<form") {
+        let start = pos + found;
+        let open_end = tag_end(html, start);
+        let close = lower[open_end..]
+            .find("</form")
+            .map_or(html.len(), |i| open_end + i);
+        let attrs = attributes(&html[start..open_end]);
+        let mut form = Form {
+            id: attr(&attrs, "id").to_string(),
+            action: attr(&attrs, "action").to_string(),
+            inputs: Vec::new(),
+        };
+        if form.id.is_empty() {
+            form.id = attr(&attrs, "name").to_string();
+        }
+
+        let mut ipos = open_end;
+        while let Some(found) = lower[ipos..close].find("<input") {
+            let istart = ipos + found;
+            let iend = tag_end(html, istart).min(close);
+            let attrs = attributes(&html[istart..iend]);
+            let row_start = lower[open_end..istart].rfind("<tr").map(|i| open_end + i);
+            let row = row_start
+                .and_then(|rs| {
+                    lower[iend..close]
+                        .find("</tr")
+                        .map(|re| strip_tags(&html[rs..iend + re]))
+                })
+                .unwrap_or_default();
+            form.inputs.push(Input {
+                kind: attr(&attrs, "type").to_ascii_lowercase(),
+                name: attr(&attrs, "name").to_string(),
+                value: attr(&attrs, "value").to_string(),
+                checked: attrs.iter().any(|(n, _)| n == "checked"),
+                row,
+            });
+            ipos = iend;
+        }
+        forms.push(form);
+        pos = close;
+    }
+    forms
+}
+
+/// Extracts the JSON object assigned to `$Config` in an Azure AD page.
+fn azure_config(html: &str) -> Option<Value> {
+    let start = html.find("$Config=")?;
+    let open = start + html[start..].find('{')?;
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in html[open..].char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return serde_json::from_str(&html[open..=open + i]).ok();
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// String field of a `$Config` or API response, `""` when absent.
+fn text<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or("")
+}
+
+// ── Login flow ────────────────────────────────────────────────────────────────
+
+struct HttpLogin<'a> {
+    http: HttpClient,
+    config: &'a LoginConfig,
+    provider: &'a dyn CredentialsProvider,
+    cancel_token: Option<&'a CancellationToken>,
+    username: Option<String>,
+    password_sent: bool,
+    mfa_seen: bool,
+}
+
+impl HttpLogin<'_> {
+    fn check_cancelled(&self) -> Result<(), AuthError> {
+        match self.cancel_token {
+            Some(token) if token.is_cancelled() => Err(AuthError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    fn run(&mut self) -> anyhow::Result<String> {
+        log::info!("Signing in over HTTP: {}", self.config.url);
+        let mut page = self.http.get(&self.config.url)?;
+        for _ in 0..MAX_STEPS {
+            self.check_cancelled()?;
+            if let Some(dsid) = self.http.dsid(&self.config.domain) {
+                log::info!("Found valid DSID, quitting.");
+                return Ok(dsid);
+            }
+            page = self.step(&page)?;
+        }
+        Err(unsupported(format!(
+            "no DSID after {} pages, last at {}",
+            MAX_STEPS, page.url
+        )))
+    }
+
+    /// Acts on one page and returns the next.
+    fn step(&mut self, page: &Page) -> anyhow::Result<Page> {
+        log::info!("Page: {}", page.url);
+        let forms = forms(&page.body);
+
+        if let Some(form) = forms.iter().find(|f| f.id == "DSIDConfirmForm") {
+            return self.resolve_session_conflict(page, form);
+        }
+
+        // SAML hand-offs between the gateway and Microsoft are auto-submitted
+        // forms (HTTP-POST binding).
+        if let Some(form) = forms
+            .iter()
+            .find(|f| f.has_input("SAMLResponse") || f.has_input("SAMLRequest"))
+        {
+            log::info!("Posting the SAML form to {}", form.action);
+            let action = resolve(&page.url, &form.action)?;
+            return self.http.post_form(&action, &form.default_fields());
+        }
+
+        let Some(config) = azure_config(&page.body) else {
+            return Err(unsupported(format!("unrecognised page at {}", page.url)));
+        };
+        self.check_azure_error(&config)?;
+        match text(&config, "pgid") {
+            "ConvergedSignIn" => self.sign_in(page, &config),
+            "ConvergedTFA" => self.mfa(page, &config),
+            "KmsiInterrupt" => {
+                log::info!("Detected KMSI – answering Yes...");
+                let post = resolve(&page.url, text(&config, "urlPost"))?;
+                self.http.post_form(
+                    &post,
+                    &fields([
+                        ("LoginOptions", "1"),
+                        ("type", "28"),
+                        ("ctx", text(&config, "sCtx")),
+                        ("flowToken", text(&config, "sFT")),
+                        ("canary", text(&config, "canary")),
+                    ]),
+                )
+            }
+            other => Err(unsupported(format!("Azure AD page '{}'", other))),
+        }
+    }
+
+    /// Maps the error code Azure AD puts in `$Config` to an [`AuthError`].
+    fn check_azure_error(&self, config: &Value) -> anyhow::Result<()> {
+        let code = match config.get("sErrorCode") {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Number(n)) => n.to_string(),
+            _ => String::new(),
+        };
+        if code.is_empty() || code == "0" {
+            return Ok(());
+        }
+        let message = text(config, "strServiceExceptionMessage").to_string();
+        log::warn!("Azure AD error {}: {}", code, message);
+        Err(match code.as_str() {
+            "50126" => {
+                record_password_failure();
+                AuthError::IncorrectPassword {
+                    message: "Your account or password is incorrect.".to_string(),
+                }
+                .into()
+            }
+            "50034" => AuthError::InvalidUsername {
+                message: "The username you entered may be incorrect or the account does not exist."
+                    .to_string(),
+            }
+            .into(),
+            "50053" => {
+                record_password_failure();
+                AuthError::AuthenticationFailed {
+                    reason: format!("Your account is temporarily locked.\n\n{}", message),
+                    suggest_manual_mode: false,
+                    suggest_clear_cache: false,
+                }
+                .into()
+            }
+            // Expired password and similar: the browser handlers know the form.
+            "50055" | "50056" | "50144" => unsupported(format!("Azure AD error {}", code)),
+            _ => AuthError::AuthenticationFailed {
+                reason: format!("Azure AD error {}.\n\n{}", code, message),
+                suggest_manual_mode: true,
+                suggest_clear_cache: true,
+            }
+            .into(),
+        })
+    }
+
+    fn sign_in(&mut self, page: &Page, config: &Value) -> anyhow::Result<Page> {
+        if self.password_sent {
+            return Err(unsupported("Azure AD asked for the password again"));
+        }
+        let username = match self.username.clone().or_else(|| self.config.email.clone()) {
+            Some(u) => u,
+            None => self
+                .provider
+                .request_email("Username (email): ")
+                .ok_or(AuthError::Cancelled)?,
+        };
+        self.username = Some(username.clone());
+
+        check_lockout(&self.config.lockout, self.provider)?;
+        let password = self
+            .provider
+            .request_password("KU Password: ")
+            .ok_or(AuthError::Cancelled)?;
+        self.password_sent = true;
+
+        log::info!("Submitting username and password");
+        let post = resolve(&page.url, text(config, "urlPost"))?;
+        self.http.post_form(
+            &post,
+            &fields([
+                ("login", &username),
+                ("loginfmt", &username),
+                ("passwd", &password),
+                ("type", "11"),
+                ("LoginOptions", "3"),
+                ("ps", "2"),
+                ("i13", "0"),
+                ("ctx", text(config, "sCtx")),
+                ("flowToken", text(config, "sFT")),
+                ("canary", text(config, "canary")),
+            ]),
+        )
+    }
+
+    /// Picks the proof to use from `arrUserProofs` following the preference.
+    fn choose_proof(&self, proofs: &[Value]) -> anyhow::Result<String> {
+        let id = |p: &Value| text(p, "authMethodId").to_string();
+        for method in &self.config.mfa_preference {
+            if *method == MfaMethod::FirstAvailable {
+                let default = proofs
+                    .iter()
+                    .find(|p| p.get("isDefault").and_then(Value::as_bool) == Some(true))
+                    .or_else(|| proofs.first());
+                if let Some(p) = default {
+                    return Ok(id(p));
+                }
+            }
+            if let Some(p) = proofs
+                .iter()
+                .find(|p| method.proof_values().contains(&text(p, "authMethodId")))
+            {
+                return Ok(id(p));
+            }
+        }
+        let options: Vec<String> = proofs
+            .iter()
+            .map(|p| format!("{} {}", text(p, "authMethodId"), text(p, "display")))
+            .collect();
+        let picked = self
+            .provider
+            .request_choice("How do you want to verify your identity?", &options)
+            .ok_or(AuthError::Cancelled)?;
+        let proof = proofs.get(picked).ok_or_else(|| {
+            anyhow::anyhow!(
+                "choice {} is not one of the {} MFA methods",
+                picked,
+                proofs.len()
+            )
+        })?;
+        Ok(id(proof))
+    }
+
+    fn mfa(&mut self, page: &Page, config: &Value) -> anyhow::Result<Page> {
+        let proofs = config
+            .get("arrUserProofs")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let begin_url = text(config, "urlBeginAuth");
+        let end_url = text(config, "urlEndAuth");
+        if proofs.is_empty() || begin_url.is_empty() || end_url.is_empty() {
+            return Err(unsupported("MFA page without proof endpoints"));
+        }
+        let begin_url = resolve(&page.url, begin_url)?;
+        let end_url = resolve(&page.url, end_url)?;
+        let method = self.choose_proof(&proofs)?;
+        log::info!("Using MFA method {}", method);
+        self.mfa_seen = true;
+
+        let needs_code = MfaMethod::Totp.proof_values().contains(&method.as_str())
+            || MfaMethod::Sms.proof_values().contains(&method.as_str());
+        let mut ctx = text(config, "sCtx").to_string();
+        let mut flow_token = text(config, "sFT").to_string();
+        let mut resends = 0;
+        let mut code = String::new();
+
+        'begin: loop {
+            let begin = self.http.post_json(
+                &begin_url,
+                &json!({
+                    "AuthMethodId": method,
+                    "Method": "BeginAuth",
+                    "ctx": ctx,
+                    "flowToken": flow_token,
+                }),
+            )?;
+            if begin.get("Success").and_then(Value::as_bool) != Some(true) {
+                return Err(AuthError::AuthenticationFailed {
+                    reason: format!("Could not start MFA: {}", text(&begin, "Message")),
+                    suggest_manual_mode: true,
+                    suggest_clear_cache: false,
+                }
+                .into());
+            }
+            let session_id = text(&begin, "SessionId").to_string();
+            ctx = text(&begin, "Ctx").to_string();
+            flow_token = text(&begin, "FlowToken").to_string();
+
+            let mut prompt = "Enter verification code: ".to_string();
+            if !needs_code {
+                if method.starts_with("TwoWayVoice") {
+                    self.provider.on_mfa_call();
+                } else {
+                    let number = begin
+                        .get("Entropy")
+                        .and_then(Value::as_u64)
+                        .filter(|&n| n > 0)
+                        .map(|n| n.to_string())
+                        .unwrap_or_default();
+                    self.provider.on_mfa_push(&number);
+                }
+            }
+
+            let started = Instant::now();
+            let mut poll_count = 0;
+            loop {
+                self.check_cancelled()
+                    .inspect_err(|_| self.provider.on_mfa_complete())?;
+                if needs_code {
+                    code = self
+                        .provider
+                        .request_text(&prompt)
+                        .ok_or(AuthError::Cancelled)?;
+                } else {
+                    sleep(Duration::from_secs(1));
+                }
+                poll_count += 1;
+                let mut end = json!({
+                    "AuthMethodId": method,
+                    "Method": "EndAuth",
+                    "SessionId": session_id,
+                    "FlowToken": flow_token,
+                    "Ctx": ctx,
+                    "PollCount": poll_count,
+                });
+                if needs_code {
+                    end["AdditionalAuthData"] = json!(code.trim());
+                }
+                let end = self.http.post_json(&end_url, &end)?;
+                if let Some(ft) = end.get("FlowToken").and_then(Value::as_str) {
+                    flow_token = ft.to_string();
+                }
+                if let Some(c) = end.get("Ctx").and_then(Value::as_str) {
+                    ctx = c.to_string();
+                }
+                let result = text(&end, "ResultValue");
+                if end.get("Success").and_then(Value::as_bool) == Some(true) {
+                    break 'begin;
+                }
+                // Only an explicit timeout re-sends: Microsoft reporting the
+                // request unanswered, or a push or call still pending when
+                // the poll limit runs out.
+                let pending = matches!(result, "AuthenticationPending" | "Pending" | "");
+                let unanswered = result.contains("NoResponse")
+                    || result.contains("Voicemail")
+                    || result.contains("Unreachable");
+                let timed_out = started.elapsed() >= MFA_POLL_LIMIT;
+                match result {
+                    _ if pending && needs_code => {
+                        log::warn!("No result for the verification code: {:?}", result);
+                        prompt = "The code could not be checked. Try again.\n\n\
+                                  Enter verification code: "
+                            .to_string();
+                        continue;
+                    }
+                    _ if pending && !timed_out => continue,
+                    "OathCodeIncorrect"
+                    | "InvalidOathCode"
+                    | "OathCodeDuplicate"
+                    | "SMSAuthFailedWrongCodeEntered" => {
+                        log::warn!("Verification code rejected: {}", result);
+                        prompt = "That code didn't work. Check the code and try again.\n\n\
+                                  Enter verification code: "
+                            .to_string();
+                        continue;
+                    }
+                    "PhoneAppDenied" | "UserVoiceAuthFailedFraudCode" => {
+                        self.provider.on_mfa_complete();
+                        return Err(AuthError::AuthenticationFailed {
+                            reason: "The sign-in request was denied.".to_string(),
+                            suggest_manual_mode: false,
+                            suggest_clear_cache: false,
+                        }
+                        .into());
+                    }
+                    _ if pending || (unanswered && !needs_code) => {}
+                    _ => {
+                        self.provider.on_mfa_complete();
+                        return Err(AuthError::AuthenticationFailed {
+                            reason: format!("MFA failed: {} {}", result, text(&end, "Message")),
+                            suggest_manual_mode: true,
+                            suggest_clear_cache: false,
+                        }
+                        .into());
+                    }
+                }
+
+                // Unanswered push or call.
+                if resends >= self.config.max_push_resends {
+                    self.provider.on_mfa_complete();
+                    return Err(AuthError::Timeout {
+                        message: "The MFA request was not answered.".to_string(),
+                    }
+                    .into());
+                }
+                resends += 1;
+                self.provider
+                    .on_mfa_resend(resends, self.config.max_push_resends);
+                continue 'begin;
+            }
+        }
+        self.provider.on_mfa_complete();
+
+        let post = resolve(&page.url, text(config, "urlPost"))?;
+        let username = self.username.clone().unwrap_or_default();
+        let mut form = fields([
+            ("type", if needs_code { "19" } else { "22" }),
+            ("request", &ctx),
+            ("mfaAuthMethod", &method),
+            ("canary", text(config, "canary")),
+            ("login", &username),
+            ("flowToken", &flow_token),
+            ("hideSmsInMfaProofs", "false"),
+        ]);
+        if needs_code {
+            form.push(("otc".to_string(), code.trim().to_string()));
+        }
+        self.http.post_form(&post, &form)
+    }
+
+    /// Answers the gateway's "you already have a session" page according to
+    /// the session-conflict policy.
+    fn resolve_session_conflict(&self, page: &Page, form: &Form) -> anyhow::Result<Page> {
+        let sessions: Vec<&Input> = form
+            .inputs
+            .iter()
+            .filter(|i| i.kind == "checkbox" && i.name == "postfixSID")
+            .collect();
+        log::info!(
+            "Detected {} existing VPN session(s) on the gateway",
+            sessions.len()
+        );
+        let describe = |i: usize| match sessions[i].row.as_str() {
+            "" => format!("session {}", i + 1),
+            row => row.to_string(),
+        };
+        let close: Vec<usize> = match self.config.session_conflict {
+            SessionConflictPolicy::CloseAll => (0..sessions.len()).collect(),
+            SessionConflictPolicy::KeepAll => Vec::new(),
+            SessionConflictPolicy::Ask if sessions.is_empty() => Vec::new(),
+            SessionConflictPolicy::Ask => {
+                let mut options: Vec<String> = (0..sessions.len())
+                    .map(|i| format!("End {}", describe(i)))
+                    .collect();
+                options.push("End all listed sessions".to_string());
+                options.push("Keep all and continue".to_string());
+                match self.provider.request_choice(
+                    "You already have a VPN session open. Which one should be ended?",
+                    &options,
+                ) {
+                    Some(i) if i < sessions.len() => vec![i],
+                    Some(i) if i == sessions.len() => (0..sessions.len()).collect(),
+                    Some(_) => Vec::new(),
+                    None => return Err(AuthError::Cancelled.into()),
+                }
+            }
+        };
+
+        let mut fields: Vec<(String, String)> = form
+            .default_fields()
+            .into_iter()
+            .filter(|(name, _)| name != "postfixSID")
+            .collect();
+        for &i in &close {
+            log::info!("Ending existing session: {}", describe(i));
+            fields.push(("postfixSID".to_string(), sessions[i].value.clone()));
+        }
+        if let Some(button) = form.inputs.iter().find(|i| i.name == "btnContinue") {
+            fields.push((button.name.clone(), button.value.clone()));
+        }
+        let action = resolve(&page.url, &form.action)?;
+        self.http.post_form(&action, &fields)
+    }
+}
+
+/// Runs the login over HTTP and returns the DSID.  Errors that downcast to
+/// [`HttpLoginUnsupported`] mean the browser should be tried instead; those
+/// are only returned before the password is sent.  Every other error is
+/// final.
+pub(crate) fn run_http_login(
+    config: &LoginConfig,
+    provider: &dyn CredentialsProvider,
+    cancel_token: Option<&CancellationToken>,
+) -> anyhow::Result<String> {
+    let mut login = HttpLogin {
+        http: HttpClient::new(&config.user_agent),
+        config,
+        provider,
+        cancel_token,
+        username: None,
+        password_sent: false,
+        mfa_seen: false,
+    };
+    let result = login
+        .run()
+        .map_err(|e| match e.downcast::<HttpLoginUnsupported>() {
+            // A browser fallback now would ask for the password a second time.
+            Ok(unsupported) if login.password_sent => AuthError::AuthenticationFailed {
+                reason: format!(
+                    "The HTTP login could not finish after signing in: {}. \
+                 Sign in once with the browser login engine to complete it.",
+                    unsupported
+                ),
+                suggest_manual_mode: true,
+                suggest_clear_cache: false,
+            }
+            .into(),
+            Ok(unsupported) => unsupported.into(),
+            Err(e) => e,
+        });
+    login.http.save_cookies();
+    if result.is_ok() {
+        record_login_success(login.mfa_seen);
+    }
+    result
+}
+
+/// Deletes the HTTP engine's saved cookies (its Microsoft sign-in).
+pub(crate) fn clear_saved_cookies() -> std::io::Result<()> {
+    match cookie_jar_path() {
+        Some(path) if path.exists() => std::fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsid::{LoginEngine, VisibleFallback};
+    use crate::lockout::LockoutPolicy;
+    use std::io::{BufRead, Read};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+
+    // ── Scraping ──────────────────────────────────────────────────────────────
+
+    #[test]
+    fn decodes_named_and_numeric_entities() {
+        assert_eq!(
+            decode_entities("a &amp; b &lt;c&gt; &quot;d&quot; &apos;e&apos;"),
+            "a & b <c> \"d\" 'e'"
+        );
+        assert_eq!(decode_entities("&#252;&#xFC;&#XfC;"), "üüü");
+    }
+
+    #[test]
+    fn leaves_unknown_or_unterminated_entities() {
+        assert_eq!(
+            decode_entities("&nbsp; &bogus; &#xZZ;"),
+            "&nbsp; &bogus; &#xZZ;"
+        );
+        assert_eq!(
+            decode_entities("AT&T and more text;"),
+            "AT&T and more text;"
+        );
+        assert_eq!(decode_entities("trailing &"), "trailing &");
+    }
+
+    #[test]
+    fn tag_end_skips_quoted_brackets() {
+        let html = r#"<input value="a>b" title='c>d'>rest"#;
+        assert_eq!(&html[tag_end(html, 0)..], "rest");
+        assert_eq!(tag_end("<input", 0), 6);
+    }
+
+    #[test]
+    fn parses_attributes() {
+        let attrs = attributes(
+            r#"<INPUT Type="checkbox" name='postfixSID' value=abc&amp;1 checked data-x = "y" />"#,
+        );
+        assert_eq!(
+            attrs,
+            vec![
+                ("type".to_string(), "checkbox".to_string()),
+                ("name".to_string(), "postfixSID".to_string()),
+                ("value".to_string(), "abc&1".to_string()),
+                ("checked".to_string(), String::new()),
+                ("data-x".to_string(), "y".to_string()),
+            ]
+        );
+        assert_eq!(attr(&attrs, "name"), "postfixSID");
+        assert_eq!(attr(&attrs, "missing"), "");
+        assert!(attributes("<br>").is_empty());
+    }
+
+    #[test]
+    fn strips_tags_and_collapses_whitespace() {
+        assert_eq!(
+            strip_tags("<td>Chrome &amp;\n  <b>Windows</b></td>"),
+            "Chrome & Windows"
+        );
+    }
+
+    #[test]
+    fn finds_forms_and_inputs() {
+        let html = r#"
+            <form id="first" action="/a"><input name="x" value="1"></form>
+            <FORM name="DSIDConfirmForm" action="/b">
+              <table>
+                <tr><td><input type="checkbox" name="postfixSID" value="s1"></td>
+                    <td>10.0.0.1</td><td>Chrome</td></tr>
+                <tr><td><input type="checkbox" name="postfixSID" value="s2" checked></td>
+                    <td>10.0.0.2</td></tr>
+              </table>
+              <input type="radio" name="r" value="off">
+              <input type="submit" name="btnContinue" value="Continue">
+              <input type="hidden" name="FormDataStr" value="data">
+            </FORM>"#;
+        let forms = forms(html);
+        assert_eq!(forms.len(), 2);
+        assert_eq!(forms[0].id, "first");
+        assert_eq!(forms[0].action, "/a");
+
+        let conflict = &forms[1];
+        assert_eq!(conflict.id, "DSIDConfirmForm");
+        assert!(conflict.has_input("FormDataStr"));
+        assert_eq!(conflict.inputs[0].row, "10.0.0.1 Chrome");
+        assert!(!conflict.inputs[0].checked);
+        assert!(conflict.inputs[1].checked);
+        assert_eq!(
+            conflict.default_fields(),
+            vec![
+                ("postfixSID".to_string(), "s2".to_string()),
+                ("FormDataStr".to_string(), "data".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn extracts_azure_config() {
+        let html = r#"<script>//<![CDATA[
+            $Config={"pgid":"ConvergedSignIn","s":"a } \" {","nested":{"n":1}};
+            var other = {"x": 1};
+        //]]></script>"#;
+        let config = azure_config(html).unwrap();
+        assert_eq!(text(&config, "pgid"), "ConvergedSignIn");
+        assert_eq!(text(&config, "s"), "a } \" {");
+        assert_eq!(config["nested"]["n"], 1);
+        assert_eq!(text(&config, "missing"), "");
+
+        assert!(azure_config("<html>no config</html>").is_none());
+        assert!(azure_config(r#"$Config={"pgid":"cut off"#).is_none());
+    }
+
+    #[test]
+    fn resolves_relative_urls() {
+        assert_eq!(
+            resolve("https://login.example.com/common/login", "/kmsi").unwrap(),
+            "https://login.example.com/kmsi"
+        );
+        assert_eq!(
+            resolve("https://a.example.com/x", "https://b.example.com/y").unwrap(),
+            "https://b.example.com/y"
+        );
+    }
+
+    // ── Local stand-in for the gateway and Microsoft ─────────────────────────
+
+    /// Recorded responses, trimmed to what the engine reads.
+    const GATEWAY_SAML_REQUEST: &str = r#"<html><body onload="document.forms[0].submit()">
+        <form method="POST" action="/common/saml2">
+        <input type="hidden" name="SAMLRequest" value="PHNhbWxwOkF1dGhuUmVxdWVzdC8+">
+        <input type="hidden" name="RelayState" value="state&amp;1">
+        <noscript><input type="submit" value="Continue"></noscript>
+        </form></body></html>"#;
+
+    const SIGN_IN: &str = r#"<html><head><script type="text/javascript">//<![CDATA[
+        $Config={"pgid":"ConvergedSignIn","urlPost":"/common/login","sCtx":"ctx-1",
+        "sFT":"ft-1","canary":"can-1","sErrorCode":"0"};
+        //]]></script></head><body></body></html>"#;
+
+    const MFA: &str = r#"<html><head><script type="text/javascript">//<![CDATA[
+        $Config={"pgid":"ConvergedTFA","urlPost":"/common/SAS/ProcessAuth",
+        "urlBeginAuth":"/common/SAS/BeginAuth","urlEndAuth":"/common/SAS/EndAuth",
+        "sCtx":"ctx-2","sFT":"ft-2","canary":"can-2","arrUserProofs":[
+        {"authMethodId":"PhoneAppNotification","isDefault":true,"display":"+X XXXXXXX12"},
+        {"authMethodId":"OneWaySMS","isDefault":false,"display":"+X XXXXXXX12"}]};
+        //]]></script></head><body></body></html>"#;
+
+    const KMSI: &str = r#"<html><head><script type="text/javascript">//<![CDATA[
+        $Config={"pgid":"KmsiInterrupt","urlPost":"/kmsi","sCtx":"ctx-3","sFT":"ft-3",
+        "canary":"can-3"};
+        //]]></script></head><body></body></html>"#;
+
+    const CHANGE_PASSWORD: &str = r#"<html><head><script type="text/javascript">//<![CDATA[
+        $Config={"pgid":"ConvergedChangePassword","urlPost":"/common/SSPR/Poll"};
+        //]]></script></head><body></body></html>"#;
+
+    const SAML_RESPONSE: &str = r#"<html><body>
+        <form method="POST" name="hiddenform" action="/dana-na/auth/saml-consumer.cgi">
+        <input type="hidden" name="SAMLResponse" value="PHNhbWxwOlJlc3BvbnNlLz4=">
+        <input type="hidden" name="RelayState" value="state&amp;1">
+        </form></body></html>"#;
+
+    const SESSION_CONFLICT: &str = r#"<html><body>
+        <form name="DSIDConfirmForm" method="POST" action="/dana-na/auth/url_default/login.cgi">
+        <table>
+        <tr><td><input type="checkbox" name="postfixSID" value="sid-old"></td>
+            <td>10.0.0.7</td><td>Firefox</td></tr>
+        </table>
+        <input type="submit" name="btnContinue" value="Continue the session">
+        <input type="hidden" name="FormDataStr" value="form-data">
+        </form></body></html>"#;
+
+    #[derive(Debug, Clone)]
+    struct Request {
+        method: String,
+        path: String,
+        body: String,
+    }
+
+    struct Reply {
+        status: u16,
+        headers: Vec<(&'static str, String)>,
+        body: String,
+    }
+
+    fn page(body: &str) -> Reply {
+        Reply {
+            status: 200,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    fn redirect(location: &str, cookie: Option<&str>) -> Reply {
+        let mut headers = vec![("Location", location.to_string())];
+        if let Some(cookie) = cookie {
+            headers.push(("Set-Cookie", cookie.to_string()));
+        }
+        Reply {
+            status: 302,
+            headers,
+            body: String::new(),
+        }
+    }
+
+    /// A local HTTP server replaying recorded pages.  `routes` gets each
+    /// request and how often its path was requested before.
+    struct StandIn {
+        base: String,
+        requests: Arc<Mutex<Vec<Request>>>,
+    }
+
+    impl StandIn {
+        fn start(routes: impl Fn(&Request, usize) -> Reply + Send + 'static) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let base = format!("http://{}", listener.local_addr().unwrap());
+            let requests: Arc<Mutex<Vec<Request>>> = Arc::default();
+            let seen = Arc::clone(&requests);
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { break };
+                    let Some(request) = read_request(&stream) else {
+                        continue;
+                    };
+                    let mut seen = seen.lock().unwrap();
+                    let before = seen.iter().filter(|r| r.path == request.path).count();
+                    seen.push(request.clone());
+                    drop(seen);
+                    write_reply(stream, routes(&request, before));
+                }
+            });
+            Self { base, requests }
+        }
+
+        fn hits(&self, path: &str) -> usize {
+            self.requests().iter().filter(|r| r.path == path).count()
+        }
+
+        fn requests(&self) -> Vec<Request> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        fn body_of(&self, path: &str) -> String {
+            self.requests()
+                .into_iter()
+                .find(|r| r.path == path)
+                .map(|r| r.body)
+                .unwrap_or_default()
+        }
+    }
+
+    fn read_request(stream: &TcpStream) -> Option<Request> {
+        let mut reader = std::io::BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let mut parts = line.split_whitespace();
+        let method = parts.next()?.to_string();
+        let path = parts.next()?.split('?').next()?.to_string();
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).ok()?;
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().ok()?;
+                }
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).ok()?;
+        Some(Request {
+            method,
+            path,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+
+    fn write_reply(mut stream: TcpStream, reply: Reply) {
+        let kind = if reply.body.starts_with('{') {
+            "application/json"
+        } else {
+            "text/html; charset=utf-8"
+        };
+        let mut head = format!(
+            "HTTP/1.1 {} X\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            reply.status,
+            kind,
+            reply.body.len()
+        );
+        for (name, value) in reply.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        let _ = stream.write_all(head.as_bytes());
+        let _ = stream.write_all(reply.body.as_bytes());
+    }
+
+    /// Answers every prompt from fixed values and records what was asked.
+    #[derive(Default)]
+    struct Answers {
+        code: Option<String>,
+        choice: Option<usize>,
+        prompts: Mutex<Vec<String>>,
+        pushes: Mutex<Vec<String>>,
+    }
+
+    impl CredentialsProvider for Answers {
+        fn request_text(&self, msg: &str) -> Option<String> {
+            self.prompts.lock().unwrap().push(msg.to_string());
+            self.code.clone()
+        }
+
+        fn request_password(&self, msg: &str) -> Option<String> {
+            self.prompts.lock().unwrap().push(msg.to_string());
+            Some("hunter2".to_string())
+        }
+
+        fn request_choice(&self, msg: &str, _options: &[String]) -> Option<usize> {
+            self.prompts.lock().unwrap().push(msg.to_string());
+            self.choice
+        }
+
+        fn on_mfa_push(&self, code: &str) {
+            self.pushes.lock().unwrap().push(code.to_string());
+        }
+    }
+
+    fn login_config(server: &StandIn, mfa_preference: Vec<MfaMethod>) -> LoginConfig {
+        LoginConfig {
+            headless: true,
+            url: format!("{}/", server.base),
+            domain: "127.0.0.1".to_string(),
+            user_agent: "Mozilla/5.0".to_string(),
+            no_auto_login: false,
+            email: Some("jdoe24@ku.edu.tr".to_string()),
+            mfa_preference,
+            max_push_resends: 1,
+            lockout: LockoutPolicy::default(),
+            session_conflict: SessionConflictPolicy::CloseAll,
+            visible_fallback: VisibleFallback::Allow,
+            engine: LoginEngine::Http,
+        }
+    }
+
+    /// The gateway → Microsoft → gateway exchange, with `mfa` standing in
+    /// for the BeginAuth and EndAuth endpoints and `after_saml` for the
+    /// gateway's answer to the SAML response.
+    fn full_flow(
+        mfa: impl Fn(&Request, usize) -> Option<Reply> + Send + 'static,
+        after_saml: impl Fn(&Request, usize) -> Option<Reply> + Send + 'static,
+    ) -> StandIn {
+        StandIn::start(move |request, before| {
+            if let Some(reply) = mfa(request, before) {
+                return reply;
+            }
+            if let Some(reply) = after_saml(request, before) {
+                return reply;
+            }
+            match request.path.as_str() {
+                "/" => page(GATEWAY_SAML_REQUEST),
+                "/common/saml2" => page(SIGN_IN),
+                "/common/login" => page(MFA),
+                "/common/SAS/ProcessAuth" => page(KMSI),
+                "/kmsi" => Reply {
+                    headers: vec![(
+                        "Set-Cookie",
+                        "ESTSAUTHPERSISTENT=persisted; Max-Age=3600; Path=/".to_string(),
+                    )],
+                    ..page(SAML_RESPONSE)
+                },
+                "/dana-na/auth/saml-consumer.cgi" => redirect("/", Some("DSID=dsid-123; Path=/")),
+                other => Reply {
+                    status: 404,
+                    headers: Vec::new(),
+                    body: format!("no route for {}", other),
+                },
+            }
+        })
+    }
+
+    fn push_mfa(request: &Request, before: usize) -> Option<Reply> {
+        match request.path.as_str() {
+            "/common/SAS/BeginAuth" => Some(page(
+                r#"{"Success":true,"SessionId":"sess-1","Ctx":"ctx-b","FlowToken":"ft-b","Entropy":42}"#,
+            )),
+            "/common/SAS/EndAuth" if before == 0 => Some(page(
+                r#"{"Success":false,"ResultValue":"AuthenticationPending","FlowToken":"ft-e"}"#,
+            )),
+            "/common/SAS/EndAuth" => Some(page(
+                r#"{"Success":true,"ResultValue":"Success","Ctx":"ctx-e","FlowToken":"ft-done"}"#,
+            )),
+            _ => None,
+        }
+    }
+
+    fn no_route(_: &Request, _: usize) -> Option<Reply> {
+        None
+    }
+
+    #[test]
+    fn signs_in_with_a_push_and_returns_the_dsid() {
+        let _home = crate::utils::scratch_home();
+        let server = full_flow(push_mfa, no_route);
+        let answers = Answers::default();
+        let config = login_config(&server, vec![MfaMethod::Push]);
+
+        let dsid = run_http_login(&config, &answers, None).unwrap();
+        assert_eq!(dsid, "dsid-123");
+
+        let login = server.body_of("/common/login");
+        assert!(login.contains("loginfmt=jdoe24%40ku.edu.tr"), "{}", login);
+        assert!(login.contains("passwd=hunter2"), "{}", login);
+        assert!(login.contains("flowToken=ft-1"), "{}", login);
+        assert!(server
+            .body_of("/common/saml2")
+            .contains("RelayState=state%261"));
+        assert!(server
+            .body_of("/common/SAS/BeginAuth")
+            .contains("PhoneAppNotification"));
+        assert_eq!(server.hits("/common/SAS/BeginAuth"), 1);
+        assert_eq!(server.hits("/common/SAS/EndAuth"), 2);
+        assert!(server
+            .body_of("/common/SAS/ProcessAuth")
+            .contains("flowToken=ft-done"));
+        assert!(server.body_of("/kmsi").contains("LoginOptions=1"));
+        assert_eq!(*answers.pushes.lock().unwrap(), vec!["42".to_string()]);
+        let gateway = server.requests().into_iter().filter(|r| r.path == "/");
+        assert!(gateway.map(|r| r.method).all(|m| m == "GET"));
+
+        // The persistent Microsoft cookie is kept for next time, privately.
+        let jar = cookie_jar_path().unwrap();
+        let saved = std::fs::read_to_string(&jar).unwrap();
+        assert!(saved.contains("ESTSAUTHPERSISTENT"), "{}", saved);
+        assert!(!saved.contains("dsid-123"), "{}", saved);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&jar).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        clear_saved_cookies().unwrap();
+    }
+
+    #[test]
+    fn pending_sms_result_asks_again_without_resending() {
+        let _home = crate::utils::scratch_home();
+        let sms = |request: &Request, before: usize| match request.path.as_str() {
+            "/common/SAS/BeginAuth" => Some(page(
+                r#"{"Success":true,"SessionId":"sess-1","Ctx":"ctx-b","FlowToken":"ft-b"}"#,
+            )),
+            "/common/SAS/EndAuth" if before == 0 => {
+                Some(page(r#"{"Success":false,"ResultValue":""}"#))
+            }
+            "/common/SAS/EndAuth" => Some(page(r#"{"Success":true,"FlowToken":"ft-done"}"#)),
+            _ => None,
+        };
+        let server = full_flow(sms, no_route);
+        let answers = Answers {
+            code: Some("123456".to_string()),
+            ..Answers::default()
+        };
+        let config = login_config(&server, vec![MfaMethod::Sms]);
+
+        assert_eq!(run_http_login(&config, &answers, None).unwrap(), "dsid-123");
+        assert_eq!(server.hits("/common/SAS/BeginAuth"), 1);
+        assert_eq!(server.hits("/common/SAS/EndAuth"), 2);
+        let code_prompts = answers
+            .prompts
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|p| p.contains("verification code"))
+            .count();
+        assert_eq!(code_prompts, 2);
+        let process = server.body_of("/common/SAS/ProcessAuth");
+        assert!(process.contains("otc=123456"), "{}", process);
+        assert!(process.contains("mfaAuthMethod=OneWaySMS"), "{}", process);
+        clear_saved_cookies().unwrap();
+    }
+
+    #[test]
+    fn ends_listed_sessions_under_close_all() {
+        let _home = crate::utils::scratch_home();
+        let conflict = |request: &Request, _: usize| match request.path.as_str() {
+            "/dana-na/auth/saml-consumer.cgi" => Some(page(SESSION_CONFLICT)),
+            "/dana-na/auth/url_default/login.cgi" => {
+                Some(redirect("/", Some("DSID=dsid-456; Path=/")))
+            }
+            _ => None,
+        };
+        let server = full_flow(push_mfa, conflict);
+        let config = login_config(&server, vec![MfaMethod::Push]);
+
+        let dsid = run_http_login(&config, &Answers::default(), None).unwrap();
+        assert_eq!(dsid, "dsid-456");
+        let confirm = server.body_of("/dana-na/auth/url_default/login.cgi");
+        assert!(confirm.contains("postfixSID=sid-old"), "{}", confirm);
+        assert!(confirm.contains("btnContinue="), "{}", confirm);
+        assert!(confirm.contains("FormDataStr=form-data"), "{}", confirm);
+        clear_saved_cookies().unwrap();
+    }
+
+    #[test]
+    fn unknown_page_before_the_password_hands_over_to_the_browser() {
+        let _home = crate::utils::scratch_home();
+        let server = StandIn::start(|_, _| page("<html><body>Maintenance</body></html>"));
+        let config = login_config(&server, vec![MfaMethod::Push]);
+        let answers = Answers::default();
+
+        let err = run_http_login(&config, &answers, None).unwrap_err();
+        assert!(
+            err.downcast_ref::<HttpLoginUnsupported>().is_some(),
+            "{}",
+            err
+        );
+        assert!(answers.prompts.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn unknown_page_after_the_password_is_final() {
+        let _home = crate::utils::scratch_home();
+        let change = |request: &Request, _: usize| {
+            (request.path == "/common/login").then(|| page(CHANGE_PASSWORD))
+        };
+        let server = full_flow(change, no_route);
+        let config = login_config(&server, vec![MfaMethod::Push]);
+
+        let err = run_http_login(&config, &Answers::default(), None).unwrap_err();
+        assert!(
+            err.downcast_ref::<HttpLoginUnsupported>().is_none(),
+            "{}",
+            err
+        );
+        assert!(matches!(
+            err.downcast_ref::<AuthError>(),
+            Some(AuthError::AuthenticationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn out_of_range_proof_choice_is_an_error() {
+        let _home = crate::utils::scratch_home();
+        let server = full_flow(push_mfa, no_route);
+        let answers = Answers {
+            choice: Some(7),
+            ..Answers::default()
+        };
+        let config = login_config(&server, Vec::new());
+
+        let err = run_http_login(&config, &answers, None).unwrap_err();
+        assert!(
+            err.to_string().contains("not one of the 2 MFA methods"),
+            "{}",
+            err
+        );
+        assert_eq!(server.hits("/common/SAS/BeginAuth"), 0);
+    }
+}
@@ -4,29 +4,53 @@
 //! using a browser and establishing VPN connections via OpenConnect.
 
 pub mod browser;
+pub mod browser_discovery;
+pub mod config;
 pub mod diagnostics;
+pub mod driver;
 pub mod dsid;
 pub mod error;
 pub mod file_logger;
+pub mod gateway;
 pub mod handlers;
 #[cfg(windows)]
 pub mod helper;
 pub mod history;
+pub mod hooks;
+mod http_login;
+pub mod lockout;
 pub mod logger;
 pub mod openconnect;
+pub mod policy;
+pub mod prewarm;
+pub mod profile;
 pub mod session;
+pub mod session_info;
 pub mod utils;
+pub mod wipe;
 
 // Re-export commonly used items
-pub use dsid::{run_login_and_get_dsid, LoginConfig};
+pub use browser_discovery::{
+    discover_browsers, resolve_browser, set_browser_choice, BrowserNotFound, InstalledBrowser,
+};
+pub use config::{Config, Setting};
+pub use dsid::{
+    run_login_and_get_dsid, LoginConfig, LoginEngine, MfaMethod, SessionConflictPolicy,
+    VisibleFallback, DEFAULT_MAX_PUSH_RESENDS,
+};
 pub use error::{AuthError, ErrorCategory};
 #[cfg(windows)]
 pub use helper::run_vpn_helper_if_requested;
 pub use history::{
-    append_event, clear_events, format_duration_secs, format_timestamp_unix, load_events,
-    ConnectionEvent, EventKind,
+    append_event, clear_events, format_duration_secs, format_timestamp_unix,
+    learned_session_lifetime, load_events, ConnectionEvent, EventKind,
 };
 pub use file_logger::FileLogger;
+pub use hooks::ConnectionHooks;
+pub use lockout::{
+    clear_failures, lockout_status, LockoutPolicy, LockoutStatus, DEFAULT_LOCKOUT_COOLDOWN_MINS,
+    DEFAULT_LOCKOUT_THRESHOLD,
+};
 pub use logger::init_logger;
 #[cfg(unix)]
 pub use openconnect::{
@@ -37,5 +61,15 @@ pub use openconnect::{
     get_openconnect_pid, get_vpn_interface_name, is_openconnect_running, is_vpn_interface_up,
     kill_process, locate_openconnect, OpenConnectRunner,
 };
-pub use session::{ConnectionStatus, ParsedLog, SessionConfig, TunnelMode, VpnSession};
-pub use utils::{get_user_data_dir, has_session_data, wipe_user_data_dir};
+pub use policy::Policy;
+pub use prewarm::{discard_warm_browser, prewarm_browser};
+pub use profile::{LoginMode, Profile, ProfileStore, TunnelKind};
+pub use session::{
+    ConnectionStatus, ParsedLog, SessionConfig, TunnelMode, VpnSession, DEFAULT_EXPIRY_WARNING_MINS,
+};
+pub use session_info::{session_info, SessionInfo};
+pub use utils::{
+    get_user_data_dir, has_session_data, remove_browser_profile, set_browser_profile,
+    sweep_orphaned_browsers, wipe_user_data_dir,
+};
+pub use wipe::{wipe_session_data, WipeScope};
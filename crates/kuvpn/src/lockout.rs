@@ -0,0 +1,217 @@
+//! Failed-password guard.
+//!
+//! Records incorrect-password failures with timestamps in the user data
+//! directory so that repeated automated attempts (wrapper scripts, GUI
+//! auto-retry) cannot lock the university account.  Once `threshold`
+//! failures fall within the cooldown window, the login flow stops submitting
+//! the password until the window passes or the user explicitly re-enters it.
+
+use crate::history::now_unix;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Default for [`LockoutPolicy::threshold`].
+pub const DEFAULT_LOCKOUT_THRESHOLD: u32 = 3;
+
+/// Default for [`LockoutPolicy::cooldown`], in minutes.
+pub const DEFAULT_LOCKOUT_COOLDOWN_MINS: u64 = 15;
+
+/// At most this many failures are kept on disk.
+const MAX_RECORDED_FAILURES: usize = 20;
+
+/// When the guard kicks in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockoutPolicy {
+    /// Incorrect passwords within `cooldown` that pause automated sign-in.
+    /// `0` disables the guard.
+    pub threshold: u32,
+    /// How long a failure counts towards the threshold.
+    pub cooldown: Duration,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_LOCKOUT_THRESHOLD,
+            cooldown: Duration::from_secs(DEFAULT_LOCKOUT_COOLDOWN_MINS * 60),
+        }
+    }
+}
+
+/// An active lockout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockoutStatus {
+    /// Incorrect passwords within the cooldown window.
+    pub failures: u32,
+    /// Time until automated sign-in is allowed again.
+    pub retry_after: Duration,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FailureLog {
+    /// Unix timestamps (seconds) of incorrect-password failures, oldest first.
+    failures: Vec<u64>,
+}
+
+impl FailureLog {
+    /// Appends a failure at `ts`, dropping the oldest beyond the cap.
+    fn push(&mut self, ts: u64) {
+        self.failures.push(ts);
+        let excess = self.failures.len().saturating_sub(MAX_RECORDED_FAILURES);
+        self.failures.drain(..excess);
+    }
+
+    /// The lockout in effect at `now` under `policy`, if any.
+    fn status_at(&self, policy: &LockoutPolicy, now: u64) -> Option<LockoutStatus> {
+        if policy.threshold == 0 {
+            return None;
+        }
+        let window = policy.cooldown.as_secs();
+        let recent: Vec<u64> = self
+            .failures
+            .iter()
+            .copied()
+            .filter(|&ts| ts + window > now)
+            .collect();
+        let threshold = policy.threshold as usize;
+        if recent.len() < threshold {
+            return None;
+        }
+
+        // The lockout lifts once enough failures age out of the window to drop
+        // below the threshold.
+        let expires = recent[recent.len() - threshold] + window;
+        Some(LockoutStatus {
+            failures: recent.len() as u32,
+            retry_after: Duration::from_secs(expires.saturating_sub(now)),
+        })
+    }
+}
+
+fn failures_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(crate::utils::kuvpn_data_dir()?.join("password_failures.json"))
+}
+
+fn load() -> Result<FailureLog, Box<dyn std::error::Error>> {
+    let path = failures_path()?;
+    if !path.exists() {
+        return Ok(FailureLog::default());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    match serde_json::from_str(&content) {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            log::warn!(
+                "password_failures.json parse error (treating as empty): {}",
+                e
+            );
+            Ok(FailureLog::default())
+        }
+    }
+}
+
+/// Records one incorrect-password failure at the current time.
+pub fn record_failure() -> Result<(), Box<dyn std::error::Error>> {
+    let mut record = load()?;
+    record.push(now_unix());
+    std::fs::write(failures_path()?, serde_json::to_string_pretty(&record)?)?;
+    Ok(())
+}
+
+/// Forgets all recorded failures (after a successful login).
+pub fn clear_failures() -> Result<(), Box<dyn std::error::Error>> {
+    let path = failures_path()?;
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+    Ok(())
+}
+
+/// Returns the active lockout under `policy`, or `None` if the password may
+/// be submitted.  A missing or unreadable failure file never locks.
+pub fn lockout_status(policy: &LockoutPolicy) -> Option<LockoutStatus> {
+    if policy.threshold == 0 {
+        return None;
+    }
+    match load() {
+        Ok(record) => record.status_at(policy, now_unix()),
+        Err(e) => {
+            log::warn!("Could not read password failure history: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_000_000;
+
+    fn policy(threshold: u32, cooldown_secs: u64) -> LockoutPolicy {
+        LockoutPolicy {
+            threshold,
+            cooldown: Duration::from_secs(cooldown_secs),
+        }
+    }
+
+    fn log(failures: &[u64]) -> FailureLog {
+        FailureLog {
+            failures: failures.to_vec(),
+        }
+    }
+
+    #[test]
+    fn below_threshold_does_not_lock() {
+        let record = log(&[NOW - 10, NOW - 5]);
+        assert_eq!(record.status_at(&policy(3, 900), NOW), None);
+    }
+
+    #[test]
+    fn threshold_within_cooldown_locks() {
+        let record = log(&[NOW - 300, NOW - 200, NOW - 100]);
+        let status = record.status_at(&policy(3, 900), NOW).unwrap();
+        assert_eq!(status.failures, 3);
+        // Lifts when the oldest of the three ages out.
+        assert_eq!(status.retry_after, Duration::from_secs(600));
+    }
+
+    #[test]
+    fn failures_outside_cooldown_do_not_count() {
+        let record = log(&[NOW - 2000, NOW - 1000, NOW - 100, NOW - 50]);
+        assert_eq!(record.status_at(&policy(3, 900), NOW), None);
+        // A failure exactly one window old has aged out.
+        let record = log(&[NOW - 900, NOW - 100, NOW - 50]);
+        assert_eq!(record.status_at(&policy(3, 900), NOW), None);
+    }
+
+    #[test]
+    fn extra_failures_extend_the_lockout() {
+        let record = log(&[NOW - 400, NOW - 300, NOW - 200, NOW - 100]);
+        let status = record.status_at(&policy(3, 900), NOW).unwrap();
+        assert_eq!(status.failures, 4);
+        // Only dropping below three failures lifts it: after NOW - 300 expires.
+        assert_eq!(status.retry_after, Duration::from_secs(600));
+    }
+
+    #[test]
+    fn zero_threshold_disables_the_guard() {
+        let record = log(&[NOW - 3, NOW - 2, NOW - 1]);
+        assert_eq!(record.status_at(&policy(0, 900), NOW), None);
+    }
+
+    #[test]
+    fn push_keeps_the_newest_failures() {
+        let mut record = FailureLog::default();
+        for ts in 0..(MAX_RECORDED_FAILURES as u64 + 5) {
+            record.push(ts);
+        }
+        assert_eq!(record.failures.len(), MAX_RECORDED_FAILURES);
+        assert_eq!(record.failures[0], 5);
+        assert_eq!(
+            record.failures.last(),
+            Some(&(MAX_RECORDED_FAILURES as u64 + 4))
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! System-wide policy for managed machines.
+//!
+//! An administrator can place a `policy.toml` in `/etc/kuvpn` (on Windows
+//! `%ProgramData%\kuvpn`).  Its `[defaults]` table uses the same keys as
+//! [`Config`] and provides the starting values users can change; keys listed
+//! in `locked` are fixed to their default and cannot be changed from the
+//! config file, a profile, the environment, the command line or the GUI.
+//!
+//! ```toml
+//! locked = ["url", "tunnel-mode", "escalation-tool", "openconnect-path"]
+//!
+//! [defaults]
+//! url = "https://vpn.ku.edu.tr"
+//! tunnel-mode = "full"
+//! escalation-tool = "pkexec"
+//! openconnect-path = "/usr/sbin/openconnect"
+//! login-mode = "visual"   # a default students may still change
+//! ```
+
+use crate::config::{Config, Setting};
+use serde::Deserialize;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Defaults and locked settings read from the system policy file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    /// Values used where the user has not chosen one.
+    pub defaults: Config,
+    /// Settings fixed to their value in `defaults`.
+    pub locked: Vec<Setting>,
+}
+
+impl Policy {
+    /// Full path of the policy file.
+    pub fn path() -> PathBuf {
+        #[cfg(target_os = "windows")]
+        {
+            let base = std::env::var("ProgramData").unwrap_or_else(|_| r"C:\ProgramData".into());
+            PathBuf::from(base).join(r"kuvpn\policy.toml")
+        }
+        #[cfg(not(target_os = "windows"))]
+        {
+            PathBuf::from("/etc/kuvpn/policy.toml")
+        }
+    }
+
+    /// Reads the policy file.  A missing file is an empty policy; a file that
+    /// does not parse, or locks a setting without giving it a value, is an
+    /// error.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        Self::parse(&content).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// Parses and validates the contents of a policy file.
+    fn parse(content: &str) -> Result<Self, String> {
+        let policy: Policy = toml::from_str(content).map_err(|e| e.to_string())?;
+        let set = policy.defaults.keys();
+        if let Some(key) = policy.locked.iter().find(|k| !set.contains(k)) {
+            return Err(format!(
+                "`{}` is locked but has no value in [defaults]",
+                key.key()
+            ));
+        }
+        Ok(policy)
+    }
+
+    /// Whether `key` is fixed by the administrator.
+    pub fn is_locked(&self, key: Setting) -> bool {
+        self.locked.contains(&key)
+    }
+
+    /// The locked settings with their values.
+    pub fn locked_config(&self) -> Config {
+        self.defaults.only(&self.locked)
+    }
+
+    /// The locked settings that `requested` sets to a different value.
+    pub fn conflicts(&self, requested: &Config) -> Vec<Setting> {
+        let locked = self.locked_config();
+        requested
+            .keys()
+            .into_iter()
+            .filter(|&k| self.is_locked(k) && requested.only(&[k]) != locked.only(&[k]))
+            .collect()
+    }
+
+    /// The locked value of `key` as a `config.toml` line, for messages.
+    pub fn locked_value(&self, key: Setting) -> String {
+        toml::to_string(&self.locked_config().only(&[key]))
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::{LoginMode, TunnelKind};
+
+    fn policy() -> Policy {
+        Policy::parse(
+            r#"
+            locked = ["url", "tunnel-mode"]
+
+            [defaults]
+            url = "https://vpn.example.com"
+            tunnel-mode = "full"
+            login-mode = "visual"
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn locked_config_keeps_only_locked_values() {
+        let policy = policy();
+        assert!(policy.is_locked(Setting::Url));
+        assert!(!policy.is_locked(Setting::LoginMode));
+        let locked = policy.locked_config();
+        assert_eq!(locked.keys(), vec![Setting::Url, Setting::TunnelMode]);
+        assert_eq!(locked.url.as_deref(), Some("https://vpn.example.com"));
+    }
+
+    #[test]
+    fn different_locked_values_conflict() {
+        let requested = Config {
+            url: Some("https://other.example.com".into()),
+            tunnel_mode: Some(TunnelKind::Full),
+            login_mode: Some(LoginMode::Manual),
+            ..Config::default()
+        };
+        assert_eq!(policy().conflicts(&requested), vec![Setting::Url]);
+    }
+
+    #[test]
+    fn matching_or_unlocked_values_do_not_conflict() {
+        let requested = Config {
+            url: Some("https://vpn.example.com".into()),
+            login_mode: Some(LoginMode::Manual),
+            ..Config::default()
+        };
+        assert!(policy().conflicts(&requested).is_empty());
+        assert!(policy().conflicts(&Config::default()).is_empty());
+        assert!(Policy::default().conflicts(&requested).is_empty());
+    }
+
+    #[test]
+    fn locked_value_is_a_config_line() {
+        assert_eq!(
+            policy().locked_value(Setting::TunnelMode),
+            "tunnel-mode = \"full\""
+        );
+    }
+
+    #[test]
+    fn locking_a_setting_needs_a_value() {
+        let err = Policy::parse("locked = [\"email\"]").unwrap_err();
+        assert!(err.contains("`email` is locked"), "{}", err);
+        assert!(Policy::parse("unknown = 1").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Keeping a headless browser ready for the next login.
+//!
+//! Launching Chrome is the slowest part of connecting.  [`prewarm_browser`]
+//! does it ahead of time, in the background: it opens the login profile in
+//! headless Chrome, loads the gateway URL and leaves the page idle until
+//! [`run_login_and_get_dsid`](crate::run_login_and_get_dsid) takes it over, or
+//! until it has gone unused for the given idle time.
+//!
+//! Only one browser can hold a profile, so launching another browser or
+//! deleting a profile first discards the warm one
+//! ([`discard_warm_browser`]).  Reading the profile's cookies for
+//! [`crate::session_info`] goes through the warm browser instead
+//! ([`with_warm_tab`]).  Only headless Full Auto logins on the browser
+//! engine use a warm browser; for the others nothing is pre-warmed.
+
+use crate::dsid::{may_show_window, BrowserSession, LoginConfig, LoginEngine};
+use headless_chrome::Tab;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// A warm page older than this is reloaded before the login, as the
+/// sign-in request it holds may have expired.
+const MAX_PAGE_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// What a warm browser was opened for; a login with other settings does not
+/// use it.
+#[derive(PartialEq, Eq)]
+struct WarmKey {
+    url: String,
+    user_agent: String,
+    profile: Option<String>,
+    browser: Option<String>,
+    /// Whether the browser has a hidden window it can show.
+    window: bool,
+}
+
+impl WarmKey {
+    fn for_config(config: &LoginConfig) -> Self {
+        Self {
+            url: config.url.clone(),
+            user_agent: config.user_agent.clone(),
+            profile: crate::utils::browser_profile(),
+            browser: crate::browser_discovery::browser_choice(),
+            window: may_show_window(config),
+        }
+    }
+}
+
+struct Warm {
+    session: BrowserSession,
+    key: WarmKey,
+    loaded_at: Instant,
+    generation: u64,
+}
+
+enum Slot {
+    Empty,
+    /// A browser is being launched by the pre-warm with this generation.
+    Warming(u64),
+    Ready(Warm),
+}
+
+static SLOT: Mutex<Slot> = Mutex::new(Slot::Empty);
+/// Signalled whenever the slot leaves [`Slot::Warming`].
+static SETTLED: Condvar = Condvar::new();
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn lock_slot() -> std::sync::MutexGuard<'static, Slot> {
+    SLOT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Locks the slot once no browser is being launched for it.
+fn settled_slot() -> std::sync::MutexGuard<'static, Slot> {
+    SETTLED
+        .wait_while(lock_slot(), |s| matches!(s, Slot::Warming(_)))
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether logins with `config` can use a warm browser.
+fn can_use_warm(config: &LoginConfig) -> bool {
+    config.headless && !config.no_auto_login && config.engine == LoginEngine::Browser
+}
+
+/// Launches a headless browser for `config` in the background and loads the
+/// gateway URL, so the next login can start right away.  The browser is
+/// closed once it has gone unused for `idle`.
+///
+/// Does nothing when `config` is not a headless Full Auto login on the
+/// browser engine, or when a browser is already warm for it.
+pub fn prewarm_browser(config: &LoginConfig, idle: Duration) {
+    if !can_use_warm(config) {
+        log::debug!("Not pre-warming the browser: only headless Full Auto logins use it");
+        return;
+    }
+    let key = WarmKey::for_config(config);
+    let generation = GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+    let stale = {
+        let mut slot = lock_slot();
+        match &*slot {
+            Slot::Warming(_) => return,
+            Slot::Ready(warm) if warm.key == key => return,
+            _ => {}
+        }
+        std::mem::replace(&mut *slot, Slot::Warming(generation))
+    };
+    if let Slot::Ready(warm) = stale {
+        warm.session.close();
+    }
+
+    let config = config.clone();
+    std::thread::spawn(move || {
+        log::info!("Pre-warming the browser for {}", config.url);
+        let opened = BrowserSession::open_warm(&config, idle).and_then(|session| {
+            session.tab.0.navigate(&config.url)?;
+            Ok(session)
+        });
+
+        let mut slot = lock_slot();
+        let current = matches!(*slot, Slot::Warming(g) if g == generation);
+        let leftover = match opened {
+            Ok(session) if current => {
+                *slot = Slot::Ready(Warm {
+                    session,
+                    key,
+                    loaded_at: Instant::now(),
+                    generation,
+                });
+                None
+            }
+            Ok(session) => Some(session),
+            Err(e) => {
+                log::warn!("Could not pre-warm the browser: {}", e);
+                if current {
+                    *slot = Slot::Empty;
+                }
+                None
+            }
+        };
+        drop(slot);
+        SETTLED.notify_all();
+        if let Some(session) = leftover {
+            session.close();
+            return;
+        }
+
+        std::thread::sleep(idle);
+        let expired = {
+            let mut slot = lock_slot();
+            match &*slot {
+                Slot::Ready(warm) if warm.generation == generation => {
+                    std::mem::replace(&mut *slot, Slot::Empty)
+                }
+                _ => Slot::Empty,
+            }
+        };
+        if let Slot::Ready(warm) = expired {
+            log::info!(
+                "Pre-warmed browser unused for {} min, closing it",
+                idle.as_secs() / 60
+            );
+            warm.session.close();
+        }
+    });
+}
+
+/// Takes the warm browser over for a login with `config`, waiting for one
+/// that is still launching.  Returns the session and whether its page is
+/// fresh enough to continue on; a warm browser for other settings, or one
+/// that has died, is closed instead.
+pub(crate) fn take_warm_browser(config: &LoginConfig) -> Option<(BrowserSession, bool)> {
+    let warm = match std::mem::replace(&mut *settled_slot(), Slot::Empty) {
+        Slot::Ready(warm) => warm,
+        _ => return None,
+    };
+    let usable = can_use_warm(config)
+        && warm.key == WarmKey::for_config(config)
+        && warm.session.tab.0.evaluate("1").is_ok();
+    if !usable {
+        log::info!("Closing the pre-warmed browser, it does not fit this login");
+        warm.session.close();
+        return None;
+    }
+    Some((warm.session, warm.loaded_at.elapsed() < MAX_PAGE_AGE))
+}
+
+/// Runs `f` on the page of the warm browser, waiting for one that is still
+/// launching, so reading the profile does not cost the warm browser.
+/// Returns `None` when no browser is warm for the selected profile.
+pub(crate) fn with_warm_tab<T>(f: impl FnOnce(&Tab) -> T) -> Option<T> {
+    match &*settled_slot() {
+        Slot::Ready(warm) if warm.key.profile == crate::utils::browser_profile() => {
+            Some(f(warm.session.raw_tab()))
+        }
+        _ => None,
+    }
+}
+
+/// Closes the pre-warmed browser, if any, waiting for one that is still
+/// launching.  Call this before exiting so the browser does not outlive the
+/// app.
+pub fn discard_warm_browser() {
+    let stale = std::mem::replace(&mut *settled_slot(), Slot::Empty);
+    if let Slot::Ready(warm) = stale {
+        log::info!("Closing the pre-warmed browser");
+        warm.session.close();
+    }
+}
@@ -0,0 +1,208 @@
+//! Named connection profiles.
+//!
+//! A profile bundles the settings that differ between gateways or accounts —
+//! URL, DSID domain, email, login and tunnel mode, escalation tool, interface
+//! name and hooks — so several setups can be kept side by side and picked by
+//! name.  Profiles are stored in `profiles.json` in the user data directory,
+//! shared by the CLI and the GUI.  Each profile also gets its own browser
+//! profile directory, so the sign-ins of different accounts are kept apart
+//! (see [`crate::utils::set_browser_profile`]).
+
+use crate::hooks::ConnectionHooks;
+use crate::session::{SessionConfig, TunnelMode};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::PathBuf;
+
+/// How much of the login is automated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LoginMode {
+    /// Headless browser, fully automated.
+    #[default]
+    FullAuto,
+    /// Visible browser, automation still runs.
+    Visual,
+    /// Visible browser, no automation; the user completes the login.
+    Manual,
+}
+
+impl LoginMode {
+    /// Derives the mode from the `headless` / `no_auto_login` pair used by
+    /// [`SessionConfig`].
+    pub fn from_flags(headless: bool, no_auto_login: bool) -> Self {
+        match (headless, no_auto_login) {
+            (_, true) => LoginMode::Manual,
+            (true, false) => LoginMode::FullAuto,
+            (false, false) => LoginMode::Visual,
+        }
+    }
+
+    /// Whether the browser runs headlessly.
+    pub fn headless(&self) -> bool {
+        matches!(self, LoginMode::FullAuto)
+    }
+
+    /// Whether automatic login handlers are disabled.
+    pub fn no_auto_login(&self) -> bool {
+        matches!(self, LoginMode::Manual)
+    }
+}
+
+/// How traffic is routed, without the script path (see [`TunnelMode`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TunnelKind {
+    /// All traffic is routed through the tunnel.
+    #[default]
+    Full,
+    /// The profile's `vpnc_script` sets up routing.
+    Manual,
+}
+
+/// A named set of connection settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub url: String,
+    pub domain: String,
+    pub email: Option<String>,
+    pub login_mode: LoginMode,
+    pub tunnel_mode: TunnelKind,
+    /// Script passed to openconnect in [`TunnelKind::Manual`] mode.
+    pub vpnc_script: Option<String>,
+    /// `sudo`, `pkexec`, … (`None` auto-detects).
+    pub escalation_tool: Option<String>,
+    pub interface_name: String,
+    pub hooks: ConnectionHooks,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+            url: "https://vpn.ku.edu.tr".to_string(),
+            domain: "vpn.ku.edu.tr".to_string(),
+            email: None,
+            login_mode: LoginMode::default(),
+            tunnel_mode: TunnelKind::default(),
+            vpnc_script: None,
+            escalation_tool: None,
+            interface_name: "kuvpn0".to_string(),
+            hooks: ConnectionHooks::default(),
+        }
+    }
+}
+
+impl Profile {
+    /// Captures the profile-covered settings of `config` under `name`.
+    pub fn from_session_config(name: impl Into<String>, config: &SessionConfig) -> Self {
+        let (tunnel_mode, vpnc_script) = match &config.tunnel_mode {
+            TunnelMode::Full => (TunnelKind::Full, None),
+            TunnelMode::Manual(script) => (TunnelKind::Manual, script.clone()),
+        };
+        Self {
+            name: name.into(),
+            url: config.url.clone(),
+            domain: config.domain.clone(),
+            email: config.email.clone(),
+            login_mode: LoginMode::from_flags(config.headless, config.no_auto_login),
+            tunnel_mode,
+            vpnc_script,
+            escalation_tool: config.escalation_tool.clone(),
+            interface_name: config.interface_name.clone(),
+            hooks: config.hooks.clone(),
+        }
+    }
+
+    /// The tunnel mode in the form [`SessionConfig`] takes.
+    pub fn session_tunnel_mode(&self) -> TunnelMode {
+        match self.tunnel_mode {
+            TunnelKind::Full => TunnelMode::Full,
+            TunnelKind::Manual => TunnelMode::Manual(self.vpnc_script.clone()),
+        }
+    }
+
+    /// Overwrites the profile-covered settings of `config`.
+    pub fn apply(&self, config: &mut SessionConfig) {
+        config.url = self.url.clone();
+        config.domain = self.domain.clone();
+        config.email = self.email.clone();
+        config.headless = self.login_mode.headless();
+        config.no_auto_login = self.login_mode.no_auto_login();
+        config.tunnel_mode = self.session_tunnel_mode();
+        config.escalation_tool = self.escalation_tool.clone();
+        config.interface_name = self.interface_name.clone();
+        config.hooks = self.hooks.clone();
+    }
+}
+
+/// All saved profiles, plus the one last selected in the GUI.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileStore {
+    #[serde(default)]
+    pub active: Option<String>,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+}
+
+fn store_path() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(crate::utils::kuvpn_data_dir()?.join("profiles.json"))
+}
+
+impl ProfileStore {
+    /// Loads the saved profiles; a missing or unreadable file yields an
+    /// empty store.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let path = store_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        match serde_json::from_str(&content) {
+            Ok(v) => Ok(v),
+            Err(e) => {
+                log::warn!("profiles.json parse error (treating as empty): {}", e);
+                Ok(Self::default())
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        std::fs::write(store_path()?, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// Profile names, in the order they were added.
+    pub fn names(&self) -> Vec<String> {
+        self.profiles.iter().map(|p| p.name.clone()).collect()
+    }
+
+    /// The profile named by `active`, if it still exists.
+    pub fn active_profile(&self) -> Option<&Profile> {
+        self.active.as_deref().and_then(|name| self.get(name))
+    }
+
+    /// Adds `profile`, replacing any profile with the same name.
+    pub fn upsert(&mut self, profile: Profile) {
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    /// Removes the profile named `name`.  Returns `false` if there was none.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.profiles.len();
+        self.profiles.retain(|p| p.name != name);
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+        }
+        self.profiles.len() != before
+    }
+}
@@ -1,4 +1,4 @@
-use crate::dsid::{run_login_and_get_dsid, LoginConfig};
+use crate::dsid::{run_login_and_get_dsid, LoginConfig, SessionConflictPolicy};
 use crate::openconnect::{
     get_openconnect_pid, is_openconnect_running, is_vpn_interface_up, kill_process,
     OpenConnectRunner, VpnProcess,
@@ -66,6 +66,16 @@ pub enum TunnelMode {
     Manual(Option<String>),
 }
 
+/// Default for [`SessionConfig::expiry_warning`], in minutes.
+pub const DEFAULT_EXPIRY_WARNING_MINS: u64 = 10;
+
+/// How long before the session lifetime ends a renewal is started.
+const RENEW_LEAD: Duration = Duration::from_secs(3 * 60);
+
+/// How long before the session lifetime ends the browser is pre-warmed,
+/// leaving it time to start before a renewal.
+const PREWARM_LEAD: Duration = Duration::from_secs(5 * 60);
+
 #[derive(Clone)]
 pub struct SessionConfig {
     pub url: String,
@@ -81,6 +91,52 @@ pub struct SessionConfig {
     /// On Unix: Split/Full generate a vpnc-script at runtime; Manual passes a user path.
     /// On Windows: only Full/Split distinction is used (no script support).
     pub tunnel_mode: TunnelMode,
+    /// MFA methods to pick, in order, when Microsoft offers a choice.
+    pub mfa_preference: Vec<crate::dsid::MfaMethod>,
+    /// How many times an unanswered push or call is re-sent before giving up.
+    pub max_push_resends: u32,
+    /// When to stop submitting the password after repeated failures.
+    pub lockout: crate::lockout::LockoutPolicy,
+    /// What to do with existing gateway sessions on the session-conflict page.
+    pub session_conflict: crate::dsid::SessionConflictPolicy,
+    /// Whether a headless login that needs the user may show its browser.
+    pub visible_fallback: crate::dsid::VisibleFallback,
+    /// Engine used for headless automated logins.
+    pub login_engine: crate::dsid::LoginEngine,
+    /// How long the gateway keeps a session before expiring it.  `None` uses
+    /// the lifetime learned from connection history, if there is one.
+    pub max_session_lifetime: Option<Duration>,
+    /// How long before the lifetime ends to warn; zero disables the warning.
+    pub expiry_warning: Duration,
+    /// Log in again in the background shortly before the lifetime ends and
+    /// restart the tunnel with the fresh DSID.
+    pub renew_before_expiry: bool,
+    /// Launch a headless browser in the background shortly before the
+    /// lifetime ends, so the next login starts faster, and close it after
+    /// this long unused.  `None` disables the pre-warm.
+    pub prewarm_idle: Option<Duration>,
+    /// Commands run when the tunnel comes up or goes down.
+    pub hooks: crate::hooks::ConnectionHooks,
+}
+
+impl SessionConfig {
+    /// The settings for a login of this session.
+    pub fn login_config(&self) -> LoginConfig {
+        LoginConfig {
+            headless: self.headless,
+            url: self.url.clone(),
+            domain: self.domain.clone(),
+            user_agent: self.user_agent.clone(),
+            no_auto_login: self.no_auto_login,
+            email: self.email.clone(),
+            mfa_preference: self.mfa_preference.clone(),
+            max_push_resends: self.max_push_resends,
+            lockout: self.lockout,
+            session_conflict: self.session_conflict,
+            visible_fallback: self.visible_fallback,
+            engine: self.login_engine,
+        }
+    }
 }
 
 /// Prompts for the sudo/pkexec password if the chosen escalation tool requires
@@ -154,6 +210,47 @@ fn spawn_stream_reader<R: Read + Send + 'static>(
     });
 }
 
+/// Logs in again for a fresh DSID on its own thread, so the watchdog keeps
+/// monitoring the current tunnel meanwhile.  The channel yields the new DSID,
+/// or `None` if the login failed; a failure only logs a warning, since the
+/// old session keeps working until it expires.
+fn spawn_renewal(
+    config: LoginConfig,
+    provider: Arc<dyn CredentialsProvider>,
+    cancel_token: CancellationToken,
+    browser_pid: Arc<Mutex<Option<u32>>>,
+    logs_tx: Arc<Mutex<Option<crossbeam_channel::Sender<String>>>>,
+) -> crossbeam_channel::Receiver<Option<String>> {
+    let (tx, rx) = crossbeam_channel::bounded(1);
+    thread::spawn(move || {
+        let result = run_login_and_get_dsid(
+            &config,
+            provider.as_ref(),
+            Some(cancel_token.clone()),
+            Some(Arc::clone(&browser_pid)),
+        );
+        *browser_pid.lock().expect("session mutex poisoned") = None;
+        let dsid = match result {
+            Ok(dsid) => Some(dsid),
+            Err(e) => {
+                if !cancel_token.is_cancelled() {
+                    if let Some(tx) = logs_tx.lock().expect("session mutex poisoned").as_ref() {
+                        let _ = tx.send(format!("Warn|Could not renew the VPN session: {}", e));
+                    }
+                }
+                None
+            }
+        };
+        if let Err(crossbeam_channel::SendError(Some(dsid))) = tx.send(dsid) {
+            // The watchdog stopped meanwhile; do not leave the new session open.
+            if let Err(e) = crate::gateway::logout(&config.url, &dsid) {
+                log::warn!("Gateway logout of the unused renewed session failed: {}", e);
+            }
+        }
+    });
+    rx
+}
+
 struct SessionThread {
     config: SessionConfig,
     status: Arc<Mutex<ConnectionStatus>>,
@@ -164,6 +261,13 @@ struct SessionThread {
     browser_pid: Arc<Mutex<Option<u32>>>,
     /// Tracks when the VPN connected so we can record session duration.
     connected_at: Option<Instant>,
+    /// DSID of the gateway session opened by the last login, logged out on
+    /// disconnect so the gateway does not keep it around.
+    dsid: Option<String>,
+    /// Gateway session lifetime in effect, resolved when the thread starts.
+    session_lifetime: Option<Duration>,
+    /// When the current gateway session is expected to expire.
+    expires_at: Arc<Mutex<Option<Instant>>>,
     /// Cached sudo/pkexec password resolved during `start_openconnect`.
     #[cfg(unix)]
     sudo_password: Option<String>,
@@ -184,6 +288,9 @@ impl SessionThread {
             logs_tx: Arc::clone(&s.logs_tx),
             browser_pid: Arc::clone(&s.browser_pid),
             connected_at: None,
+            dsid: None,
+            session_lifetime: None,
+            expires_at: Arc::clone(&s.expires_at),
             #[cfg(unix)]
             sudo_password: None,
             #[cfg(unix)]
@@ -214,6 +321,33 @@ impl SessionThread {
         *self.browser_pid.lock().expect("session mutex poisoned") = None;
     }
 
+    /// Starts counting the session lifetime for a freshly obtained DSID.
+    fn start_session_clock(&self) {
+        *self.expires_at.lock().expect("session mutex poisoned") =
+            self.session_lifetime.map(|l| Instant::now() + l);
+    }
+
+    fn expires_in(&self) -> Option<Duration> {
+        self.expires_at
+            .lock()
+            .expect("session mutex poisoned")
+            .map(|t| t.saturating_duration_since(Instant::now()))
+    }
+
+    /// Resolves the lifetime from the config, falling back to the one learned
+    /// from connection history.
+    fn resolve_session_lifetime(&mut self) {
+        self.session_lifetime = self.config.max_session_lifetime.or_else(|| {
+            let events = crate::history::load_events().ok()?;
+            let secs = crate::history::learned_session_lifetime(&events)?;
+            self.send_log(format!(
+                "Debug|Gateway session lifetime learned from history: {}",
+                crate::history::format_duration_secs(secs)
+            ));
+            Some(Duration::from_secs(secs))
+        });
+    }
+
     fn handle_login_error(&self, e: anyhow::Error) {
         let mut status = self.status.lock().expect("session mutex poisoned");
         if *status == ConnectionStatus::Disconnecting {
@@ -237,16 +371,19 @@ impl SessionThread {
         // to the next Reconnected history event so history shows how long each
         // segment lasted before dropping.
         let mut prev_duration: Option<u64> = None;
+        self.resolve_session_lifetime();
 
         loop {
             let is_reconnect = attempt > 0;
             let dropped_after = if self.is_vpn_connected() {
                 self.send_log("Info|VPN interface already active, monitoring...");
                 self.set_status(ConnectionStatus::Connected);
-                self.run_watchdog(None, is_reconnect, prev_duration)
+                self.run_watchdog(None, &provider, is_reconnect, prev_duration)
             } else {
                 match self.launch_vpn(&provider) {
-                    Ok(Some(proc)) => self.run_watchdog(Some(proc), is_reconnect, prev_duration),
+                    Ok(Some(proc)) => {
+                        self.run_watchdog(Some(proc), &provider, is_reconnect, prev_duration)
+                    }
                     Ok(None) => None, // user cancelled
                     Err(_) => None,   // auth/launch error
                 }
@@ -313,23 +450,16 @@ impl SessionThread {
         self.send_log("Info|Initializing tunnel...");
         thread::sleep(Duration::from_millis(100));
 
+        self.dsid = Some(dsid.clone());
+        self.start_session_clock();
         let mut proc = self.start_openconnect(dsid, provider)?;
         self.spawn_log_readers(&mut proc);
         Ok(Some(proc))
     }
 
     fn acquire_dsid(&self, provider: &Arc<dyn CredentialsProvider>) -> Result<String, ()> {
-        let login_config = LoginConfig {
-            headless: self.config.headless,
-            url: self.config.url.clone(),
-            domain: self.config.domain.clone(),
-            user_agent: self.config.user_agent.clone(),
-            no_auto_login: self.config.no_auto_login,
-            email: self.config.email.clone(),
-        };
-
         let result = run_login_and_get_dsid(
-            &login_config,
+            &self.config.login_config(),
             provider.as_ref(),
             Some(self.cancel_token.clone()),
             Some(Arc::clone(&self.browser_pid)),
@@ -347,6 +477,74 @@ impl SessionThread {
         result.map_err(|e| self.handle_login_error(e))
     }
 
+    /// Starts logging in again for a fresh DSID while the current tunnel
+    /// stays up (see [`spawn_renewal`]).  Sessions already open on the
+    /// gateway are kept whatever the configured policy, since one of them is
+    /// the live tunnel.
+    fn start_renewal(
+        &self,
+        provider: &Arc<dyn CredentialsProvider>,
+    ) -> crossbeam_channel::Receiver<Option<String>> {
+        self.send_log("Info|Renewing the VPN session before it expires...");
+        let config = LoginConfig {
+            session_conflict: SessionConflictPolicy::KeepAll,
+            ..self.config.login_config()
+        };
+        spawn_renewal(
+            config,
+            Arc::clone(provider),
+            self.cancel_token.clone(),
+            Arc::clone(&self.browser_pid),
+            Arc::clone(&self.logs_tx),
+        )
+    }
+
+    /// Switches over to the DSID a renewal login returned: stops the tunnel
+    /// and logs out the session it was using.  A login that only handed back
+    /// the current session, as it does while the browser profile still holds
+    /// its cookie, counts as a failed renewal and leaves the tunnel alone.
+    /// Returns the DSID to restart the tunnel with.
+    fn switch_to_renewed(
+        &mut self,
+        dsid: String,
+        process: &mut Option<VpnProcess>,
+    ) -> Option<String> {
+        if self.dsid.as_deref() == Some(dsid.as_str()) {
+            self.send_log(
+                "Warn|Could not renew the VPN session: the login returned the current session",
+            );
+            return None;
+        }
+
+        self.set_status(ConnectionStatus::Connecting);
+        self.send_log("Info|Restarting the tunnel with the renewed session...");
+        if let Some(p) = process {
+            let _ = p.kill();
+            let _ = p.wait();
+        }
+        if let Some(old) = self.dsid.replace(dsid.clone()).filter(|old| *old != dsid) {
+            if let Err(e) = crate::gateway::logout(&self.config.url, &old) {
+                log::warn!("Gateway logout of the old session failed: {}", e);
+            }
+        }
+        Some(dsid)
+    }
+
+    /// Reports a tunnel that could not be restarted after a renewal stopped
+    /// the old one.  `start_openconnect` may already have set a more specific
+    /// error.
+    fn restart_failed(&mut self) {
+        if *self.status.lock().expect("session mutex poisoned") != ConnectionStatus::Error {
+            self.set_conn_error("Could not restart the tunnel with the renewed session");
+        }
+        if let Some(cmd) = &self.config.hooks.on_disconnect {
+            crate::hooks::run_hook(cmd, "disconnect", &self.config.interface_name);
+        }
+        // Lets `cleanup` record the error in history rather than a normal
+        // disconnect.
+        self.connected_at = None;
+    }
+
     /// Resolves the vpnc-script path for this session.
     /// On Unix: generates a temp script for Full mode or returns the Manual path.
     /// On non-Unix: always returns `None` (no script support).
@@ -491,15 +689,24 @@ impl SessionThread {
     /// and then died unexpectedly (eligible for reconnect; duration = how long it
     /// was connected this cycle).  Returns `None` for user-initiated cancellations
     /// and launch-phase errors.
+    ///
+    /// When the gateway session lifetime is known, warns before it runs out
+    /// and, if enabled, swaps in a freshly logged-in session shortly before.
     fn run_watchdog(
         &mut self,
         mut process: Option<VpnProcess>,
-        is_reconnect: bool,
-        prev_duration: Option<u64>,
+        provider: &Arc<dyn CredentialsProvider>,
+        mut is_reconnect: bool,
+        mut prev_duration: Option<u64>,
     ) -> Option<u64> {
-        let start_time = Instant::now();
+        let mut start_time = Instant::now();
         let mut connected_detected = process.is_none();
         let timeout = Duration::from_secs(30);
+        let mut expiry_warned = false;
+        let mut renew_attempted = false;
+        let mut renewal: Option<crossbeam_channel::Receiver<Option<String>>> = None;
+        let mut renewed = false;
+        let mut prewarmed = false;
 
         // Windows: separate the UAC-waiting phase from the connection-establishment
         // phase so the 30-second connection timeout doesn't fire while the user is
@@ -566,6 +773,9 @@ impl SessionThread {
                     self.connected_at = Some(Instant::now());
                     self.set_status(ConnectionStatus::Connected);
                     self.send_log("Info|Connected.");
+                    if let Some(cmd) = &self.config.hooks.on_connect {
+                        crate::hooks::run_hook(cmd, "connect", &self.config.interface_name);
+                    }
 
                     let kind = if is_reconnect {
                         crate::history::EventKind::Reconnected
@@ -576,8 +786,72 @@ impl SessionThread {
                     // For reconnects, record how long the previous segment lasted
                     // before it dropped so history shows meaningful durations.
                     event.duration_secs = prev_duration;
+                    if renewed {
+                        event.message = Some("session renewed".to_string());
+                    }
                     let _ = crate::history::append_event(&event);
                 }
+
+                let remaining = self.expires_in();
+                let warning = self.config.expiry_warning;
+                if let Some(remaining) = remaining.filter(|&r| !warning.is_zero() && r <= warning) {
+                    if !expiry_warned {
+                        expiry_warned = true;
+                        self.send_log(format!(
+                            "Warn|Session expires in {} min — the gateway will end it then.",
+                            remaining.as_secs().div_ceil(60)
+                        ));
+                    }
+                }
+                if let Some(idle) = self.config.prewarm_idle {
+                    if !prewarmed && remaining.is_some_and(|r| r <= PREWARM_LEAD) {
+                        prewarmed = true;
+                        crate::prewarm::prewarm_browser(&self.config.login_config(), idle);
+                    }
+                }
+                let renew_due = remaining.is_some_and(|r| r <= RENEW_LEAD);
+                if self.config.renew_before_expiry && renew_due && !renew_attempted {
+                    renew_attempted = true;
+                    renewal = Some(self.start_renewal(provider));
+                }
+                // The renewal login runs on its own thread; pick up its DSID
+                // once it is done.
+                let finished = match renewal.as_ref().map(|r| r.try_recv()) {
+                    None | Some(Err(crossbeam_channel::TryRecvError::Empty)) => None,
+                    Some(result) => Some(result.ok().flatten()),
+                };
+                if let Some(result) = finished {
+                    renewal = None;
+                    let fresh = result.and_then(|d| self.switch_to_renewed(d, &mut process));
+                    if let Some(dsid) = fresh {
+                        // The old tunnel is down; bring up a new one on the
+                        // fresh DSID.
+                        prev_duration = self.connected_at.map(|t| t.elapsed().as_secs());
+                        self.start_session_clock();
+                        let mut proc = match self.start_openconnect(dsid, provider) {
+                            Ok(proc) => proc,
+                            Err(()) => {
+                                self.restart_failed();
+                                return None;
+                            }
+                        };
+                        self.spawn_log_readers(&mut proc);
+                        process = Some(proc);
+                        connected_detected = false;
+                        is_reconnect = true;
+                        renewed = true;
+                        expiry_warned = false;
+                        renew_attempted = false;
+                        prewarmed = false;
+                        start_time = Instant::now();
+                        #[cfg(windows)]
+                        {
+                            connect_phase_start = None;
+                            uac_logged = false;
+                        }
+                        continue;
+                    }
+                }
             } else if connected_detected {
                 // Tunnel was up and just went down unexpectedly.
                 if let Some(ref mut p) = process {
@@ -661,7 +935,7 @@ impl SessionThread {
         }
     }
 
-    fn cleanup(&self, reconnect_attempts: u32) {
+    fn cleanup(&mut self, reconnect_attempts: u32) {
         // Windows: the stop-file signal was already sent; give the elevated helper
         // up to 5 s to kill openconnect before falling back to UAC-elevated taskkill.
         // In the normal case OC dies within ~200 ms and the loop exits immediately.
@@ -677,6 +951,16 @@ impl SessionThread {
             thread::sleep(Duration::from_millis(500));
         }
 
+        // Killing openconnect leaves the session open on the gateway; end it
+        // explicitly so the next login does not hit the session-conflict page.
+        *self.expires_at.lock().expect("session mutex poisoned") = None;
+        if let Some(dsid) = self.dsid.take() {
+            match crate::gateway::logout(&self.config.url, &dsid) {
+                Ok(()) => log::info!("Logged out of the gateway session"),
+                Err(e) => log::warn!("Gateway logout failed: {}", e),
+            }
+        }
+
         let duration_secs = self.connected_at.map(|t| t.elapsed().as_secs());
 
         if is_openconnect_running() {
@@ -690,6 +974,9 @@ impl SessionThread {
             // Was connected at some point — record a normal disconnect.
             self.set_status(ConnectionStatus::Disconnected);
             self.send_log("Info|Disconnected.");
+            if let Some(cmd) = &self.config.hooks.on_disconnect {
+                crate::hooks::run_hook(cmd, "disconnect", &self.config.interface_name);
+            }
             let mut event =
                 crate::history::ConnectionEvent::now(crate::history::EventKind::Disconnected);
             event.duration_secs = duration_secs;
@@ -725,6 +1012,7 @@ pub struct VpnSession {
     error_category: Arc<Mutex<Option<crate::error::ErrorCategory>>>,
     logs_tx: Arc<Mutex<Option<crossbeam_channel::Sender<String>>>>,
     browser_pid: Arc<Mutex<Option<u32>>>,
+    expires_at: Arc<Mutex<Option<Instant>>>,
 }
 
 impl VpnSession {
@@ -737,6 +1025,7 @@ impl VpnSession {
             error_category: Arc::new(Mutex::new(None)),
             logs_tx: Arc::new(Mutex::new(None)),
             browser_pid: Arc::new(Mutex::new(None)),
+            expires_at: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -781,6 +1070,15 @@ impl VpnSession {
         *self.error_category.lock().expect("session mutex poisoned")
     }
 
+    /// Time left until the gateway is expected to expire the current session,
+    /// or `None` when the session lifetime is unknown or no session is open.
+    pub fn expires_in(&self) -> Option<Duration> {
+        self.expires_at
+            .lock()
+            .expect("session mutex poisoned")
+            .map(|t| t.saturating_duration_since(Instant::now()))
+    }
+
     /// Returns true if the session has reached a terminal state.
     pub fn is_finished(&self) -> bool {
         let s = self.status();
@@ -812,3 +1110,99 @@ impl VpnSession {
         thread::spawn(move || thread.run(provider))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    /// A session thread for `url` whose current gateway session is `dsid`,
+    /// with the receiving end of its log channel.
+    fn session_thread(
+        url: &str,
+        dsid: &str,
+    ) -> (SessionThread, crossbeam_channel::Receiver<String>) {
+        let session = VpnSession::new(SessionConfig {
+            url: url.to_string(),
+            domain: "127.0.0.1".to_string(),
+            user_agent: "Mozilla/5.0".to_string(),
+            headless: true,
+            no_auto_login: false,
+            email: None,
+            openconnect_path: "openconnect".to_string(),
+            escalation_tool: None,
+            interface_name: "kuvpn0".to_string(),
+            tunnel_mode: TunnelMode::Full,
+            mfa_preference: Vec::new(),
+            max_push_resends: 0,
+            lockout: Default::default(),
+            session_conflict: Default::default(),
+            visible_fallback: Default::default(),
+            login_engine: Default::default(),
+            max_session_lifetime: None,
+            expiry_warning: Duration::ZERO,
+            renew_before_expiry: true,
+            prewarm_idle: None,
+            hooks: Default::default(),
+        });
+        let (tx, rx) = crossbeam_channel::unbounded();
+        session.set_logs_tx(tx);
+        let mut thread = SessionThread::from_session(&session);
+        thread.dsid = Some(dsid.to_string());
+        (thread, rx)
+    }
+
+    #[test]
+    fn renewal_returning_the_current_session_keeps_the_tunnel() {
+        let gateway = TcpListener::bind("127.0.0.1:0").unwrap();
+        gateway.set_nonblocking(true).unwrap();
+        let url = format!("http://{}", gateway.local_addr().unwrap());
+        let (mut thread, logs) = session_thread(&url, "current");
+        *thread.status.lock().unwrap() = ConnectionStatus::Connected;
+
+        assert_eq!(
+            thread.switch_to_renewed("current".to_string(), &mut None),
+            None
+        );
+        assert_eq!(thread.dsid.as_deref(), Some("current"));
+        assert_eq!(*thread.status.lock().unwrap(), ConnectionStatus::Connected);
+        let warning = logs.try_recv().unwrap();
+        assert!(warning.starts_with("Warn|Could not renew"), "{}", warning);
+        // The live session was not logged out.
+        assert!(gateway.accept().is_err());
+    }
+
+    #[test]
+    fn renewal_with_a_new_session_logs_out_the_old_one() {
+        let gateway = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", gateway.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = gateway.accept().unwrap();
+            let mut request = String::new();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            while reader.read_line(&mut request).unwrap() > 2 {}
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            request
+        });
+        let (mut thread, _logs) = session_thread(&url, "old");
+
+        assert_eq!(
+            thread
+                .switch_to_renewed("new".to_string(), &mut None)
+                .as_deref(),
+            Some("new")
+        );
+        assert_eq!(thread.dsid.as_deref(), Some("new"));
+        assert_eq!(*thread.status.lock().unwrap(), ConnectionStatus::Connecting);
+        let request = server.join().unwrap();
+        assert!(
+            request.starts_with("GET /dana-na/auth/logout.cgi "),
+            "{}",
+            request
+        );
+        assert!(request.contains("DSID=old"), "{}", request);
+    }
+}
@@ -0,0 +1,136 @@
+//! Freshness of the saved browser session.
+//!
+//! Reports whether the profile still holds a persistent Microsoft sign-in
+//! (so the next login can skip MFA), together with the times of the last
+//! DSID and the last login that went through MFA.  The timestamps are
+//! recorded by the login flow; the cookies are read from the profile through
+//! the pre-warmed browser if there is one (see [`crate::prewarm`]), else a
+//! short-lived headless browser.  Both belong to the browser profile
+//! selected with [`crate::utils::set_browser_profile`].
+
+use crate::browser::with_profile_tab;
+use crate::history::now_unix;
+use crate::wipe::{domain_matches, MICROSOFT_SSO_DOMAINS};
+use headless_chrome::protocol::cdp::Network;
+use headless_chrome::Tab;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Name of the persistent Microsoft sign-in cookie ("Stay signed in").
+const PERSISTENT_SSO_COOKIE: &str = "ESTSAUTHPERSISTENT";
+
+/// Name of the gateway session cookie.
+const DSID_COOKIE: &str = "DSID";
+
+/// What is known about the saved session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// Whether the profile directory holds any data at all.
+    pub has_profile: bool,
+    /// Expiry (Unix seconds) of the persistent Microsoft sign-in cookie, or
+    /// `None` when the profile has none.
+    pub sso_expires: Option<u64>,
+    /// Whether a DSID cookie for the gateway is stored in the profile.
+    pub has_dsid_cookie: bool,
+    /// When a DSID was last obtained (Unix seconds).
+    pub last_dsid: Option<u64>,
+    /// When a login last went through an MFA step (Unix seconds).
+    pub last_mfa: Option<u64>,
+}
+
+impl SessionInfo {
+    /// Whether the persistent sign-in is present and not yet expired, i.e.
+    /// the next login is expected to need no MFA.
+    pub fn sso_valid(&self) -> bool {
+        self.sso_expires.is_some_and(|t| t > now_unix())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LoginRecord {
+    #[serde(default)]
+    last_dsid: Option<u64>,
+    #[serde(default)]
+    last_mfa: Option<u64>,
+}
+
+/// The default profile keeps its record in the data directory, where older
+/// releases put it; a named profile keeps it in its own profile directory.
+fn record_path() -> Result<PathBuf, Box<dyn Error>> {
+    let dir = match crate::utils::browser_profile() {
+        None => crate::utils::kuvpn_data_dir()?,
+        Some(_) => crate::utils::get_user_data_dir()?,
+    };
+    Ok(dir.join("session_state.json"))
+}
+
+fn load_record() -> Result<LoginRecord, Box<dyn Error>> {
+    let path = record_path()?;
+    if !path.exists() {
+        return Ok(LoginRecord::default());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    match serde_json::from_str(&content) {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            log::warn!("session_state.json parse error (treating as empty): {}", e);
+            Ok(LoginRecord::default())
+        }
+    }
+}
+
+/// Records that a DSID was obtained now, and that the login went through an
+/// MFA step if `mfa` is set.
+pub(crate) fn record_login(mfa: bool) -> Result<(), Box<dyn Error>> {
+    let mut record = load_record()?;
+    let now = now_unix();
+    record.last_dsid = Some(now);
+    if mfa {
+        record.last_mfa = Some(now);
+    }
+    std::fs::write(record_path()?, serde_json::to_string_pretty(&record)?)?;
+    Ok(())
+}
+
+/// Inspects the saved session.  `gateway_domain` is the DSID cookie domain
+/// (e.g. `vpn.ku.edu.tr`).
+///
+/// Opens the profile in a headless browser when it holds data, so it must
+/// not run while a login browser is open on the same profile.
+pub fn session_info(gateway_domain: &str) -> Result<SessionInfo, Box<dyn Error>> {
+    let record = load_record()?;
+    let mut info = SessionInfo {
+        has_profile: crate::utils::has_session_data(),
+        last_dsid: record.last_dsid,
+        last_mfa: record.last_mfa,
+        ..SessionInfo::default()
+    };
+    if !info.has_profile {
+        return Ok(info);
+    }
+
+    let read = |tab: &Tab| -> Result<Vec<Network::Cookie>, Box<dyn Error>> {
+        Ok(tab.call_method(Network::GetAllCookies(None))?.cookies)
+    };
+    let cookies = match crate::prewarm::with_warm_tab(read) {
+        Some(Ok(cookies)) => cookies,
+        Some(Err(e)) => {
+            log::debug!("Could not read cookies through the warm browser: {}", e);
+            with_profile_tab(read)?
+        }
+        None => with_profile_tab(read)?,
+    };
+    for cookie in cookies {
+        let is_sso = MICROSOFT_SSO_DOMAINS
+            .iter()
+            .any(|d| domain_matches(&cookie.domain, d));
+        if is_sso && cookie.name == PERSISTENT_SSO_COOKIE && !cookie.session {
+            let expires = cookie.expires.max(0.0) as u64;
+            info.sso_expires = Some(info.sso_expires.map_or(expires, |t| t.max(expires)));
+        } else if cookie.name == DSID_COOKIE && domain_matches(&cookie.domain, gateway_domain) {
+            info.has_dsid_cookie = true;
+        }
+    }
+    Ok(info)
+}
@@ -1,8 +1,8 @@
 use console::{Style, Term};
-use dialoguer::{Input, Password};
+use dialoguer::{Confirm, Input, Password, Select};
 use std::env;
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
@@ -36,7 +36,40 @@ pub trait CredentialsProvider: Send + Sync {
     fn request_email(&self, msg: &str) -> Option<String> {
         self.request_text(msg)
     }
+    /// Ask the user to pick one of `options`; returns the chosen index.
+    /// The default lists the options and reads their number via `request_text`,
+    /// re-asking until the answer is in range.
+    fn request_choice(&self, msg: &str, options: &[String]) -> Option<usize> {
+        let mut prompt = msg.trim_end_matches(": ").to_string();
+        for (i, option) in options.iter().enumerate() {
+            prompt.push_str(&format!("\n  {}. {}", i + 1, option));
+        }
+        prompt.push_str("\nNumber: ");
+        loop {
+            let answer = self.request_text(&prompt)?;
+            match answer.trim().parse::<usize>() {
+                Ok(n) if (1..=options.len()).contains(&n) => return Some(n - 1),
+                _ => log::warn!("'{}' is not one of the listed numbers", answer.trim()),
+            }
+        }
+    }
     fn on_mfa_push(&self, _code: &str) {}
+    /// Called when Microsoft is phoning the user to approve the sign-in.
+    /// `on_mfa_complete` follows once the call page goes away.
+    fn on_mfa_call(&self) {}
+    /// Called each time an unanswered push or call is sent again
+    /// (`attempt` counts from 1 up to `max`).
+    fn on_mfa_resend(&self, _attempt: u32, _max: u32) {}
+    /// Called when `page` (e.g. Terms of Use) has to be finished by the user
+    /// in the browser window.  `on_mfa_complete` follows once it goes away.
+    fn on_browser_action(&self, _page: &str) {}
+    /// Called when the lockout guard holds back the password after `failures`
+    /// recent incorrect attempts.  Returns `true` only if the user explicitly
+    /// chose to type the password again now; the default refuses, so
+    /// unattended providers wait for the cooldown.
+    fn confirm_password_retry(&self, _failures: u32, _retry_after: std::time::Duration) -> bool {
+        false
+    }
     fn on_mfa_complete(&self) {}
 
     /// Install a guard that is polled while a prompt is visible.
@@ -75,6 +108,12 @@ impl CredentialsProvider for TerminalCredentialsProvider {
         )
     }
 
+    fn request_choice(&self, msg: &str, options: &[String]) -> Option<usize> {
+        let term = Term::stderr();
+        let _ = term.clear_line();
+        prompt_choice(msg, options)
+    }
+
     fn on_mfa_push(&self, code: &str) {
         let bold = Style::new().bold();
         let cyan = Style::new().cyan().bold();
@@ -87,12 +126,74 @@ impl CredentialsProvider for TerminalCredentialsProvider {
         eprintln!();
     }
 
+    fn on_mfa_call(&self) {
+        let bold = Style::new().bold();
+        eprintln!();
+        eprintln!(
+            "{} Answer the phone call to approve the sign-in",
+            bold.apply_to(">>")
+        );
+        eprintln!();
+    }
+
+    fn on_mfa_resend(&self, attempt: u32, max: u32) {
+        let yellow = Style::new().yellow();
+        eprintln!(
+            "  {} No response — sending another request ({}/{})",
+            yellow.apply_to("~"),
+            attempt,
+            max
+        );
+    }
+
+    fn on_browser_action(&self, page: &str) {
+        let bold = Style::new().bold();
+        eprintln!();
+        eprintln!(
+            "{} {} needs your input — finish it in the browser window",
+            bold.apply_to(">>"),
+            page
+        );
+        eprintln!();
+    }
+
+    fn confirm_password_retry(&self, failures: u32, retry_after: std::time::Duration) -> bool {
+        let term = Term::stderr();
+        let _ = term.clear_line();
+        prompt_password_retry(failures, retry_after)
+    }
+
     fn on_mfa_complete(&self) {
         let green = Style::new().green();
         eprintln!("  {} MFA approved", green.apply_to("✓"));
     }
 }
 
+/// Terminal prompt behind [`CredentialsProvider::request_choice`]: lets the
+/// user pick one of `options` with the arrow keys.
+pub fn prompt_choice(msg: &str, options: &[String]) -> Option<usize> {
+    Select::new()
+        .with_prompt(msg.trim_end_matches(": ").trim_end_matches(':'))
+        .items(options)
+        .default(0)
+        .interact_opt()
+        .ok()
+        .flatten()
+}
+
+/// Terminal prompt behind [`CredentialsProvider::confirm_password_retry`].
+pub fn prompt_password_retry(failures: u32, retry_after: std::time::Duration) -> bool {
+    Confirm::new()
+        .with_prompt(format!(
+            "{} recent incorrect passwords. Re-enter the password now instead of waiting {}?",
+            failures,
+            crate::history::format_duration_secs(retry_after.as_secs())
+        ))
+        .default(false)
+        .interact()
+        .unwrap_or(false)
+}
+
 use fd_lock::{RwLock, RwLockWriteGuard};
 use once_cell::sync::Lazy;
 use std::fs::File;
@@ -102,27 +203,74 @@ use std::sync::Mutex;
 static INSTANCE_LOCK: Lazy<Mutex<Option<RwLockWriteGuard<'static, File>>>> =
     Lazy::new(|| Mutex::new(None));
 
-/// Platform-relative path from the home directory to the kuvpn profile directory.
+/// Platform-relative path from the home directory to the kuvpn data directory.
 #[cfg(target_os = "linux")]
-const PROFILE_SUBPATH: &str = ".local/share/kuvpn/profile";
+const DATA_SUBPATH: &str = ".local/share/kuvpn";
 
 #[cfg(target_os = "macos")]
-const PROFILE_SUBPATH: &str = "Library/Application Support/kuvpn/profile";
+const DATA_SUBPATH: &str = "Library/Application Support/kuvpn";
 
 #[cfg(target_os = "windows")]
-const PROFILE_SUBPATH: &str = "AppData/Roaming/kuvpn/profile";
+const DATA_SUBPATH: &str = "AppData/Roaming/kuvpn";
 
-/// Returns the profile directory path without creating it.
-fn profile_dir_path() -> Result<PathBuf, Box<dyn Error>> {
+/// The connection profile whose browser data later logins use; `None` for
+/// the shared default profile directory.
+static BROWSER_PROFILE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Selects the browser profile directory for this process: the one of the
+/// connection profile `name`, or the default one for `None`.  Each profile
+/// keeps its own Microsoft sign-in, so switching accounts needs no wipe.
+pub fn set_browser_profile(name: Option<String>) {
+    *BROWSER_PROFILE.lock().unwrap_or_else(|e| e.into_inner()) = name;
+}
+
+/// The profile last given to [`set_browser_profile`].
+pub(crate) fn browser_profile() -> Option<String> {
+    BROWSER_PROFILE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+fn data_dir_path() -> Result<PathBuf, Box<dyn Error>> {
     let home_dir = env::var("HOME").or_else(|_| env::var("USERPROFILE"))?;
-    Ok(PathBuf::from(&home_dir).join(PROFILE_SUBPATH))
+    Ok(PathBuf::from(&home_dir).join(DATA_SUBPATH))
+}
+
+/// Directory name for profile `name`: characters that are not safe in a
+/// file name are written as `%XX`, so different names never share a directory.
+fn profile_dir_name(name: &str) -> String {
+    let mut out = String::new();
+    for (i, b) in name.bytes().enumerate() {
+        let safe = b.is_ascii_alphanumeric() || b"-_ ".contains(&b) || (b == b'.' && i > 0);
+        if safe {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// Browser profile directory of connection profile `name` (`None` for the
+/// default one), without creating it.
+fn profile_dir_for(name: Option<&str>) -> Result<PathBuf, Box<dyn Error>> {
+    let data_dir = data_dir_path()?;
+    Ok(match name {
+        None => data_dir.join("profile"),
+        Some(name) => data_dir.join("profiles").join(profile_dir_name(name)),
+    })
+}
+
+/// Returns the profile directory path without creating it.
+pub(crate) fn profile_dir_path() -> Result<PathBuf, Box<dyn Error>> {
+    profile_dir_for(browser_profile().as_deref())
 }
 
 /// Returns the kuvpn application data directory (`…/kuvpn/`), creating it if absent.
-/// This is the parent of the profile directory and contains history, lock files, etc.
+/// This holds the profile directories, history, lock files, etc.
 pub(crate) fn kuvpn_data_dir() -> Result<PathBuf, Box<dyn Error>> {
-    let mut dir = profile_dir_path()?;
-    dir.pop(); // …/kuvpn/profile → …/kuvpn/
+    let dir = data_dir_path()?;
     std::fs::create_dir_all(&dir)?;
     Ok(dir)
 }
@@ -145,12 +293,116 @@ pub fn ensure_single_instance() -> Result<(), Box<dyn Error>> {
     }
 }
 
+/// Kills browsers left running on a kuvpn browser profile, e.g. by an
+/// earlier kuvpn that crashed mid-login, and removes stale `SingletonLock`s.
+/// Chrome will not open a profile whose lock names a live browser, so the
+/// next login would fail on it and wipe the profile in the retry.
+///
+/// Call at startup, after [`ensure_single_instance`], as every browser on
+/// the profiles is then an orphan.  Does nothing on Windows, where Chrome
+/// keeps no `SingletonLock`.
+pub fn sweep_orphaned_browsers() {
+    #[cfg(unix)]
+    {
+        let Ok(data_dir) = data_dir_path() else {
+            return;
+        };
+        let named = std::fs::read_dir(data_dir.join("profiles"))
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|e| e.path()));
+        for dir in std::iter::once(data_dir.join("profile")).chain(named) {
+            sweep_profile_lock(&dir);
+        }
+    }
+}
+
+/// Kills the browser holding the `SingletonLock` of profile directory `dir`,
+/// if it is running here on that profile, and removes the lock.
+#[cfg(unix)]
+fn sweep_profile_lock(dir: &Path) {
+    use nix::sys::signal::kill;
+    use nix::unistd::{gethostname, Pid};
+
+    let lock = dir.join("SingletonLock");
+    // Chrome points the lock at `<hostname>-<pid>`.
+    let Ok(target) = std::fs::read_link(&lock) else {
+        return;
+    };
+    let Some((host, pid)) = target
+        .to_str()
+        .and_then(|t| t.rsplit_once('-'))
+        .and_then(|(host, pid)| Some((host.to_string(), pid.parse::<u32>().ok()?)))
+    else {
+        return;
+    };
+    // A browser on another machine sharing the home directory is not ours.
+    if gethostname().ok().and_then(|h| h.into_string().ok()) != Some(host) {
+        return;
+    }
+
+    if browser_holds_profile(pid, dir) {
+        log::warn!(
+            "Killing orphaned browser (PID {}) still holding {:?}",
+            pid,
+            dir
+        );
+        crate::openconnect::kill_browser_process(pid);
+        let alive = || kill(Pid::from_raw(pid as i32), None).is_ok();
+        for _ in 0..20 {
+            if !alive() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+    } else {
+        log::info!("Removing stale browser lock in {:?}", dir);
+    }
+    if let Err(e) = std::fs::remove_file(&lock) {
+        log::warn!("Could not remove {:?}: {}", lock, e);
+    }
+}
+
+/// Whether process `pid` is a browser started on profile directory `dir`.
+#[cfg(unix)]
+fn browser_holds_profile(pid: u32, dir: &Path) -> bool {
+    let flag = format!("--user-data-dir={}", dir.display());
+
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read(format!("/proc/{}/cmdline", pid))
+            .map(|cmdline| cmdline.split(|&b| b == 0).any(|arg| arg == flag.as_bytes()))
+            .unwrap_or(false)
+    }
+
+    // No /proc: ask `ps`, whose output joins the arguments with spaces.
+    #[cfg(not(target_os = "linux"))]
+    {
+        std::process::Command::new("ps")
+            .args(["-ww", "-o", "command=", "-p", &pid.to_string()])
+            .output()
+            .map(|out| {
+                let command = String::from_utf8_lossy(&out.stdout);
+                command.trim_end().match_indices(&flag).any(|(i, _)| {
+                    matches!(
+                        command.trim_end()[i + flag.len()..].chars().next(),
+                        None | Some(' ')
+                    )
+                })
+            })
+            .unwrap_or(false)
+    }
+}
+
 /// Returns a platform-appropriate user data directory for the Chrome profile.
 ///
 /// - **Linux:** `~/.local/share/kuvpn/profile`
 /// - **macOS:** `~/Library/Application Support/kuvpn/profile`
 /// - **Windows:** `%USERPROFILE%\AppData\Roaming\kuvpn\profile`
 ///
+/// With a connection profile selected (see [`set_browser_profile`]) it is
+/// `profiles/<name>` next to that instead.
+///
 /// Creates the directory if it does not already exist.
 pub fn get_user_data_dir() -> Result<PathBuf, Box<dyn Error>> {
     let user_data_dir = profile_dir_path()?;
@@ -175,17 +427,44 @@ pub fn has_session_data() -> bool {
 /// Removes the profile directory. Does not create it first.
 /// Uses path-component checks to guard against wiping unrelated directories.
 pub fn wipe_user_data_dir() -> Result<(), Box<dyn Error>> {
-    let path = profile_dir_path()?;
+    remove_profile_dir(&profile_dir_path()?)
+}
+
+/// Removes the browser profile directory of the deleted connection profile
+/// `name`.  Does nothing if it has none.
+pub fn remove_browser_profile(name: &str) -> Result<(), Box<dyn Error>> {
+    remove_profile_dir(&profile_dir_for(Some(name))?)
+}
+
+fn remove_profile_dir(path: &Path) -> Result<(), Box<dyn Error>> {
+    crate::prewarm::discard_warm_browser();
     if !path.components().any(|c| c.as_os_str() == "kuvpn") {
         return Err("Refusing to wipe: path does not appear to be a kuvpn directory".into());
     }
     if path.exists() {
-        std::fs::remove_dir_all(&path)?;
+        std::fs::remove_dir_all(path)?;
         log::info!("Wiped profile directory: {:?}", path);
     }
     Ok(())
 }
 
+/// Points `HOME` at a scratch directory for the rest of the test run, so
+/// tests never touch the real data directory, and serialises the tests that
+/// use it.  Hold the guard for the whole test.
+#[cfg(test)]
+pub(crate) fn scratch_home() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    static HOME: std::sync::Once = std::sync::Once::new();
+    let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    HOME.call_once(|| {
+        let dir = env::temp_dir().join(format!("kuvpn-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create scratch home");
+        env::set_var("HOME", &dir);
+        env::set_var("USERPROFILE", &dir);
+    });
+    guard
+}
+
 /// Escapes JavaScript strings to prevent injection.
 pub fn js_escape(s: &str) -> String {
     s.replace('\\', "\\\\")
@@ -194,3 +473,46 @@ pub fn js_escape(s: &str) -> String {
         .replace('\r', "\\r")
         .replace('\t', "\\t")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_dir_name_keeps_plain_names() {
+        assert_eq!(profile_dir_name("work"), "work");
+        assert_eq!(profile_dir_name("Lab VPN_2-b.old"), "Lab VPN_2-b.old");
+    }
+
+    #[test]
+    fn profile_dir_name_escapes_path_characters() {
+        assert_eq!(profile_dir_name("a/b"), "a%2Fb");
+        assert_eq!(profile_dir_name("a\\b"), "a%5Cb");
+        assert_eq!(profile_dir_name("c:d"), "c%3Ad");
+        assert_eq!(profile_dir_name("."), "%2E");
+        assert_eq!(profile_dir_name(".."), "%2E.");
+        assert_eq!(profile_dir_name(".hidden"), "%2Ehidden");
+        assert_eq!(profile_dir_name("ü"), "%C3%BC");
+    }
+
+    #[test]
+    fn profile_dir_name_never_maps_two_names_to_one_directory() {
+        let names = [
+            "a/b", "a%2Fb", "a_b", "a b", "ab", ".a", "%2Ea", "a.", "ü", "%C3%BC",
+        ];
+        let dirs: std::collections::HashSet<_> =
+            names.iter().map(|n| profile_dir_name(n)).collect();
+        assert_eq!(dirs.len(), names.len());
+    }
+
+    #[test]
+    fn named_profiles_live_under_profiles() {
+        let _home = scratch_home();
+        let data = data_dir_path().unwrap();
+        assert_eq!(profile_dir_for(None).unwrap(), data.join("profile"));
+        assert_eq!(
+            profile_dir_for(Some("../work")).unwrap(),
+            data.join("profiles").join("%2E.%2Fwork")
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Selective clearing of the saved browser session.
+//!
+//! Deleting the whole profile directory also throws away the Microsoft SSO
+//! cookies, so the next login has to go through MFA again.  The scopes here
+//! open the profile in a headless browser and clear only what was asked for
+//! through CDP, which is usually enough to recover from a stale gateway
+//! session.  Only the selected browser profile is touched (see
+//! [`crate::utils::set_browser_profile`]).
+
+use crate::browser::with_profile_tab;
+use headless_chrome::protocol::cdp::Network;
+use headless_chrome::Tab;
+use std::error::Error;
+
+/// Cookie domains of the Microsoft sign-in (SSO) session.
+pub(crate) const MICROSOFT_SSO_DOMAINS: &[&str] = &[
+    "login.microsoftonline.com",
+    "login.microsoft.com",
+    "login.live.com",
+    "account.live.com",
+];
+
+/// What to clear from the saved browser session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WipeScope {
+    /// Cookies of the VPN gateway (`DSID`, `DSFirstAccess`, …).  Keeps the
+    /// Microsoft sign-in, so the next login usually needs no MFA.
+    Gateway,
+    /// Cookies of the Microsoft sign-in, forcing a full login with MFA.
+    Sso,
+    /// The HTTP cache only; no cookies are touched.
+    Cache,
+    /// The whole profile directory (see [`crate::utils::wipe_user_data_dir`]).
+    All,
+}
+
+impl WipeScope {
+    /// Every scope, in the order they are listed in UIs.
+    pub const ALL: [WipeScope; 4] = [
+        WipeScope::Gateway,
+        WipeScope::Sso,
+        WipeScope::Cache,
+        WipeScope::All,
+    ];
+
+    /// Short identifier used in CLI flags and logs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WipeScope::Gateway => "gateway",
+            WipeScope::Sso => "sso",
+            WipeScope::Cache => "cache",
+            WipeScope::All => "all",
+        }
+    }
+}
+
+impl std::fmt::Display for WipeScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            WipeScope::Gateway => "gateway session",
+            WipeScope::Sso => "Microsoft sign-in",
+            WipeScope::Cache => "browser cache",
+            WipeScope::All => "all session data",
+        })
+    }
+}
+
+/// Returns `true` if `cookie_domain` (as reported by CDP, possibly with a
+/// leading dot) is `domain` or one of its subdomains.
+pub(crate) fn domain_matches(cookie_domain: &str, domain: &str) -> bool {
+    let cookie_domain = cookie_domain.trim_start_matches('.');
+    cookie_domain.eq_ignore_ascii_case(domain)
+        || cookie_domain
+            .to_ascii_lowercase()
+            .ends_with(&format!(".{}", domain.to_ascii_lowercase()))
+}
+
+/// Clears `scope` from the saved browser session.  `gateway_domain` is the
+/// DSID cookie domain (e.g. `vpn.ku.edu.tr`) and is only used by
+/// [`WipeScope::Gateway`].
+///
+/// [`WipeScope::Sso`] also removes the HTTP login engine's cookie file, which
+/// holds its Microsoft sign-in.
+///
+/// Must not run while a login browser is open on the same profile.
+pub fn wipe_session_data(scope: WipeScope, gateway_domain: &str) -> Result<(), Box<dyn Error>> {
+    if scope == WipeScope::All {
+        return crate::utils::wipe_user_data_dir();
+    }
+    if scope == WipeScope::Sso {
+        crate::http_login::clear_saved_cookies()?;
+    }
+    if !crate::utils::has_session_data() {
+        log::info!("No saved session data, nothing to clear");
+        return Ok(());
+    }
+
+    with_profile_tab(|tab| clear(tab, scope, gateway_domain))
+}
+
+fn clear(tab: &Tab, scope: WipeScope, gateway_domain: &str) -> Result<(), Box<dyn Error>> {
+    if scope == WipeScope::Cache {
+        tab.call_method(Network::ClearBrowserCache(None))?;
+        log::info!("Cleared the browser cache");
+        return Ok(());
+    }
+
+    let cookies = tab.call_method(Network::GetAllCookies(None))?.cookies;
+    let mut removed = 0;
+    for cookie in cookies {
+        let matches = match scope {
+            WipeScope::Gateway => domain_matches(&cookie.domain, gateway_domain),
+            _ => MICROSOFT_SSO_DOMAINS
+                .iter()
+                .any(|d| domain_matches(&cookie.domain, d)),
+        };
+        if !matches {
+            continue;
+        }
+        tab.call_method(Network::DeleteCookies {
+            name: cookie.name,
+            url: None,
+            domain: Some(cookie.domain),
+            path: Some(cookie.path),
+            partition_key: None,
+        })?;
+        removed += 1;
+    }
+    log::info!("Cleared {} cookie(s) of the {}", removed, scope);
+    Ok(())
+}